@@ -0,0 +1,7 @@
+; A heading folds everything up to the next heading of its level.
+[
+  (section)
+  (fenced_code_block)
+  (list)
+  (block_quote)
+] @fold
@@ -0,0 +1,24 @@
+; Items, blocks and anything else bracketed that spans lines.
+[
+  (mod_item)
+  (foreign_mod_item)
+  (trait_item)
+  (impl_item)
+  (struct_item)
+  (enum_item)
+  (union_item)
+  (function_item)
+  (macro_definition)
+  (block)
+  (match_block)
+  (declaration_list)
+  (field_declaration_list)
+  (enum_variant_list)
+  (field_initializer_list)
+  (use_declaration)
+  (token_tree)
+  (arguments)
+  (array_expression)
+  (closure_expression)
+  (block_comment)
+] @fold
@@ -0,0 +1,11 @@
+; Doc comments are markdown whose untagged code fences are Rust. Each `///`
+; line is its own comment, so the lines of a block are parsed together to
+; keep code fences whole.
+((line_comment
+  (doc_comment) @injection.content)
+ (#set! injection.language "rustdoc")
+ (#set! injection.combined))
+
+((block_comment
+  (doc_comment) @injection.content)
+ (#set! injection.language "rustdoc"))
@@ -0,0 +1,9 @@
+; Rustdoc takes code fences without a language to be Rust.
+((fenced_code_block
+  .
+  (fenced_code_block_delimiter)
+  .
+  (block_continuation)?
+  .
+  (code_fence_content) @injection.content)
+ (#set! injection.language "rust"))
@@ -0,0 +1,238 @@
+use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use unicode_width::UnicodeWidthStr;
+
+/// An ex command such as `:vsplit` or `:q!`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    pub name: String,
+    pub bang: bool,
+    pub args: Vec<String>,
+}
+
+impl Command {
+    pub fn parse(text: &str) -> Option<Command> {
+        let mut words = text.split_whitespace();
+        let head = words.next()?;
+        let (name, bang) = match head.strip_suffix('!') {
+            Some(name) => (name, true),
+            None => (head, false),
+        };
+        Some(Command {
+            name: name.to_string(),
+            bang,
+            args: words.map(|w| w.to_string()).collect(),
+        })
+    }
+}
+
+pub enum CommandLineEvent {
+    Submit(Command),
+    Cancel,
+    None,
+}
+
+/// The `:` prompt shown in the footer.
+#[derive(Default)]
+pub struct CommandLine {
+    active: bool,
+    input: String,
+    history: Vec<String>,
+    history_idx: Option<usize>,
+}
+
+impl CommandLine {
+    pub fn open(&mut self) {
+        self.active = true;
+        self.input.clear();
+        self.history_idx = None;
+    }
+
+    /// Opens with `text` already typed, e.g. a command waiting for its
+    /// argument.
+    pub fn open_with(&mut self, text: &str) {
+        self.open();
+        self.input.push_str(text);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> CommandLineEvent {
+        let Event::Key(key) = event else {
+            return CommandLineEvent::None;
+        };
+        if key.kind != KeyEventKind::Press {
+            return CommandLineEvent::None;
+        }
+        match key.code {
+            KeyCode::Esc => {
+                self.active = false;
+                CommandLineEvent::Cancel
+            }
+            KeyCode::Enter => {
+                self.active = false;
+                let text = std::mem::take(&mut self.input);
+                if !text.trim().is_empty() {
+                    self.history.retain(|h| *h != text);
+                    self.history.push(text.clone());
+                }
+                match Command::parse(&text) {
+                    Some(command) => CommandLineEvent::Submit(command),
+                    None => CommandLineEvent::Cancel,
+                }
+            }
+            KeyCode::Backspace if self.input.is_empty() => {
+                self.active = false;
+                CommandLineEvent::Cancel
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                CommandLineEvent::None
+            }
+            KeyCode::Up => {
+                self.browse_history(true);
+                CommandLineEvent::None
+            }
+            KeyCode::Down => {
+                self.browse_history(false);
+                CommandLineEvent::None
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                CommandLineEvent::None
+            }
+            _ => CommandLineEvent::None,
+        }
+    }
+
+    fn browse_history(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() - 1;
+        self.history_idx = match (self.history_idx, older) {
+            (None, true) => Some(last),
+            (Some(0), true) => Some(0),
+            (Some(i), true) => Some(i - 1),
+            (Some(i), false) if i < last => Some(i + 1),
+            (_, false) => None,
+        };
+        self.input = match self.history_idx {
+            Some(i) => self.history[i].clone(),
+            None => String::new(),
+        };
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title("command");
+        let inner = block.inner(area);
+        frame.render_widget(
+            Paragraph::new(format!(":{}", self.input)).block(block),
+            area,
+        );
+        let x = inner.x + 1 + self.input.width() as u16;
+        frame.set_cursor_position(Position::new(
+            x.min(inner.right().saturating_sub(1)),
+            inner.y,
+        ));
+    }
+}
+
+pub enum PromptEvent {
+    Choose(char),
+    Cancel,
+    None,
+}
+
+/// A question answered with a single key, like Vim's swap file prompt.
+pub struct Prompt {
+    message: String,
+    choices: Vec<(char, String)>,
+    cancel: String,
+}
+
+impl Prompt {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            choices: vec![],
+            cancel: "cancel".to_string(),
+        }
+    }
+
+    /// Labels what Esc does, "cancel" unless set.
+    pub fn cancel(mut self, label: impl Into<String>) -> Self {
+        self.cancel = label.into();
+        self
+    }
+
+    pub fn choice(mut self, key: char, label: impl Into<String>) -> Self {
+        self.choices.push((key, label.into()));
+        self
+    }
+
+    pub fn handle_event(&self, event: &Event) -> PromptEvent {
+        let Event::Key(key) = event else {
+            return PromptEvent::None;
+        };
+        if key.kind != KeyEventKind::Press {
+            return PromptEvent::None;
+        }
+        match key.code {
+            KeyCode::Esc => PromptEvent::Cancel,
+            KeyCode::Char(c) => {
+                let c = c.to_ascii_lowercase();
+                match self.choices.iter().any(|(k, _)| *k == c) {
+                    true => PromptEvent::Choose(c),
+                    false => PromptEvent::None,
+                }
+            }
+            _ => PromptEvent::None,
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        let choices: Vec<String> = self
+            .choices
+            .iter()
+            .map(|(key, label)| format!("[{key}] {label}"))
+            .chain([format!("[Esc] {}", self.cancel)])
+            .collect();
+        let text = vec![
+            Line::from(self.message.as_str()),
+            Line::from(choices.join("  "))
+                .style(Style::new().fg(Color::Yellow)),
+        ];
+        frame.render_widget(
+            Paragraph::new(text)
+                .wrap(Wrap { trim: true })
+                .block(Block::bordered().title("prompt")),
+            area,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bang_and_args() {
+        assert_eq!(
+            Command::parse(" qa! "),
+            Some(Command { name: "qa".into(), bang: true, args: vec![] })
+        );
+        assert_eq!(
+            Command::parse("rename new_name"),
+            Some(Command {
+                name: "rename".into(),
+                bang: false,
+                args: vec!["new_name".into()]
+            })
+        );
+        assert_eq!(Command::parse("  "), None);
+    }
+}
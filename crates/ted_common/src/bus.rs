@@ -0,0 +1,254 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Rounds of dispatch per call. Anything published after that waits for the
+/// next call, so handlers that keep answering each other can't hang the UI.
+const MAX_ROUNDS: usize = 16;
+
+/// Something one component tells whoever is interested.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// Asks for a file to be opened in an editor.
+    OpenFile(PathBuf),
+    /// A buffer was written to this path.
+    FileSaved(PathBuf),
+    /// Asks for two files to be compared, the older one first.
+    DiffFiles(PathBuf, PathBuf),
+}
+
+/// What subscribers pick messages by, one per `Message` variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topic {
+    OpenFile,
+    FileSaved,
+    DiffFiles,
+}
+
+impl Message {
+    pub fn topic(&self) -> Topic {
+        match self {
+            Message::OpenFile(_) => Topic::OpenFile,
+            Message::FileSaved(_) => Topic::FileSaved,
+            Message::DiffFiles(..) => Topic::DiffFiles,
+        }
+    }
+}
+
+/// A handle to publish on a bus. Clones share the bus's queue, so each
+/// component that publishes is given its own; delivery happens when the
+/// main loop dispatches. One that was never given a bus's publishes to
+/// nobody.
+#[derive(Debug, Clone, Default)]
+pub struct Publisher(Rc<RefCell<Vec<Message>>>);
+
+impl Publisher {
+    pub fn publish(&self, message: Message) {
+        self.0.borrow_mut().push(message);
+    }
+
+    fn take(&self) -> Vec<Message> {
+        std::mem::take(&mut *self.0.borrow_mut())
+    }
+}
+
+/// Routes published messages to subscribers, identified by `K`, e.g. an
+/// enum of the app's components.
+///
+/// Delivery order is fixed: messages go out in the order they were
+/// published, and each one reaches subscribers in the order they
+/// subscribed.
+pub struct Bus<K> {
+    queue: Publisher,
+    subscribers: Vec<(K, Vec<Topic>)>,
+    seq: u64,
+    trace: Option<File>,
+}
+
+impl<K: Copy + Eq + fmt::Debug> Bus<K> {
+    pub fn new() -> Self {
+        Self {
+            queue: Publisher::default(),
+            subscribers: vec![],
+            seq: 0,
+            trace: None,
+        }
+    }
+
+    /// A handle for a component to publish on this bus with.
+    pub fn publisher(&self) -> Publisher {
+        self.queue.clone()
+    }
+
+    pub fn publish(&self, message: Message) {
+        self.queue.publish(message);
+    }
+
+    /// Adds `topics` to what `subscriber` receives. A subscriber keeps its
+    /// place in the delivery order when it subscribes again.
+    pub fn subscribe(&mut self, subscriber: K, topics: &[Topic]) {
+        match self.subscribers.iter_mut().find(|(s, _)| *s == subscriber) {
+            Some((_, subscribed)) => {
+                for topic in topics {
+                    if !subscribed.contains(topic) {
+                        subscribed.push(*topic);
+                    }
+                }
+            }
+            None => self.subscribers.push((subscriber, topics.to_vec())),
+        }
+    }
+
+    /// Appends a line per delivered message to `path`.
+    pub fn trace_to(&mut self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        self.trace =
+            Some(OpenOptions::new().create(true).append(true).open(path)?);
+        Ok(())
+    }
+
+    /// Hands every pending message to `deliver` along with each subscriber,
+    /// including messages published while delivering. Returns whether
+    /// there was anything.
+    pub fn dispatch(&mut self, mut deliver: impl FnMut(K, &Message)) -> bool {
+        let mut any = false;
+        for _ in 0..MAX_ROUNDS {
+            let pending = self.queue.take();
+            if pending.is_empty() {
+                break;
+            }
+            any = true;
+            for message in pending {
+                self.seq += 1;
+                let topic = message.topic();
+                let recipients: Vec<K> = self
+                    .subscribers
+                    .iter()
+                    .filter(|(_, topics)| topics.contains(&topic))
+                    .map(|(subscriber, _)| *subscriber)
+                    .collect();
+                if let Some(trace) = &mut self.trace {
+                    // Tracing is best effort; a full disk shouldn't stop
+                    // messages from going out.
+                    let _ = writeln!(
+                        trace,
+                        "#{} {:?} -> {:?}",
+                        self.seq, message, recipients
+                    );
+                }
+                for subscriber in recipients {
+                    deliver(subscriber, &message);
+                }
+            }
+        }
+        any
+    }
+}
+
+impl<K: Copy + Eq + fmt::Debug> Default for Bus<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Sub {
+        App,
+        Explorer,
+        Status,
+    }
+
+    #[test]
+    fn dispatches_in_order() {
+        let mut bus = Bus::new();
+        bus.subscribe(Sub::Explorer, &[Topic::FileSaved]);
+        bus.subscribe(Sub::App, &[Topic::OpenFile, Topic::FileSaved]);
+        let publisher = bus.publisher();
+        publisher.publish(Message::FileSaved("a".into()));
+        publisher.publish(Message::OpenFile("b".into()));
+        let mut seen = vec![];
+        assert!(bus.dispatch(|sub, message| {
+            if sub == Sub::App && message.topic() == Topic::OpenFile {
+                // Published mid-dispatch, still delivered in this call.
+                publisher.publish(Message::FileSaved("c".into()));
+            }
+            seen.push(format!("{sub:?} {message:?}"));
+        }));
+        assert_eq!(
+            seen,
+            [
+                r#"Explorer FileSaved("a")"#,
+                r#"App FileSaved("a")"#,
+                r#"App OpenFile("b")"#,
+                r#"Explorer FileSaved("c")"#,
+                r#"App FileSaved("c")"#,
+            ]
+        );
+        assert!(!bus.dispatch(|_, _| {}));
+    }
+
+    #[test]
+    fn subscribers_keep_their_place_and_only_get_their_topics() {
+        let mut bus = Bus::new();
+        bus.subscribe(Sub::Status, &[Topic::FileSaved]);
+        bus.subscribe(Sub::App, &[Topic::OpenFile]);
+        bus.subscribe(Sub::Explorer, &[Topic::DiffFiles]);
+        // Subscribing again adds topics without moving to the back.
+        bus.subscribe(Sub::App, &[Topic::FileSaved, Topic::OpenFile]);
+        bus.publish(Message::FileSaved("a".into()));
+        bus.publish(Message::DiffFiles("b".into(), "c".into()));
+        bus.publish(Message::OpenFile("d".into()));
+        let mut seen = vec![];
+        bus.dispatch(|sub, message| seen.push((sub, message.topic())));
+        assert_eq!(
+            seen,
+            [
+                (Sub::Status, Topic::FileSaved),
+                (Sub::App, Topic::FileSaved),
+                (Sub::Explorer, Topic::DiffFiles),
+                (Sub::App, Topic::OpenFile),
+            ]
+        );
+    }
+
+    #[test]
+    fn publishers_share_one_queue() {
+        let mut bus = Bus::new();
+        bus.subscribe(Sub::App, &[Topic::OpenFile]);
+        let (first, second) = (bus.publisher(), bus.publisher());
+        first.publish(Message::OpenFile("a".into()));
+        second.publish(Message::OpenFile("b".into()));
+        first.publish(Message::OpenFile("c".into()));
+        // Publishing on a bus that isn't dispatched reaches nobody.
+        Publisher::default().publish(Message::OpenFile("x".into()));
+        let mut seen = vec![];
+        bus.dispatch(|_, message| seen.push(message.clone()));
+        let opened = ["a", "b", "c"].map(|p| Message::OpenFile(p.into()));
+        assert_eq!(seen, opened);
+    }
+
+    #[test]
+    fn handlers_that_keep_answering_wait_for_the_next_dispatch() {
+        let mut bus = Bus::new();
+        bus.subscribe(Sub::App, &[Topic::FileSaved]);
+        let publisher = bus.publisher();
+        publisher.publish(Message::FileSaved("a".into()));
+        let mut delivered = 0;
+        let mut echo = |_: Sub, message: &Message| {
+            delivered += 1;
+            publisher.publish(message.clone());
+        };
+        assert!(bus.dispatch(&mut echo));
+        assert!(bus.dispatch(&mut echo));
+        assert_eq!(delivered, 2 * MAX_ROUNDS);
+    }
+}
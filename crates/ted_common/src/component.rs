@@ -0,0 +1,63 @@
+use crate::bus::Message;
+use crate::keymap::KeyContext;
+use ratatui::crossterm::event::Event;
+use ratatui::prelude::*;
+
+/// What a component did with an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventResult {
+    Consumed,
+    /// Not for this component; the caller may pass it on.
+    Ignored,
+    /// Handled, and the component asks the app to run a global action such
+    /// as `quit`.
+    Action(String),
+}
+
+/// A pane the main loop can route events to and draw without knowing what
+/// it is.
+///
+/// Keys are first resolved against the component's `key_context` and the
+/// global keymap. Bound actions the app doesn't handle itself go to
+/// `run_action`; keys neither keymap binds go to `handle_event` along with
+/// mouse and paste events. An `EventResult::Action` bubbles back up and
+/// runs like a global keymap action.
+///
+/// Components don't hold on to each other. They publish messages through
+/// the `bus::Publisher` they were given, and the app delivers the topics
+/// a component subscribed to through `on_message`.
+pub trait Component {
+    /// A short name, e.g. for the status line.
+    fn id(&self) -> &str;
+
+    fn handle_event(&mut self, event: &Event) -> EventResult;
+
+    fn render(&mut self, frame: &mut Frame, area: Rect);
+
+    /// Called before rendering with whether the component has focus.
+    fn set_focused(&mut self, _focused: bool) {}
+
+    /// The keymap context keys are resolved in while this has focus.
+    fn key_context(&self) -> KeyContext {
+        KeyContext::Global
+    }
+
+    /// Whether the component waits on more keys of a sequence of its own,
+    /// like Vim's `ci`. The keymap leaves the next key to it meanwhile.
+    fn has_pending_keys(&self) -> bool {
+        false
+    }
+
+    /// Actions this component understands, for validating keymaps.
+    fn actions(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Runs a keymap action, returning `false` if the name is unknown.
+    fn run_action(&mut self, _action: &str) -> bool {
+        false
+    }
+
+    /// Receives a message from the bus.
+    fn on_message(&mut self, _message: &Message) {}
+}
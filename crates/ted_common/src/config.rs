@@ -0,0 +1,50 @@
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+use std::{env, fs, io};
+
+/// The user's `config.toml`, kept as a raw table so every crate can pull out
+/// its own section without `ted_common` knowing about it.
+#[derive(Debug, Default)]
+pub struct Config {
+    table: toml::Table,
+}
+
+impl Config {
+    pub fn load() -> io::Result<Self> {
+        let Some(dir) = config_dir() else {
+            return Ok(Self::default());
+        };
+        let path = dir.join("config.toml");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let table = text
+            .parse::<toml::Table>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self { table })
+    }
+
+    pub fn section<T: DeserializeOwned + Default>(
+        &self,
+        name: &str,
+    ) -> io::Result<T> {
+        match self.table.get(name) {
+            Some(value) => value
+                .clone()
+                .try_into()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            None => Ok(T::default()),
+        }
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join("ted"));
+    }
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/ted"))
+}
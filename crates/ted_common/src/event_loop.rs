@@ -0,0 +1,143 @@
+use crate::notify;
+use crate::tasks::TaskEvent;
+use ratatui::crossterm::event::{self, Event};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Everything the main loop wakes up for, on one channel.
+#[derive(Debug)]
+pub enum LoopEvent {
+    Terminal(Event),
+    /// Sent every tick interval for periodic work like swap files.
+    Tick,
+    /// A timer set with `EventLoop::set_timer` is due.
+    Timer(&'static str),
+    Task(TaskEvent),
+    /// Some other source, like a language server, has something to be
+    /// polled.
+    Wake,
+}
+
+/// Multiplexes terminal input, ticks, timers and background task reports so
+/// the UI can update without waiting for a key press.
+pub struct EventLoop {
+    sender: Sender<LoopEvent>,
+    receiver: Receiver<LoopEvent>,
+    tick: Duration,
+    next_tick: Instant,
+    timers: Vec<(Instant, &'static str)>,
+}
+
+impl EventLoop {
+    pub fn new(tick: Duration) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver,
+            tick,
+            next_tick: Instant::now() + tick,
+            timers: vec![],
+        }
+    }
+
+    /// For anything that wants to wake the loop from another thread.
+    pub fn sender(&self) -> Sender<LoopEvent> {
+        self.sender.clone()
+    }
+
+    /// Reads terminal events on a thread of their own from now on.
+    pub fn spawn_terminal_reader(&self) {
+        let sender = self.sender();
+        thread::Builder::new()
+            .name("ted-terminal".to_string())
+            .spawn(move || {
+                loop {
+                    match event::read() {
+                        Ok(event) => {
+                            if sender.send(LoopEvent::Terminal(event)).is_err()
+                            {
+                                return;
+                            }
+                        }
+                        Err(e) => {
+                            notify::error(format!("terminal input: {e}"));
+                            return;
+                        }
+                    }
+                }
+            })
+            .expect("spawning the terminal reader");
+    }
+
+    /// Fires `LoopEvent::Timer(name)` once after `after`. Setting a timer
+    /// that is already pending moves it.
+    pub fn set_timer(&mut self, name: &'static str, after: Duration) {
+        self.cancel_timer(name);
+        self.timers.push((Instant::now() + after, name));
+    }
+
+    pub fn cancel_timer(&mut self, name: &'static str) {
+        self.timers.retain(|(_, n)| *n != name);
+    }
+
+    /// Blocks until something happens.
+    pub fn wait(&mut self) -> LoopEvent {
+        loop {
+            if let Some(event) = self.due(Instant::now()) {
+                return event;
+            }
+            let deadline = self
+                .timers
+                .iter()
+                .map(|(at, _)| *at)
+                .fold(self.next_tick, Instant::min);
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.receiver.recv_timeout(timeout) {
+                Ok(event) => return event,
+                Err(RecvTimeoutError::Timeout) => {}
+                // We hold a sender ourselves.
+                Err(RecvTimeoutError::Disconnected) => unreachable!(),
+            }
+        }
+    }
+
+    /// Something that is ready right now, without blocking.
+    pub fn poll(&mut self) -> Option<LoopEvent> {
+        self.due(Instant::now()).or_else(|| self.receiver.try_recv().ok())
+    }
+
+    fn due(&mut self, now: Instant) -> Option<LoopEvent> {
+        let due = (0..self.timers.len())
+            .filter(|idx| self.timers[*idx].0 <= now)
+            .min_by_key(|idx| self.timers[*idx].0);
+        if let Some(idx) = due {
+            return Some(LoopEvent::Timer(self.timers.remove(idx).1));
+        }
+        if self.next_tick <= now {
+            self.next_tick = now + self.tick;
+            return Some(LoopEvent::Tick);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiplexes_timers_and_sent_events() {
+        let mut events = EventLoop::new(Duration::from_secs(60));
+        events.set_timer("late", Duration::from_millis(40));
+        events.set_timer("soon", Duration::from_millis(10));
+        assert!(events.poll().is_none());
+        events.sender().send(LoopEvent::Tick).unwrap();
+        assert!(matches!(events.wait(), LoopEvent::Tick));
+        assert!(matches!(events.wait(), LoopEvent::Timer("soon")));
+        events.cancel_timer("late");
+        events.set_timer("again", Duration::ZERO);
+        assert!(matches!(events.wait(), LoopEvent::Timer("again")));
+        assert!(events.poll().is_none());
+    }
+}
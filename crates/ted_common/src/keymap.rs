@@ -0,0 +1,467 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+const DEFAULT_KEYMAP: &str =
+    include_str!("../../../assets/keymaps/default.toml");
+const DEFAULT_LEADER: &str = "\\";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already folded into the character for printable keys, and
+        // terminals disagree on whether they report it.
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyCode::Char(c.to_ascii_lowercase())
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+
+    /// Parses a key sequence written the way Vim does, e.g. `gg`, `<C-w>h`
+    /// or `<leader>f`. `<leader>` expands to `leader`.
+    pub fn parse_seq(
+        text: &str,
+        leader: Option<KeyPress>,
+    ) -> Result<Vec<KeyPress>, KeymapError> {
+        let invalid = || KeymapError::InvalidKey(text.to_string());
+        let mut keys = vec![];
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '<' {
+                keys.push(KeyPress::new(KeyCode::Char(c), KeyModifiers::NONE));
+                continue;
+            }
+            let name: String =
+                chars.by_ref().take_while(|c| *c != '>').collect();
+            if name.eq_ignore_ascii_case("leader") {
+                keys.push(leader.ok_or_else(invalid)?);
+            } else {
+                keys.push(parse_key_name(&name).ok_or_else(invalid)?);
+            }
+        }
+        if keys.is_empty() {
+            return Err(invalid());
+        }
+        Ok(keys)
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl From<KeyPress> for KeyEvent {
+    fn from(key: KeyPress) -> Self {
+        KeyEvent::new(key.code, key.modifiers)
+    }
+}
+
+fn parse_key_name(name: &str) -> Option<KeyPress> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+        modifiers |= match rest.as_bytes()[0].to_ascii_uppercase() {
+            b'C' => KeyModifiers::CONTROL,
+            b'A' | b'M' => KeyModifiers::ALT,
+            b'S' => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        rest = &rest[2..];
+    }
+    let code = match rest.to_ascii_lowercase().as_str() {
+        "esc" => KeyCode::Esc,
+        "cr" | "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "bs" | "backspace" => KeyCode::Backspace,
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "del" | "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        f if f.len() > 1 && f.starts_with('f') => {
+            KeyCode::F(f[1..].parse().ok()?)
+        }
+        _ => {
+            let mut chars = rest.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+            KeyCode::Char(c)
+        }
+    };
+    Some(KeyPress::new(code, modifiers))
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let name = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char('<') => "lt".to_string(),
+            KeyCode::Char(c) if self.modifiers.is_empty() => {
+                return write!(f, "{}", c);
+            }
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Enter => "CR".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::Backspace => "BS".to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            code => format!("{:?}", code),
+        };
+        write!(f, "<")?;
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "C-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "A-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "S-")?;
+        }
+        write!(f, "{}>", name)
+    }
+}
+
+pub fn format_seq(keys: &[KeyPress]) -> String {
+    keys.iter().map(|k| k.to_string()).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyContext {
+    Global,
+    Explorer,
+    Normal,
+    Insert,
+    Visual,
+    Diff,
+}
+
+impl KeyContext {
+    pub const ALL: [KeyContext; 6] = [
+        KeyContext::Global,
+        KeyContext::Explorer,
+        KeyContext::Normal,
+        KeyContext::Insert,
+        KeyContext::Visual,
+        KeyContext::Diff,
+    ];
+}
+
+impl fmt::Display for KeyContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Self::Global => write!(f, "global"),
+            Self::Explorer => write!(f, "explorer"),
+            Self::Normal => write!(f, "normal"),
+            Self::Insert => write!(f, "insert"),
+            Self::Visual => write!(f, "visual"),
+            Self::Diff => write!(f, "diff"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeymapError {
+    InvalidKey(String),
+    UnknownAction { context: KeyContext, keys: String, action: String },
+    Conflict { context: KeyContext, first: String, second: String },
+    Parse(String),
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Self::InvalidKey(keys) => {
+                write!(f, "invalid key sequence `{}`", keys)
+            }
+            Self::UnknownAction { context, keys, action } => write!(
+                f,
+                "[{}] `{}` is bound to unknown action `{}`",
+                context, keys, action
+            ),
+            Self::Conflict { context, first, second } => write!(
+                f,
+                "[{}] `{}` conflicts with `{}`",
+                context, first, second
+            ),
+            Self::Parse(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for KeymapError {}
+
+/// The `[keymap]` section of `config.toml`. Each context table maps a key
+/// sequence to an action name; binding to `"nop"` removes a default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct KeymapConfig {
+    pub leader: Option<String>,
+    pub global: HashMap<String, String>,
+    pub explorer: HashMap<String, String>,
+    pub normal: HashMap<String, String>,
+    pub insert: HashMap<String, String>,
+    pub visual: HashMap<String, String>,
+    pub diff: HashMap<String, String>,
+}
+
+impl KeymapConfig {
+    fn context(&self, context: KeyContext) -> &HashMap<String, String> {
+        match context {
+            KeyContext::Global => &self.global,
+            KeyContext::Explorer => &self.explorer,
+            KeyContext::Normal => &self.normal,
+            KeyContext::Insert => &self.insert,
+            KeyContext::Visual => &self.visual,
+            KeyContext::Diff => &self.diff,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    bindings: HashMap<KeyContext, Vec<(Vec<KeyPress>, String)>>,
+}
+
+impl Keymap {
+    /// Builds the keymap from the bundled defaults with `user` laid on top,
+    /// rejecting unknown actions and ambiguous bindings.
+    pub fn load(
+        user: &KeymapConfig,
+        actions: &[(KeyContext, &[&str])],
+    ) -> Result<Self, Vec<KeymapError>> {
+        let defaults: KeymapConfig = toml::from_str(DEFAULT_KEYMAP)
+            .map_err(|e| vec![KeymapError::Parse(e.to_string())])?;
+        let leader_text = user
+            .leader
+            .as_deref()
+            .or(defaults.leader.as_deref())
+            .unwrap_or(DEFAULT_LEADER);
+        let leader = KeyPress::parse_seq(leader_text, None)
+            .ok()
+            .filter(|keys| keys.len() == 1)
+            .map(|keys| keys[0])
+            .ok_or_else(|| {
+                vec![KeymapError::InvalidKey(leader_text.to_string())]
+            })?;
+
+        let mut keymap = Keymap::default();
+        let mut errors = vec![];
+        for context in KeyContext::ALL {
+            let known = actions
+                .iter()
+                .find(|(c, _)| *c == context)
+                .map(|(_, names)| *names)
+                .unwrap_or(&[]);
+            let mut merged: Vec<(Vec<KeyPress>, String)> = vec![];
+            let sources = [defaults.context(context), user.context(context)];
+            for (text, action) in sources.iter().flat_map(|m| sort_by_key(m)) {
+                let keys = match KeyPress::parse_seq(text, Some(leader)) {
+                    Ok(keys) => keys,
+                    Err(e) => {
+                        errors.push(e);
+                        continue;
+                    }
+                };
+                merged.retain(|(k, _)| *k != keys);
+                if action == "nop" {
+                    continue;
+                }
+                if !known.contains(&action.as_str()) {
+                    errors.push(KeymapError::UnknownAction {
+                        context,
+                        keys: text.clone(),
+                        action: action.clone(),
+                    });
+                    continue;
+                }
+                merged.push((keys, action.clone()));
+            }
+            errors.extend(find_conflicts(context, &merged));
+            keymap.bindings.insert(context, merged);
+        }
+        if errors.is_empty() { Ok(keymap) } else { Err(errors) }
+    }
+
+    pub fn bindings(&self, context: KeyContext) -> &[(Vec<KeyPress>, String)] {
+        self.bindings.get(&context).map(|b| b.as_slice()).unwrap_or(&[])
+    }
+}
+
+// HashMap iteration order is random; sort so errors come out stable.
+fn sort_by_key(map: &HashMap<String, String>) -> Vec<(&String, &String)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort();
+    entries
+}
+
+/// One sequence being a prefix of another means the shorter one could never
+/// wait for the longer one, since there is no timeout.
+fn find_conflicts(
+    context: KeyContext,
+    bindings: &[(Vec<KeyPress>, String)],
+) -> Vec<KeymapError> {
+    let mut conflicts = vec![];
+    for (i, (a, _)) in bindings.iter().enumerate() {
+        for (b, _) in &bindings[i + 1..] {
+            if a.starts_with(b) || b.starts_with(a) {
+                conflicts.push(KeymapError::Conflict {
+                    context,
+                    first: format_seq(a),
+                    second: format_seq(b),
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeymapResult {
+    Action(String),
+    Pending,
+    /// Nothing matched; these keys should be handed to the component as-is.
+    Unmatched(Vec<KeyPress>),
+}
+
+/// Accumulates keys for multi-key sequences across events.
+#[derive(Debug, Default)]
+pub struct KeymapResolver {
+    pending: Vec<KeyPress>,
+}
+
+impl KeymapResolver {
+    /// Feeds `key`, matching against `contexts` from most to least specific.
+    pub fn feed(
+        &mut self,
+        keymap: &Keymap,
+        contexts: &[KeyContext],
+        key: KeyPress,
+    ) -> KeymapResult {
+        self.pending.push(key);
+        let mut is_prefix = false;
+        for context in contexts {
+            for (keys, action) in keymap.bindings(*context) {
+                if *keys == self.pending {
+                    self.pending.clear();
+                    return KeymapResult::Action(action.clone());
+                }
+                is_prefix |= keys.starts_with(&self.pending);
+            }
+        }
+        if is_prefix {
+            KeymapResult::Pending
+        } else {
+            KeymapResult::Unmatched(std::mem::take(&mut self.pending))
+        }
+    }
+
+    pub fn pending(&self) -> &[KeyPress] {
+        &self.pending
+    }
+
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads `user` accepting every default action plus a couple of
+    /// editor ones, so the tests don't depend on the exact defaults.
+    fn load(user: &KeymapConfig) -> Result<Keymap, Vec<KeymapError>> {
+        let defaults: KeymapConfig = toml::from_str(DEFAULT_KEYMAP).unwrap();
+        let mut known: Vec<&str> = vec!["save", "move_down"];
+        for context in KeyContext::ALL {
+            known
+                .extend(defaults.context(context).values().map(|a| a.as_str()));
+        }
+        let actions: Vec<(KeyContext, &[&str])> =
+            KeyContext::ALL.iter().map(|c| (*c, known.as_slice())).collect();
+        Keymap::load(user, &actions)
+    }
+
+    fn config(text: &str) -> KeymapConfig {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn parses_sequences() {
+        let leader = KeyPress::new(KeyCode::Char(' '), KeyModifiers::NONE);
+        let keys = KeyPress::parse_seq("<C-W>h<leader>", Some(leader)).unwrap();
+        assert_eq!(
+            keys,
+            vec![
+                KeyPress::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+                KeyPress::new(KeyCode::Char('h'), KeyModifiers::NONE),
+                leader,
+            ]
+        );
+        assert_eq!(format_seq(&keys), "<C-w>h<Space>");
+        assert!(KeyPress::parse_seq("<Nope>", None).is_err());
+    }
+
+    #[test]
+    fn resolves_chords_with_leader() {
+        let user = config(
+            "leader = '<Space>'\n[normal]\n'<leader>w' = 'save'\nJ = 'move_down'",
+        );
+        let keymap = load(&user).unwrap();
+        let mut resolver = KeymapResolver::default();
+        let contexts = [KeyContext::Normal, KeyContext::Global];
+        let space = KeyPress::new(KeyCode::Char(' '), KeyModifiers::NONE);
+        let w = KeyPress::new(KeyCode::Char('w'), KeyModifiers::NONE);
+        assert_eq!(
+            resolver.feed(&keymap, &contexts, space),
+            KeymapResult::Pending
+        );
+        assert_eq!(
+            resolver.feed(&keymap, &contexts, w),
+            KeymapResult::Action("save".to_string())
+        );
+        assert_eq!(
+            resolver.feed(&keymap, &contexts, w),
+            KeymapResult::Unmatched(vec![w])
+        );
+    }
+
+    #[test]
+    fn reports_conflicts_and_unknown_actions() {
+        let user = config("[normal]\nQ = 'save'\nQQ = 'move_down'\nx = 'fly'");
+        let errors = load(&user).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(
+            errors.iter().any(|e| matches!(e, KeymapError::Conflict { .. }))
+        );
+        assert!(errors.contains(&KeymapError::UnknownAction {
+            context: KeyContext::Normal,
+            keys: "x".to_string(),
+            action: "fly".to_string(),
+        }));
+    }
+}
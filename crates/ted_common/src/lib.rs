@@ -1,3 +1,13 @@
+pub mod bus;
+pub mod component;
+pub mod config;
+pub mod event_loop;
+pub mod keymap;
+pub mod notify;
+pub mod open_files;
+pub mod state;
+pub mod tasks;
+
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
 }
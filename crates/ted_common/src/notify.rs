@@ -0,0 +1,245 @@
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Clear, Paragraph};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const MAX_HISTORY: usize = 200;
+const MAX_TOASTS: usize = 5;
+const TOAST_WIDTH: u16 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// How long a toast stays up; errors linger so they can be read.
+    fn timeout(self) -> Duration {
+        match self {
+            Severity::Info => Duration::from_secs(3),
+            Severity::Warning => Duration::from_secs(5),
+            Severity::Error => Duration::from_secs(8),
+        }
+    }
+
+    fn style(self) -> Style {
+        match self {
+            Severity::Info => Style::new().fg(Color::LightBlue),
+            Severity::Warning => Style::new().fg(Color::Yellow),
+            Severity::Error => Style::new().fg(Color::LightRed),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+    /// How many times the same message was posted back to back.
+    pub count: usize,
+    pub posted: Instant,
+}
+
+impl Notification {
+    fn text(&self) -> String {
+        match self.count {
+            1 => self.message.clone(),
+            n => format!("{} (x{n})", self.message),
+        }
+    }
+}
+
+/// Posted from anywhere, waiting for the UI to pick them up.
+static PENDING: Mutex<Vec<(Severity, String)>> = Mutex::new(Vec::new());
+
+/// Queues a notification. Works from any thread or component; the UI shows
+/// it on its next `Notifications::collect`.
+pub fn post(severity: Severity, message: impl Into<String>) {
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    pending.push((severity, message.into()));
+}
+
+pub fn info(message: impl Into<String>) {
+    post(Severity::Info, message);
+}
+
+pub fn warn(message: impl Into<String>) {
+    post(Severity::Warning, message);
+}
+
+pub fn error(message: impl Into<String>) {
+    post(Severity::Error, message);
+}
+
+/// The UI side of notifications: toasts that expire on their own and the
+/// history behind `:messages`.
+#[derive(Default)]
+pub struct Notifications {
+    toasts: Vec<Notification>,
+    history: Vec<Notification>,
+}
+
+impl Notifications {
+    /// Takes in everything posted since the last call. Returns whether
+    /// there was anything.
+    pub fn collect(&mut self) -> bool {
+        let pending = {
+            let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+            std::mem::take(&mut *pending)
+        };
+        let any = !pending.is_empty();
+        for (severity, message) in pending {
+            self.push(severity, message, Instant::now());
+        }
+        any
+    }
+
+    /// Adds a notification. A repeat of a live toast bumps its count and
+    /// timer instead of stacking a copy.
+    pub fn push(&mut self, severity: Severity, message: String, now: Instant) {
+        let same =
+            |n: &Notification| n.severity == severity && n.message == message;
+        if let Some(idx) = self.toasts.iter().position(same) {
+            let mut toast = self.toasts.remove(idx);
+            toast.count += 1;
+            toast.posted = now;
+            self.toasts.push(toast);
+        } else {
+            self.toasts.push(Notification {
+                severity,
+                message: message.clone(),
+                count: 1,
+                posted: now,
+            });
+            if self.toasts.len() > MAX_TOASTS {
+                self.toasts.remove(0);
+            }
+        }
+        match self.history.last_mut() {
+            Some(last) if same(last) => {
+                last.count += 1;
+                last.posted = now;
+            }
+            _ => {
+                self.history.push(Notification {
+                    severity,
+                    message,
+                    count: 1,
+                    posted: now,
+                });
+                if self.history.len() > MAX_HISTORY {
+                    self.history.remove(0);
+                }
+            }
+        }
+    }
+
+    /// Drops toasts that have been up long enough. Returns whether any
+    /// went away.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let before = self.toasts.len();
+        self.toasts.retain(|t| now < t.posted + t.severity.timeout());
+        self.toasts.len() != before
+    }
+
+    /// How long until the next toast expires, to wake up for it.
+    pub fn next_expiry(&self, now: Instant) -> Option<Duration> {
+        self.toasts
+            .iter()
+            .map(|t| {
+                (t.posted + t.severity.timeout()).saturating_duration_since(now)
+            })
+            .min()
+    }
+
+    pub fn toasts(&self) -> &[Notification] {
+        &self.toasts
+    }
+
+    pub fn history(&self) -> &[Notification] {
+        &self.history
+    }
+
+    /// Stacks the live toasts upwards from the bottom-right of `area`.
+    pub fn draw_toasts(&self, frame: &mut Frame, area: Rect) {
+        let width = TOAST_WIDTH.min(area.width);
+        let mut bottom = area.bottom();
+        for toast in self.toasts.iter().rev() {
+            if bottom < area.y + 3 {
+                break;
+            }
+            let rect = Rect::new(area.right() - width, bottom - 3, width, 3);
+            let style = toast.severity.style();
+            frame.render_widget(Clear, rect);
+            frame.render_widget(
+                Paragraph::new(toast.text()).block(
+                    Block::bordered()
+                        .title(toast.severity.label())
+                        .border_style(style),
+                ),
+                rect,
+            );
+            bottom -= 3;
+        }
+    }
+
+    /// Draws the history, newest at the bottom, `scroll` lines back from
+    /// the end.
+    pub fn draw_history(&self, frame: &mut Frame, area: Rect, scroll: usize) {
+        let block = Block::bordered().title("messages");
+        let rows = block.inner(area).height as usize;
+        let end = self.history.len().saturating_sub(scroll);
+        let start = end.saturating_sub(rows);
+        let lines: Vec<Line> = self.history[start..end]
+            .iter()
+            .map(|n| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:<8}", n.severity.label()),
+                        n.severity.style(),
+                    ),
+                    Span::raw(n.text()),
+                ])
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedups_and_expires() {
+        let mut notifications = Notifications::default();
+        let now = Instant::now();
+        notifications.push(Severity::Error, "boom".into(), now);
+        notifications.push(Severity::Info, "hi".into(), now);
+        notifications.push(Severity::Error, "boom".into(), now);
+        assert_eq!(notifications.toasts().len(), 2);
+        assert_eq!(notifications.toasts()[1].count, 2);
+        // Only back-to-back repeats collapse in the history.
+        assert_eq!(notifications.history().len(), 3);
+        notifications.push(Severity::Error, "boom".into(), now);
+        assert_eq!(notifications.history().len(), 3);
+        assert_eq!(notifications.history()[2].count, 2);
+
+        assert!(notifications.expire(now + Duration::from_secs(4)));
+        assert_eq!(notifications.toasts().len(), 1);
+        assert_eq!(
+            notifications.next_expiry(now + Duration::from_secs(4)),
+            Some(Duration::from_secs(4))
+        );
+    }
+}
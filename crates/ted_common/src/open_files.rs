@@ -0,0 +1,12 @@
+use std::path::{Path, PathBuf};
+
+/// The files open in buffers, for whatever follows their text without
+/// knowing the editor, like the language servers.
+pub trait OpenFiles {
+    /// Every open file with the version of its text, which changes
+    /// whenever the text does.
+    fn versions(&self) -> Vec<(PathBuf, u64)>;
+
+    /// The text of `path`, if it is open.
+    fn text(&self, path: &Path) -> Option<Vec<String>>;
+}
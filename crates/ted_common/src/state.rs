@@ -0,0 +1,44 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+/// Where ted keeps what it remembers between runs, as opposed to what the
+/// user configures.
+pub fn state_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_STATE_HOME") {
+        return Some(PathBuf::from(dir).join("ted"));
+    }
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state/ted"))
+}
+
+/// Turns a path into a single file name, the way Vim names its swap and
+/// undo files: `/home/me/src` becomes `%home%me%src`.
+pub fn escape_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':') { '%' } else { c })
+        .collect()
+}
+
+/// Reads `name` from the state directory. Missing or unreadable state is
+/// not worth failing over, so it falls back to the default.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    state_dir()
+        .and_then(|dir| fs::read_to_string(dir.join(name)).ok())
+        .and_then(|text| toml::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn save<T: Serialize>(name: &str, value: &T) -> io::Result<()> {
+    let Some(dir) = state_dir() else {
+        return Ok(());
+    };
+    let text = toml::to_string(value)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let path = dir.join(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, text)
+}
@@ -0,0 +1,225 @@
+use crate::event_loop::LoopEvent;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub type TaskId = u64;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// How a background task ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskOutcome {
+    Done,
+    Failed(String),
+    Cancelled,
+}
+
+/// What workers report back to the main loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskEvent {
+    Progress { id: TaskId, done: u64, total: Option<u64> },
+    Finished { id: TaskId, outcome: TaskOutcome },
+}
+
+/// A task that is queued or running, as far as the UI knows.
+#[derive(Debug, Clone)]
+pub struct TaskStatus {
+    pub id: TaskId,
+    pub label: String,
+    pub done: u64,
+    pub total: Option<u64>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl TaskStatus {
+    /// Progress in percent, when the task said how much there is to do.
+    pub fn percent(&self) -> Option<u64> {
+        let total = self.total.filter(|t| *t > 0)?;
+        Some(self.done.min(total) * 100 / total)
+    }
+}
+
+/// Handed to a running task to report progress and check for cancellation.
+pub struct TaskContext {
+    id: TaskId,
+    cancelled: Arc<AtomicBool>,
+    events: Sender<LoopEvent>,
+}
+
+impl TaskContext {
+    /// Long tasks should check this now and then and return early; the
+    /// result of a cancelled task is dropped either way.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn progress(&self, done: u64, total: Option<u64>) {
+        let event = TaskEvent::Progress { id: self.id, done, total };
+        let _ = self.events.send(LoopEvent::Task(event));
+    }
+}
+
+/// A few threads running background work off the UI thread. Progress and
+/// completion arrive in the main loop as `LoopEvent::Task`, which should be
+/// fed back through `update`.
+pub struct WorkerPool {
+    jobs: Sender<Job>,
+    events: Sender<LoopEvent>,
+    next_id: TaskId,
+    tasks: Vec<TaskStatus>,
+}
+
+impl WorkerPool {
+    pub fn new(threads: usize, events: Sender<LoopEvent>) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        for n in 0..threads.max(1) {
+            let queue = Arc::clone(&queue);
+            thread::Builder::new()
+                .name(format!("ted-worker-{n}"))
+                .spawn(move || work(&queue))
+                .expect("spawning a worker thread");
+        }
+        Self { jobs, events, next_id: 0, tasks: vec![] }
+    }
+
+    /// Queues `job` and returns its id. `label` is what the UI shows while
+    /// it runs. Errors and panics come back as `TaskOutcome::Failed`.
+    pub fn spawn<F>(&mut self, label: impl Into<String>, job: F) -> TaskId
+    where
+        F: FnOnce(&TaskContext) -> Result<(), String> + Send + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let ctx = TaskContext {
+            id,
+            cancelled: Arc::clone(&cancelled),
+            events: self.events.clone(),
+        };
+        self.tasks.push(TaskStatus {
+            id,
+            label: label.into(),
+            done: 0,
+            total: None,
+            cancelled,
+        });
+        let job: Job = Box::new(move || {
+            let outcome = if ctx.is_cancelled() {
+                TaskOutcome::Cancelled
+            } else {
+                match panic::catch_unwind(AssertUnwindSafe(|| job(&ctx))) {
+                    _ if ctx.is_cancelled() => TaskOutcome::Cancelled,
+                    Ok(Ok(())) => TaskOutcome::Done,
+                    Ok(Err(e)) => TaskOutcome::Failed(e),
+                    Err(_) => TaskOutcome::Failed("panicked".to_string()),
+                }
+            };
+            let event = TaskEvent::Finished { id: ctx.id, outcome };
+            let _ = ctx.events.send(LoopEvent::Task(event));
+        });
+        // The workers only stop when the pool is dropped, so this can't
+        // fail while `self` is alive.
+        let _ = self.jobs.send(job);
+        id
+    }
+
+    /// Asks a task to stop. Returns `false` if it isn't queued or running.
+    pub fn cancel(&mut self, id: TaskId) -> bool {
+        match self.tasks.iter().find(|t| t.id == id) {
+            Some(task) => {
+                task.cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cancel_all(&mut self) {
+        for task in &self.tasks {
+            task.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Applies a report from a worker. Returns the task once it finished.
+    pub fn update(&mut self, event: &TaskEvent) -> Option<TaskStatus> {
+        match *event {
+            TaskEvent::Progress { id, done, total } => {
+                if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+                    task.done = done;
+                    task.total = total;
+                }
+                None
+            }
+            TaskEvent::Finished { id, .. } => {
+                let idx = self.tasks.iter().position(|t| t.id == id)?;
+                Some(self.tasks.remove(idx))
+            }
+        }
+    }
+
+    /// Queued and running tasks, oldest first.
+    pub fn tasks(&self) -> &[TaskStatus] {
+        &self.tasks
+    }
+}
+
+fn work(queue: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = {
+            let queue = queue.lock().unwrap_or_else(|e| e.into_inner());
+            queue.recv()
+        };
+        match job {
+            Ok(job) => job(),
+            // The pool is gone.
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next_task_event(events: &Receiver<LoopEvent>) -> TaskEvent {
+        match events.recv().unwrap() {
+            LoopEvent::Task(event) => event,
+            _ => panic!("expected a task event"),
+        }
+    }
+
+    #[test]
+    fn reports_progress_and_cancellation() {
+        let (tx, rx) = mpsc::channel();
+        let mut pool = WorkerPool::new(1, tx);
+        let (release, blocked) = mpsc::channel::<()>();
+        let first = pool.spawn("first", move |ctx| {
+            ctx.progress(1, Some(4));
+            blocked.recv().map_err(|e| e.to_string())
+        });
+        // Queued behind `first` on the only worker, so it never starts.
+        let second = pool.spawn("second", |_| Ok(()));
+        assert!(pool.cancel(second));
+
+        let event = next_task_event(&rx);
+        pool.update(&event);
+        assert_eq!(pool.tasks()[0].percent(), Some(25));
+        release.send(()).unwrap();
+
+        let event = next_task_event(&rx);
+        let done = pool.update(&event).unwrap();
+        assert_eq!(done.id, first);
+        assert_eq!(
+            event,
+            TaskEvent::Finished { id: first, outcome: TaskOutcome::Done }
+        );
+        assert_eq!(
+            next_task_event(&rx),
+            TaskEvent::Finished { id: second, outcome: TaskOutcome::Cancelled }
+        );
+    }
+}
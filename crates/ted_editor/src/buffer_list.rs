@@ -0,0 +1,274 @@
+use crate::fold::FoldMethod;
+use crate::git::GitBase;
+use crate::gutter::GutterConfig;
+use crate::vim_editor::vim_editor::VimEditor;
+use std::io;
+use std::path::{Path, PathBuf};
+use ted_common::bus::Publisher;
+use ted_common::open_files::OpenFiles;
+
+/// The ordered set of open buffers and which one is being edited.
+/// There is always at least one buffer, an unnamed scratch one if needed.
+pub struct BufferList {
+    buffers: Vec<VimEditor>,
+    active: usize,
+    fold_method: FoldMethod,
+    gutter: GutterConfig,
+    git_base: GitBase,
+    bus: Publisher,
+}
+
+impl BufferList {
+    pub fn new() -> Self {
+        Self {
+            buffers: vec![VimEditor::new()],
+            active: 0,
+            fold_method: FoldMethod::default(),
+            gutter: GutterConfig::default(),
+            git_base: GitBase::default(),
+            bus: Publisher::default(),
+        }
+    }
+
+    /// How buffers find their folds, now and once opened.
+    pub fn set_fold_method(&mut self, method: FoldMethod) {
+        self.fold_method = method;
+        for buffer in &mut self.buffers {
+            buffer.set_fold_method(method);
+        }
+    }
+
+    /// What buffers show left of their text, now and once opened.
+    pub fn set_gutter(&mut self, config: GutterConfig) {
+        self.gutter = config;
+        for buffer in &mut self.buffers {
+            buffer.set_gutter(config);
+        }
+    }
+
+    /// What buffers show their changes against, now and once opened.
+    pub fn set_git_base(&mut self, base: GitBase) {
+        self.git_base = base;
+        for buffer in &mut self.buffers {
+            buffer.set_git_base(base);
+        }
+    }
+
+    /// Where buffers publish, e.g. that they were saved, now and once
+    /// opened.
+    pub fn set_publisher(&mut self, bus: Publisher) {
+        for buffer in &mut self.buffers {
+            buffer.set_publisher(bus.clone());
+        }
+        self.bus = bus;
+    }
+
+    pub fn git_base(&self) -> GitBase {
+        self.git_base
+    }
+
+    /// Hands the buffer of `path` what git has for it, as read with
+    /// `git_base`.
+    pub fn set_git_lines(&mut self, path: &Path, lines: Option<Vec<String>>) {
+        if let Some(idx) = self.position(path) {
+            self.buffers[idx].set_git_lines(lines);
+        }
+    }
+
+    /// Opens `path` in a new buffer, or focuses it if it is already open.
+    pub fn open(&mut self, path: impl Into<PathBuf>) -> io::Result<usize> {
+        let path = path.into();
+        if let Some(idx) = self.position(&path) {
+            self.active = idx;
+            return Ok(idx);
+        }
+        Ok(self.push(VimEditor::open(path)?))
+    }
+
+    /// Adds `editor` as a new buffer and makes it active.
+    pub fn push(&mut self, editor: VimEditor) -> usize {
+        let editor = self.configure(editor);
+        // Replace the initial scratch buffer instead of piling up next to it.
+        if self.buffers.len() == 1 && self.is_pristine_scratch(0) {
+            self.buffers[0] = editor;
+            self.active = 0;
+        } else {
+            self.buffers.push(editor);
+            self.active = self.buffers.len() - 1;
+        }
+        self.active
+    }
+
+    /// `editor` set up like the other buffers.
+    fn configure(&self, mut editor: VimEditor) -> VimEditor {
+        editor.set_fold_method(self.fold_method);
+        editor.set_gutter(self.gutter);
+        editor.set_git_base(self.git_base);
+        editor.set_publisher(self.bus.clone());
+        editor
+    }
+
+    /// The buffer of `path`, however either was spelled: `src/a.rs` and
+    /// `/work/src/a.rs` are the same file when `/work` is the current dir.
+    pub fn position(&self, path: &Path) -> Option<usize> {
+        let path = std::path::absolute(path).ok()?;
+        self.buffers.iter().position(|b| {
+            b.path().and_then(|p| std::path::absolute(p).ok()).as_ref()
+                == Some(&path)
+        })
+    }
+
+    fn is_pristine_scratch(&self, idx: usize) -> bool {
+        let buffer = &self.buffers[idx];
+        buffer.path().is_none() && !buffer.is_dirty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &VimEditor> {
+        self.buffers.iter()
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&VimEditor> {
+        self.buffers.get(idx)
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut VimEditor> {
+        self.buffers.get_mut(idx)
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &VimEditor {
+        &self.buffers[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut VimEditor {
+        &mut self.buffers[self.active]
+    }
+
+    pub fn select(&mut self, idx: usize) {
+        if idx < self.buffers.len() {
+            self.active = idx;
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        self.active = (self.active + 1) % self.buffers.len();
+    }
+
+    pub fn select_prev(&mut self) {
+        let len = self.buffers.len();
+        self.active = (self.active + len - 1) % len;
+    }
+
+    pub fn close(&mut self, idx: usize) {
+        if idx >= self.buffers.len() {
+            return;
+        }
+        self.buffers.remove(idx);
+        if self.buffers.is_empty() {
+            let scratch = self.configure(VimEditor::new());
+            self.buffers.push(scratch);
+        }
+        if self.active > idx || self.active >= self.buffers.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+    }
+
+    /// Moves buffer `from` to position `to`, keeping the same buffer active.
+    pub fn move_buffer(&mut self, from: usize, to: usize) {
+        let len = self.buffers.len();
+        if from >= len || to >= len || from == to {
+            return;
+        }
+        let buffer = self.buffers.remove(from);
+        self.buffers.insert(to, buffer);
+        self.active = if self.active == from {
+            to
+        } else if from < self.active && self.active <= to {
+            self.active - 1
+        } else if to <= self.active && self.active < from {
+            self.active + 1
+        } else {
+            self.active
+        };
+    }
+}
+
+impl OpenFiles for BufferList {
+    fn versions(&self) -> Vec<(PathBuf, u64)> {
+        self.buffers
+            .iter()
+            .filter_map(|b| Some((b.path()?.to_path_buf(), b.version())))
+            .collect()
+    }
+
+    fn text(&self, path: &Path) -> Option<Vec<String>> {
+        self.position(path).map(|idx| self.buffers[idx].lines())
+    }
+}
+
+impl Default for BufferList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gutter::LineNumbers;
+    use ratatui::prelude::*;
+
+    fn list_of(n: usize) -> BufferList {
+        let mut list = BufferList::new();
+        for _ in 1..n {
+            list.buffers.push(VimEditor::new());
+        }
+        list
+    }
+
+    #[test]
+    fn move_buffer_keeps_active_buffer() {
+        let mut list = list_of(4);
+        list.select(2);
+        list.move_buffer(0, 3);
+        assert_eq!(list.active_index(), 1);
+        list.move_buffer(1, 0);
+        assert_eq!(list.active_index(), 0);
+    }
+
+    #[test]
+    fn closing_last_buffer_leaves_scratch() {
+        let mut list = list_of(1);
+        let gutter = GutterConfig {
+            line_numbers: LineNumbers::None,
+            signs: false,
+            folds: false,
+        };
+        list.set_gutter(gutter);
+        list.close(0);
+        assert_eq!(list.len(), 1);
+        assert_eq!(list.active_index(), 0);
+        // The scratch buffer has the gutter the others had.
+        let area = Rect::new(0, 0, 20, 5);
+        let lines = ["text".to_string()];
+        let mut drawn = Buffer::empty(area);
+        list.active_mut().set_lines(&lines);
+        list.active_mut().render(area, &mut drawn);
+        let mut configured = VimEditor::scratch(&lines);
+        configured.set_gutter(gutter);
+        let mut expected = Buffer::empty(area);
+        configured.render(area, &mut expected);
+        assert_eq!(drawn, expected);
+    }
+}
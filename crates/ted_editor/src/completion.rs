@@ -0,0 +1,629 @@
+use crate::text_edit::{TextEdit, end_of};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
+
+const DEFAULT_SNIPPETS: &str =
+    include_str!("../../../assets/snippets/default.toml");
+
+/// Rows of items shown at once.
+const MAX_ROWS: usize = 10;
+const MIN_LIST_WIDTH: usize = 16;
+const MAX_LIST_WIDTH: usize = 40;
+const DOC_WIDTH: u16 = 48;
+/// Path completion stops reading a directory after this many entries.
+const MAX_PATHS: usize = 200;
+
+/// Where an item came from, in the order they are ranked on equal scores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    Lsp,
+    Snippet,
+    Path,
+    Word,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub source: Source,
+    /// A short tag like `fn` or `struct`, shown next to the label.
+    pub kind: Option<String>,
+    pub detail: Option<String>,
+    pub documentation: Option<String>,
+    /// What the typed text is matched against.
+    pub filter_text: String,
+    pub insert_text: String,
+    /// `insert_text` has placeholders like `$1` and `${2:name}`.
+    pub snippet: bool,
+    /// The column on the cursor row where the replaced text starts.
+    pub start: usize,
+    /// Other changes that come with the item, e.g. an import.
+    pub additional_edits: Vec<TextEdit>,
+}
+
+impl CompletionItem {
+    /// An item that inserts `label` over the text from `start` on.
+    pub fn plain(label: String, source: Source, start: usize) -> Self {
+        Self {
+            filter_text: label.clone(),
+            insert_text: label.clone(),
+            label,
+            source,
+            kind: None,
+            detail: None,
+            documentation: None,
+            snippet: false,
+            start,
+            additional_edits: vec![],
+        }
+    }
+
+    fn tag(&self) -> &str {
+        match (&self.kind, self.source) {
+            (Some(kind), _) => kind,
+            (None, Source::Lsp) => "",
+            (None, Source::Snippet) => "snip",
+            (None, Source::Path) => "path",
+            (None, Source::Word) => "word",
+        }
+    }
+}
+
+/// What accepting an item does to the buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accepted {
+    pub edits: Vec<TextEdit>,
+    pub cursor: (usize, usize),
+}
+
+/// The menu of items for the word at the cursor, filtered as it is typed.
+/// Sources can add items at any time, e.g. when a language server answers.
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub row: usize,
+    items: Vec<CompletionItem>,
+    /// Indices into `items`, best match first.
+    matches: Vec<usize>,
+    selected: usize,
+    line: String,
+    col: usize,
+}
+
+impl Completion {
+    pub fn new(row: usize) -> Self {
+        Self {
+            row,
+            items: vec![],
+            matches: vec![],
+            selected: 0,
+            line: String::new(),
+            col: 0,
+        }
+    }
+
+    /// Adds items, dropping words and paths some other source already
+    /// offers under the same label.
+    pub fn add(&mut self, items: Vec<CompletionItem>) {
+        let keep = self.selected().cloned();
+        self.items.extend(items);
+        self.items.sort_by(|a, b| {
+            a.label.cmp(&b.label).then(a.source.cmp(&b.source))
+        });
+        self.items
+            .dedup_by(|b, a| a.label == b.label && b.source >= Source::Path);
+        self.refilter(keep);
+    }
+
+    /// Filters for the cursor at `col` on `line`, the text of `row`.
+    pub fn update(&mut self, line: &str, col: usize) {
+        if self.line == line && self.col == col {
+            return;
+        }
+        self.line = line.to_string();
+        self.col = col;
+        self.refilter(None);
+    }
+
+    fn refilter(&mut self, keep: Option<CompletionItem>) {
+        let chars: Vec<char> = self.line.chars().collect();
+        let mut scored: Vec<(i64, usize)> = vec![];
+        for (idx, item) in self.items.iter().enumerate() {
+            if item.start > self.col || self.col > chars.len() {
+                continue;
+            }
+            let query: String = chars[item.start..self.col].iter().collect();
+            // Nothing to complete on a word that's already typed out.
+            if item.source == Source::Word && query == item.label {
+                continue;
+            }
+            if let Some(score) = fuzzy_score(&query, &item.filter_text) {
+                scored.push((score, idx));
+            }
+        }
+        let items = &self.items;
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            let (a, b) = (&items[*a], &items[*b]);
+            b_score
+                .cmp(a_score)
+                .then(a.source.cmp(&b.source))
+                .then(a.label.len().cmp(&b.label.len()))
+                .then(a.label.cmp(&b.label))
+        });
+        self.matches = scored.into_iter().map(|(_, idx)| idx).collect();
+        // New items don't move the selection off the one still matching.
+        self.selected = keep
+            .and_then(|keep| {
+                self.matches.iter().position(|idx| self.items[*idx] == keep)
+            })
+            .unwrap_or(0);
+    }
+
+    /// Where the selected item's text starts on the row.
+    pub fn start(&self) -> Option<usize> {
+        self.selected().map(|item| item.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    pub fn selected(&self) -> Option<&CompletionItem> {
+        self.matches.get(self.selected).map(|idx| &self.items[*idx])
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + 1) % self.matches.len();
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        let len = self.matches.len();
+        if len > 0 {
+            self.selected = (self.selected + len - 1) % len;
+        }
+    }
+
+    /// The edits that put the selected item in place of the text typed so
+    /// far, and where the cursor goes afterwards.
+    pub fn accept(&self) -> Option<Accepted> {
+        let item = self.selected()?;
+        let start = (self.row, item.start);
+        let (text, offset) = match item.snippet {
+            true => {
+                let indent: String = self
+                    .line
+                    .chars()
+                    .take_while(|c| c.is_whitespace())
+                    .collect();
+                expand_snippet(&item.insert_text, &indent)
+            }
+            false => {
+                let len = item.insert_text.chars().count();
+                (item.insert_text.clone(), len)
+            }
+        };
+        let before_cursor: String = text.chars().take(offset).collect();
+        let (mut row, col) = end_of(start, &before_cursor);
+        for edit in &item.additional_edits {
+            if edit.end.0 < self.row {
+                row = row.saturating_add_signed(edit.row_delta());
+            }
+        }
+        let mut edits = item.additional_edits.clone();
+        edits.push(TextEdit { start, end: (self.row, self.col), text });
+        Some(Accepted { edits, cursor: (row, col) })
+    }
+
+    fn list_width(&self) -> usize {
+        let widest = self
+            .matches
+            .iter()
+            .map(|idx| {
+                let item = &self.items[*idx];
+                item.label.chars().count() + item.tag().chars().count() + 1
+            })
+            .max()
+            .unwrap_or(0);
+        // Room for the borders.
+        (widest + 2).clamp(MIN_LIST_WIDTH, MAX_LIST_WIDTH)
+    }
+
+    /// How big the menu is, with room for documentation on the side when
+    /// the selected item has any.
+    pub fn size(&self) -> (u16, u16) {
+        let rows = self.matches.len().min(MAX_ROWS) as u16 + 2;
+        let docs = self.documentation().is_some();
+        let width = self.list_width() as u16 + if docs { DOC_WIDTH } else { 0 };
+        (width, if docs { rows.max(MAX_ROWS as u16 / 2) } else { rows })
+    }
+
+    fn documentation(&self) -> Option<String> {
+        let item = self.selected()?;
+        let text = match (&item.detail, &item.documentation) {
+            (Some(detail), Some(docs)) => format!("{detail}\n\n{docs}"),
+            (Some(text), None) | (None, Some(text)) => text.clone(),
+            (None, None) => return None,
+        };
+        Some(text)
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        let list_width = (self.list_width() as u16).min(area.width);
+        let [list, docs] = Layout::horizontal([
+            Constraint::Length(list_width),
+            Constraint::Min(0),
+        ])
+        .areas(area);
+        let block =
+            Block::bordered().border_style(Style::new().fg(Color::DarkGray));
+        let rows = block.inner(list).height as usize;
+        let first = (self.selected + 1).saturating_sub(rows);
+        let inner = list.width.saturating_sub(2) as usize;
+        let lines: Vec<Line> = self
+            .matches
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(rows)
+            .map(|(pos, idx)| {
+                let item = &self.items[*idx];
+                let tag = item.tag();
+                let room = inner.saturating_sub(tag.chars().count() + 1);
+                let label: String = item.label.chars().take(room).collect();
+                let pad = inner.saturating_sub(
+                    label.chars().count() + tag.chars().count(),
+                );
+                let line = Line::from(vec![
+                    Span::raw(label),
+                    Span::raw(" ".repeat(pad)),
+                    Span::styled(
+                        tag.to_string(),
+                        Style::new().fg(Color::DarkGray),
+                    ),
+                ]);
+                match pos == self.selected {
+                    true => line.style(Style::new().bg(Color::Blue)),
+                    false => line,
+                }
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(block), list);
+        if let Some(text) = self.documentation()
+            && docs.width > 2
+        {
+            frame.render_widget(
+                Paragraph::new(text).wrap(Wrap { trim: false }).block(
+                    Block::bordered()
+                        .border_style(Style::new().fg(Color::DarkGray)),
+                ),
+                docs,
+            );
+        }
+    }
+}
+
+/// How well `query` matches `candidate`, or `None` if its chars don't all
+/// appear in order. Matches at the start, after a separator or on a camel
+/// case hump and runs of consecutive chars score higher, gaps lower.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last: Option<usize> = None;
+    for q in query.chars() {
+        let found = (pos..candidate.len())
+            .find(|idx| candidate[*idx].to_lowercase().eq(q.to_lowercase()))?;
+        score += 1;
+        if found == 0 {
+            score += 10;
+        } else {
+            let prev = candidate[found - 1];
+            if !prev.is_alphanumeric()
+                || (prev.is_lowercase() && candidate[found].is_uppercase())
+            {
+                score += 6;
+            }
+        }
+        match last {
+            Some(last) if found == last + 1 => score += 5,
+            Some(last) => score -= (found - last - 1).min(5) as i64,
+            None => score -= found.min(5) as i64,
+        }
+        if candidate[found] == q {
+            score += 1;
+        }
+        last = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+/// Turns an LSP style snippet into plain text, indenting continuation lines
+/// with `indent`. Returns the text and where in it, in chars, the cursor
+/// goes: `$0`, else the first placeholder, else the end.
+pub fn expand_snippet(body: &str, indent: &str) -> (String, usize) {
+    let mut expansion = Expansion {
+        text: String::new(),
+        len: 0,
+        stops: BTreeMap::new(),
+        indent,
+    };
+    expansion.copy(&mut body.chars().peekable(), false);
+    let Expansion { text, len, stops, .. } = expansion;
+    let cursor = stops
+        .get(&0)
+        .or_else(|| stops.range(1..).next().map(|(_, pos)| pos))
+        .copied()
+        .unwrap_or(len);
+    (text, cursor)
+}
+
+struct Expansion<'a> {
+    text: String,
+    /// Length of `text` in chars.
+    len: usize,
+    /// Where each tab stop is, by number.
+    stops: BTreeMap<u32, usize>,
+    indent: &'a str,
+}
+
+impl Expansion<'_> {
+    fn push(&mut self, c: char) {
+        self.text.push(c);
+        self.len += 1;
+        if c == '\n' {
+            self.text.push_str(self.indent);
+            self.len += self.indent.chars().count();
+        }
+    }
+
+    /// Copies the body up to its end, or up to the `}` that closes the
+    /// placeholder default being copied when `nested`.
+    fn copy(&mut self, chars: &mut Peekable<Chars>, nested: bool) {
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        self.push(escaped);
+                    }
+                }
+                '}' if nested => return,
+                '$' if chars.peek().is_some_and(char::is_ascii_digit) => {
+                    let stop = number(chars);
+                    self.stops.entry(stop).or_insert(self.len);
+                }
+                '$' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    let stop = number(chars);
+                    self.stops.entry(stop).or_insert(self.len);
+                    match chars.next() {
+                        // `${1:default}`
+                        Some(':') => self.copy(chars, true),
+                        // `${1|one,two|}` keeps the first choice.
+                        Some('|') => {
+                            let mut first = true;
+                            for c in chars.by_ref() {
+                                match c {
+                                    '|' => break,
+                                    ',' => first = false,
+                                    c if first => self.push(c),
+                                    _ => {}
+                                }
+                            }
+                            chars.next();
+                        }
+                        Some('}') | None => {}
+                        // Variables like `${TM_FILENAME}` expand to nothing.
+                        Some(_) => {
+                            while chars.next().is_some_and(|c| c != '}') {}
+                        }
+                    }
+                }
+                c => self.push(c),
+            }
+        }
+    }
+}
+
+fn number(chars: &mut Peekable<Chars>) -> u32 {
+    let mut number = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        number.push(digit);
+    }
+    number.parse().unwrap_or(u32::MAX)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Where the word ending at `col` starts.
+pub fn word_start(line: &str, col: usize) -> usize {
+    let chars: Vec<char> = line.chars().take(col).collect();
+    chars.iter().rposition(|c| !is_word_char(*c)).map_or(0, |idx| idx + 1)
+}
+
+/// The whole word around `col`, e.g. the symbol under the cursor.
+pub fn word_at(line: &str, col: usize) -> String {
+    let start = word_start(line, col);
+    line.chars().skip(start).take_while(|c| is_word_char(*c)).collect()
+}
+
+/// Words of three chars or more from `texts`, e.g. every open buffer.
+pub fn words<'a>(
+    texts: impl IntoIterator<Item = &'a [String]>,
+    start: usize,
+) -> Vec<CompletionItem> {
+    let mut words = BTreeSet::new();
+    for lines in texts {
+        for line in lines {
+            for word in line.split(|c: char| !is_word_char(c)) {
+                if word.chars().count() >= 3
+                    && !word.starts_with(|c: char| c.is_ascii_digit())
+                {
+                    words.insert(word);
+                }
+            }
+        }
+    }
+    words
+        .into_iter()
+        .map(|word| {
+            CompletionItem::plain(word.to_string(), Source::Word, start)
+        })
+        .collect()
+}
+
+/// Entries of the directory named by the path being typed before `col`,
+/// relative to `base` unless it is absolute. Only offered once the text
+/// has a `/` in it.
+pub fn paths(line: &str, col: usize, base: &Path) -> Vec<CompletionItem> {
+    let before: String = line.chars().take(col).collect();
+    let token_start = before
+        .rfind(|c: char| c.is_whitespace() || "\"'`()[]{}<>=,;".contains(c))
+        .map_or(0, |idx| idx + 1);
+    let token = &before[token_start..];
+    let Some((dir, name)) = token.rsplit_once('/') else {
+        return vec![];
+    };
+    let dir = match dir {
+        "" => Path::new("/").to_path_buf(),
+        "~" => match std::env::var_os("HOME") {
+            Some(home) => home.into(),
+            None => return vec![],
+        },
+        dir => base.join(dir),
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return vec![];
+    };
+    let start = col - name.chars().count();
+    entries
+        .filter_map(Result::ok)
+        .take(MAX_PATHS)
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            if file_name.starts_with('.') && !name.starts_with('.') {
+                return None;
+            }
+            let mut item =
+                CompletionItem::plain(file_name, Source::Path, start);
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                item.insert_text.push('/');
+                item.kind = Some("dir".to_string());
+            }
+            Some(item)
+        })
+        .collect()
+}
+
+/// The `[completion]` section of `config.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CompletionConfig {
+    /// More snippets per file extension, added to the bundled ones.
+    pub snippets: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Snippets by file extension, then by name.
+#[derive(Debug, Clone, Default)]
+pub struct Snippets {
+    snippets: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Snippets {
+    pub fn new(config: &CompletionConfig) -> Self {
+        let mut snippets: BTreeMap<String, BTreeMap<String, String>> =
+            toml::from_str(DEFAULT_SNIPPETS).unwrap_or_default();
+        for (extension, extra) in &config.snippets {
+            snippets
+                .entry(extension.clone())
+                .or_default()
+                .extend(extra.clone());
+        }
+        Self { snippets }
+    }
+
+    pub fn items(
+        &self,
+        path: Option<&Path>,
+        start: usize,
+    ) -> Vec<CompletionItem> {
+        let extension =
+            path.and_then(|p| p.extension()).and_then(|e| e.to_str());
+        let Some(snippets) = extension.and_then(|e| self.snippets.get(e))
+        else {
+            return vec![];
+        };
+        snippets
+            .iter()
+            .map(|(name, body)| {
+                let mut item =
+                    CompletionItem::plain(name.clone(), Source::Snippet, start);
+                item.insert_text = body.clone();
+                item.snippet = true;
+                item.documentation = Some(expand_snippet(body, "").0);
+                item
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_fuzzy_matches() {
+        assert_eq!(fuzzy_score("xz", "abc"), None);
+        let prefix = fuzzy_score("buf", "buffer_list").unwrap();
+        let hump = fuzzy_score("bl", "BufferList").unwrap();
+        let scattered = fuzzy_score("bl", "bubble").unwrap();
+        assert!(hump > scattered);
+        assert!(prefix > fuzzy_score("buf", "rebuf").unwrap());
+
+        let mut menu = Completion::new(0);
+        menu.add(vec![
+            CompletionItem::plain("bubble".into(), Source::Word, 4),
+            CompletionItem::plain("BufferList".into(), Source::Lsp, 4),
+            CompletionItem::plain("BufferList".into(), Source::Word, 4),
+        ]);
+        menu.update("let bl", 6);
+        assert_eq!(menu.matches.len(), 2);
+        assert_eq!(menu.selected().unwrap().source, Source::Lsp);
+    }
+
+    #[test]
+    fn expands_snippets() {
+        let (text, cursor) =
+            expand_snippet("fn ${1:name}($2) {\n    $0\n}", "  ");
+        assert_eq!(text, "fn name() {\n      \n  }");
+        assert_eq!(cursor, 18);
+        let (text, cursor) = expand_snippet("${1|a,b|} \\$x$1", "");
+        assert_eq!((text.as_str(), cursor), ("a $x", 0));
+    }
+
+    #[test]
+    fn accepts_with_additional_edits() {
+        let mut item = CompletionItem::plain("HashMap".into(), Source::Lsp, 8);
+        item.additional_edits.push(TextEdit {
+            start: (0, 0),
+            end: (0, 0),
+            text: "use std::collections::HashMap;\n".into(),
+        });
+        let mut menu = Completion::new(2);
+        menu.add(vec![item]);
+        menu.update("let m = Has", 11);
+        let accepted = menu.accept().unwrap();
+        assert_eq!(accepted.cursor, (3, 15));
+        assert_eq!(accepted.edits[1].start, (2, 8));
+        assert_eq!(accepted.edits[1].end, (2, 11));
+    }
+}
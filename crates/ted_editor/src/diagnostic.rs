@@ -0,0 +1,57 @@
+use ratatui::style::Color;
+
+/// How bad a diagnostic is, least severe first so the worst one on a line
+/// is the `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Hint,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn color(self) -> Color {
+        match self {
+            Severity::Hint => Color::Gray,
+            Severity::Info => Color::LightBlue,
+            Severity::Warning => Color::Yellow,
+            Severity::Error => Color::LightRed,
+        }
+    }
+
+    /// What the sign column shows.
+    pub fn sign(self) -> char {
+        match self {
+            Severity::Hint => 'H',
+            Severity::Info => 'I',
+            Severity::Warning => 'W',
+            Severity::Error => 'E',
+        }
+    }
+}
+
+/// A problem reported for a range of text, e.g. by a language server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// `(row, column)` in chars.
+    pub start: (usize, usize),
+    /// Exclusive, like `start`.
+    pub end: (usize, usize),
+    pub severity: Severity,
+    pub message: String,
+    /// Who reported it, e.g. `rustc`.
+    pub source: Option<String>,
+}
+
+impl Diagnostic {
+    /// Whether the char at `(row, col)` is underlined. An empty range still
+    /// marks the char it starts at.
+    pub fn covers(&self, row: usize, col: usize) -> bool {
+        let pos = (row, col);
+        match self.start == self.end {
+            true => pos == self.start,
+            false => self.start <= pos && pos < self.end,
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use similar::{Algorithm, DiffTag};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// A diff that takes longer than this settles for a coarser answer, so a
+/// big rewrite can't stall typing.
+const DEADLINE: Duration = Duration::from_millis(50);
+
+/// Rows `old` of one text replaced by rows `new` of another. One of the
+/// two is empty when lines were only added or only deleted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkKind {
+    Added,
+    Deleted,
+    Modified,
+}
+
+impl Hunk {
+    pub fn kind(&self) -> HunkKind {
+        match (self.old.is_empty(), self.new.is_empty()) {
+            (true, _) => HunkKind::Added,
+            (_, true) => HunkKind::Deleted,
+            _ => HunkKind::Modified,
+        }
+    }
+
+    /// Whether the hunk is on `row` of the new text. Deleted lines are on
+    /// the row above where they were, or the first row if they started
+    /// the text.
+    pub fn touches(&self, row: usize) -> bool {
+        match self.kind() {
+            HunkKind::Deleted => self.new.start.saturating_sub(1) == row,
+            _ => self.new.contains(&row),
+        }
+    }
+}
+
+/// The line changes from `old` to `new`, in order.
+pub fn hunks(old: &[String], new: &[String]) -> Vec<Hunk> {
+    let deadline = Some(Instant::now() + DEADLINE);
+    let ops = similar::capture_diff_slices_deadline(
+        Algorithm::Myers,
+        old,
+        new,
+        deadline,
+    );
+    let mut hunks: Vec<Hunk> = vec![];
+    for op in ops {
+        let (tag, old, new) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        // A deletion right before an insertion is one change.
+        match hunks.last_mut() {
+            Some(last)
+                if last.old.end == old.start && last.new.end == new.start =>
+            {
+                last.old.end = old.end;
+                last.new.end = new.end;
+            }
+            _ => hunks.push(Hunk { old, new }),
+        }
+    }
+    hunks
+}
+
+/// The ranges of chars that differ between two versions of a line: what
+/// `old` lost and what `new` gained.
+pub fn changed_chars(
+    old: &str,
+    new: &str,
+) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let old: Vec<char> = old.chars().collect();
+    let new: Vec<char> = new.chars().collect();
+    let deadline = Some(Instant::now() + DEADLINE);
+    let ops = similar::capture_diff_slices_deadline(
+        Algorithm::Myers,
+        &old,
+        &new,
+        deadline,
+    );
+    let mut removed = vec![];
+    let mut added = vec![];
+    for op in ops {
+        let (tag, old, new) = op.as_tag_tuple();
+        if tag != DiffTag::Equal {
+            removed.extend((!old.is_empty()).then_some(old));
+            added.extend((!new.is_empty()).then_some(new));
+        }
+    }
+    (removed, added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn finds_line_hunks() {
+        let old = lines("a\nb\nc\nd\ne");
+        let new = lines("a\nB\nc\nx\ny\nd");
+        let hunks = hunks(&old, &new);
+        assert_eq!(
+            hunks,
+            [
+                Hunk { old: 1..2, new: 1..2 },
+                Hunk { old: 3..3, new: 3..5 },
+                Hunk { old: 4..5, new: 6..6 },
+            ]
+        );
+        let kinds: Vec<HunkKind> = hunks.iter().map(Hunk::kind).collect();
+        assert_eq!(
+            kinds,
+            [HunkKind::Modified, HunkKind::Added, HunkKind::Deleted]
+        );
+        assert!(hunks[2].touches(5));
+        assert!(hunks[1].touches(4) && !hunks[1].touches(5));
+    }
+
+    #[test]
+    fn finds_changed_chars() {
+        let (removed, added) = changed_chars("let x = 1;", "let y = 2;!");
+        assert_eq!(removed, [4..5, 8..9]);
+        assert_eq!(added, [4..5, 8..9, 10..11]);
+    }
+}
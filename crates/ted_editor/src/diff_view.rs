@@ -0,0 +1,667 @@
+use crate::diff::{self, Hunk};
+use ratatui::crossterm::event::{Event, MouseEventKind};
+use ratatui::prelude::*;
+use ratatui::widgets::Block;
+use serde::Deserialize;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use ted_common::component::{Component, EventResult};
+use ted_common::keymap::KeyContext;
+use ted_common::notify;
+
+const REMOVED: Style = Style::new().fg(Color::Red);
+const ADDED: Style = Style::new().fg(Color::Green);
+const NUMBER: Style = Style::new().fg(Color::DarkGray);
+/// Fills the space across from lines the other side doesn't have.
+const FILLER: Style = Style::new().fg(Color::DarkGray);
+const CURSOR_LINE: Style = Style::new().bg(Color::Rgb(50, 50, 50));
+/// Rows a mouse wheel step scrolls.
+const SCROLL: usize = 3;
+const TAB: &str = "    ";
+
+/// How the two texts are laid out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffLayout {
+    /// One text on each side, changed lines across from each other.
+    #[default]
+    SideBySide,
+    /// One text, with the lines each change removed above those it added.
+    Inline,
+}
+
+/// The `[diff]` section of `config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DiffConfig {
+    pub layout: DiffLayout,
+}
+
+/// One of the two texts being compared.
+pub struct DiffSide {
+    title: String,
+    lines: Vec<String>,
+    /// The buffer the text is from, which `do` and `dp` change. Text read
+    /// from disk or git can only be copied from.
+    path: Option<PathBuf>,
+    /// The buffer's version the text is as of.
+    version: u64,
+}
+
+impl DiffSide {
+    /// The text of the buffer for `path` at `version`.
+    pub fn buffer(
+        title: String,
+        lines: Vec<String>,
+        path: PathBuf,
+        version: u64,
+    ) -> Self {
+        Self { title, lines, path: Some(path), version }
+    }
+
+    /// Text that only serves to compare with, like the saved file.
+    pub fn fixed(title: String, lines: Vec<String>) -> Self {
+        Self { title, lines, path: None, version: 0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Left,
+    Right,
+}
+
+/// A line of the view: a line of either text or of both, and the hunk it
+/// belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Row {
+    left: Option<usize>,
+    right: Option<usize>,
+    hunk: Option<usize>,
+}
+
+/// The chars a row's left line lacks on the right, and the other way round.
+#[derive(Debug, Default)]
+struct Changed {
+    removed: Vec<Range<usize>>,
+    added: Vec<Range<usize>>,
+}
+
+/// Two texts compared line by line. Both sides scroll together, since a
+/// row of the view stands for a line on each side.
+pub struct DiffView {
+    left: DiffSide,
+    right: DiffSide,
+    layout: DiffLayout,
+    hunks: Vec<Hunk>,
+    rows: Vec<Row>,
+    /// The chars each row's left and right line change, by row.
+    changed: Vec<Changed>,
+    cursor: usize,
+    top: usize,
+    /// Rows shown at the last draw, for half pages.
+    height: usize,
+    /// The side `do` copies into and `dp` copies from.
+    side: Side,
+    /// Sides changed by `do` and `dp` that their buffers have yet to get.
+    edited: Vec<Side>,
+    focused: bool,
+}
+
+impl DiffView {
+    pub const ACTIONS: &'static [&'static str] = &[
+        "down",
+        "up",
+        "half_page_down",
+        "half_page_up",
+        "first",
+        "last",
+        "next_hunk",
+        "prev_hunk",
+        "obtain",
+        "put",
+        "switch_side",
+        "toggle_layout",
+    ];
+
+    /// Compares `left`, usually the older text, with `right`.
+    pub fn new(left: DiffSide, right: DiffSide, layout: DiffLayout) -> Self {
+        let mut view = Self {
+            left,
+            right,
+            layout,
+            hunks: vec![],
+            rows: vec![],
+            changed: vec![],
+            cursor: 0,
+            top: 0,
+            height: 0,
+            side: Side::Right,
+            edited: vec![],
+            focused: false,
+        };
+        view.update();
+        if let Some(row) = view.hunk_start(0) {
+            view.cursor = row;
+        }
+        view
+    }
+
+    /// The new text of each buffer `do` or `dp` changed since the last
+    /// call.
+    pub fn take_edits(&mut self) -> Vec<(PathBuf, Vec<String>)> {
+        std::mem::take(&mut self.edited)
+            .into_iter()
+            .filter_map(|side| {
+                let side = self.side(side);
+                Some((side.path.clone()?, side.lines.clone()))
+            })
+            .collect()
+    }
+
+    /// The buffers the sides show, with the version each side is as of.
+    pub fn buffers(&self) -> impl Iterator<Item = (&Path, u64)> {
+        [&self.left, &self.right]
+            .into_iter()
+            .filter_map(|side| Some((side.path.as_deref()?, side.version)))
+    }
+
+    /// Takes `lines` as the text of the buffer for `path` at `version`, so
+    /// that `do` and `dp` copy into what the buffer has now.
+    pub fn refresh(&mut self, path: &Path, version: u64, lines: &[String]) {
+        let mut changed = false;
+        for side in [&mut self.left, &mut self.right] {
+            if side.path.as_deref() == Some(path) {
+                side.version = version;
+                if side.lines != lines {
+                    side.lines = lines.to_vec();
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            self.update();
+        }
+    }
+
+    fn side(&self, side: Side) -> &DiffSide {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+        }
+    }
+
+    /// Compares the texts again and lays out the rows.
+    fn update(&mut self) {
+        self.hunks = diff::hunks(&self.left.lines, &self.right.lines);
+        self.rows = rows(
+            &self.hunks,
+            self.left.lines.len(),
+            self.right.lines.len(),
+            self.layout,
+        );
+        self.changed = self.rows.iter().map(|row| self.changed(row)).collect();
+        self.cursor = self.cursor.min(self.rows.len().saturating_sub(1));
+        self.top = self.top.min(self.cursor);
+    }
+
+    /// The first row of hunk `idx`.
+    fn hunk_start(&self, idx: usize) -> Option<usize> {
+        self.rows.iter().position(|row| row.hunk == Some(idx))
+    }
+
+    /// `]c` and `[c`.
+    fn goto_hunk(&mut self, down: bool) {
+        let starts =
+            (0..self.hunks.len()).filter_map(|idx| self.hunk_start(idx));
+        let next = match down {
+            true => starts.filter(|start| *start > self.cursor).min(),
+            false => starts.filter(|start| *start < self.cursor).max(),
+        };
+        match next {
+            Some(row) => self.cursor = row,
+            None => notify::info("no more changes"),
+        }
+    }
+
+    /// `do` copies the change under the cursor from the other side into
+    /// this one, `dp` from this side into the other one.
+    fn copy_hunk(&mut self, into_this_side: bool) {
+        let Some(idx) = self.rows.get(self.cursor).and_then(|row| row.hunk)
+        else {
+            notify::warn("no change here");
+            return;
+        };
+        let target = match (self.side, into_this_side) {
+            (Side::Left, true) | (Side::Right, false) => Side::Left,
+            _ => Side::Right,
+        };
+        let Hunk { old, new } = self.hunks[idx].clone();
+        let (source, target_side, range) = match target {
+            Side::Left => (&self.right.lines[new], &mut self.left, old),
+            Side::Right => (&self.left.lines[old], &mut self.right, new),
+        };
+        if target_side.path.is_none() {
+            notify::warn(format!("{} can't be changed", target_side.title));
+            return;
+        }
+        let source = source.to_vec();
+        target_side.lines.splice(range, source);
+        if !self.edited.contains(&target) {
+            self.edited.push(target);
+        }
+        self.update();
+    }
+
+    fn half_page(&mut self, down: bool) {
+        let step = (self.height / 2).max(1);
+        self.cursor = match down {
+            true => self.cursor + step,
+            false => self.cursor.saturating_sub(step),
+        };
+        self.cursor = self.cursor.min(self.rows.len().saturating_sub(1));
+    }
+
+    /// Moves `top` as little as it takes to show the cursor.
+    fn scroll_to_cursor(&mut self, height: usize) {
+        self.height = height;
+        if self.cursor < self.top {
+            self.top = self.cursor;
+        } else if height > 0 && self.cursor >= self.top + height {
+            self.top = self.cursor + 1 - height;
+        }
+    }
+
+    fn draw_side_by_side(&self, area: Rect, buf: &mut Buffer) {
+        let areas: [Rect; 2] =
+            Layout::horizontal([Constraint::Fill(1); 2]).areas(area);
+        for (side, area) in [Side::Left, Side::Right].into_iter().zip(areas) {
+            let text = self.side(side);
+            let block = Block::bordered()
+                .title(text.title.as_str())
+                .border_style(border_style(self.focused && self.side == side));
+            let inner = block.inner(area);
+            block.render(area, buf);
+            let numbers = number_width(text.lines.len());
+            for (y, row_idx) in (inner.y..inner.bottom()).zip(self.top..) {
+                let Some(row) = self.rows.get(row_idx) else {
+                    break;
+                };
+                let line = Rect::new(inner.x, y, inner.width, 1);
+                let idx = match side {
+                    Side::Left => row.left,
+                    Side::Right => row.right,
+                };
+                let Some(idx) = idx else {
+                    let filler = "╱".repeat(inner.width as usize);
+                    buf.set_string(line.x, y, filler, FILLER);
+                    continue;
+                };
+                let number = format!("{:>numbers$} ", idx + 1);
+                let width = line.width as usize;
+                let x = buf.set_stringn(line.x, y, number, width, NUMBER).0;
+                let (changed, style) = self.changes(row_idx, side);
+                let spans = highlight_changes(&text.lines[idx], changed, style);
+                buf.set_line(x, y, &Line::from(spans), line.right() - x);
+            }
+            self.draw_cursor_line(inner, buf);
+        }
+    }
+
+    fn draw_inline(&self, area: Rect, buf: &mut Buffer) {
+        let title = format!("{} → {}", self.left.title, self.right.title);
+        let block = Block::bordered()
+            .title(title)
+            .border_style(border_style(self.focused));
+        let inner = block.inner(area);
+        block.render(area, buf);
+        let numbers =
+            number_width(self.left.lines.len().max(self.right.lines.len()));
+        for (y, row_idx) in (inner.y..inner.bottom()).zip(self.top..) {
+            let Some(row) = self.rows.get(row_idx) else {
+                break;
+            };
+            let line = Rect::new(inner.x, y, inner.width, 1);
+            let number = |idx: Option<usize>| match idx {
+                Some(idx) => format!("{:>numbers$} ", idx + 1),
+                None => " ".repeat(numbers + 1),
+            };
+            let label = number(row.left) + &number(row.right);
+            let width = line.width as usize;
+            let x = buf.set_stringn(line.x, y, label, width, NUMBER).0;
+            let (side, sign, idx) = match (row.left, row.right) {
+                (Some(idx), None) => (Side::Left, "-", idx),
+                (_, Some(idx)) if row.hunk.is_some() => (Side::Right, "+", idx),
+                (_, Some(idx)) => (Side::Right, " ", idx),
+                (None, None) => continue,
+            };
+            let (changed, style) = self.changes(row_idx, side);
+            let text = &self.side(side).lines[idx];
+            let mut spans = vec![Span::styled(sign, style)];
+            spans.extend(highlight_changes(text, changed, style));
+            buf.set_line(x, y, &Line::from(spans), line.right() - x);
+        }
+        self.draw_cursor_line(inner, buf);
+    }
+
+    /// The chars of `side`'s line in row `idx` that differ from the line
+    /// the other side has in its place, and how the line looks.
+    fn changes(&self, idx: usize, side: Side) -> (&[Range<usize>], Style) {
+        let style = match (self.rows[idx].hunk, side) {
+            (None, _) => Style::new(),
+            (Some(_), Side::Left) => REMOVED,
+            (Some(_), Side::Right) => ADDED,
+        };
+        let changed = &self.changed[idx];
+        match side {
+            Side::Left => (&changed.removed, style),
+            Side::Right => (&changed.added, style),
+        }
+    }
+
+    /// The chars that differ between the lines `row` stands for.
+    fn changed(&self, row: &Row) -> Changed {
+        let Some(idx) = row.hunk else {
+            return Changed::default();
+        };
+        // Inline rows hold one line; its counterpart is as far into the
+        // other side of the hunk.
+        let hunk = &self.hunks[idx];
+        let (left, right) = match (row.left, row.right) {
+            (Some(left), Some(right)) => (left, right),
+            (Some(left), None) if self.layout == DiffLayout::Inline => {
+                (left, hunk.new.start + left - hunk.old.start)
+            }
+            (None, Some(right)) if self.layout == DiffLayout::Inline => {
+                (hunk.old.start + right - hunk.new.start, right)
+            }
+            _ => return Changed::default(),
+        };
+        if !hunk.old.contains(&left) || !hunk.new.contains(&right) {
+            return Changed::default();
+        }
+        let (removed, added) = diff::changed_chars(
+            &self.left.lines[left],
+            &self.right.lines[right],
+        );
+        Changed { removed, added }
+    }
+
+    fn draw_cursor_line(&self, inner: Rect, buf: &mut Buffer) {
+        if !self.focused {
+            return;
+        }
+        let y = inner.y + (self.cursor - self.top) as u16;
+        if y < inner.bottom() {
+            buf.set_style(Rect::new(inner.x, y, inner.width, 1), CURSOR_LINE);
+        }
+    }
+}
+
+impl Component for DiffView {
+    fn id(&self) -> &str {
+        "diff"
+    }
+
+    /// Scrolls with the mouse wheel; keys go through `run_action`.
+    fn handle_event(&mut self, event: &Event) -> EventResult {
+        let last = self.rows.len().saturating_sub(1);
+        match event {
+            Event::Mouse(mouse) => match mouse.kind {
+                MouseEventKind::ScrollDown => {
+                    self.top = (self.top + SCROLL).min(last);
+                    self.cursor = self.cursor.max(self.top);
+                }
+                MouseEventKind::ScrollUp => {
+                    self.top = self.top.saturating_sub(SCROLL);
+                    let bottom = self.top + self.height.saturating_sub(1);
+                    self.cursor = self.cursor.min(bottom);
+                }
+                _ => return EventResult::Ignored,
+            },
+            _ => return EventResult::Ignored,
+        }
+        EventResult::Consumed
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) {
+        // The borders take two rows.
+        self.scroll_to_cursor(area.height.saturating_sub(2) as usize);
+        let buf = frame.buffer_mut();
+        match self.layout {
+            DiffLayout::SideBySide => self.draw_side_by_side(area, buf),
+            DiffLayout::Inline => self.draw_inline(area, buf),
+        }
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn key_context(&self) -> KeyContext {
+        KeyContext::Diff
+    }
+
+    fn actions(&self) -> &'static [&'static str] {
+        Self::ACTIONS
+    }
+
+    /// Runs a keymap action, returning `false` if the name is unknown.
+    fn run_action(&mut self, action: &str) -> bool {
+        let last = self.rows.len().saturating_sub(1);
+        match action {
+            "down" => self.cursor = (self.cursor + 1).min(last),
+            "up" => self.cursor = self.cursor.saturating_sub(1),
+            "half_page_down" => self.half_page(true),
+            "half_page_up" => self.half_page(false),
+            "first" => self.cursor = 0,
+            "last" => self.cursor = last,
+            "next_hunk" => self.goto_hunk(true),
+            "prev_hunk" => self.goto_hunk(false),
+            "obtain" => self.copy_hunk(true),
+            "put" => self.copy_hunk(false),
+            "switch_side" => {
+                self.side = match self.side {
+                    Side::Left => Side::Right,
+                    Side::Right => Side::Left,
+                }
+            }
+            "toggle_layout" => {
+                self.layout = match self.layout {
+                    DiffLayout::SideBySide => DiffLayout::Inline,
+                    DiffLayout::Inline => DiffLayout::SideBySide,
+                };
+                let row = self.rows.get(self.cursor).copied();
+                self.update();
+                // Stay on the same line of text.
+                if let Some(row) = row
+                    && let Some(cursor) = self.rows.iter().position(|r| {
+                        (r.right.is_some() && r.right == row.right)
+                            || (r.left.is_some() && r.left == row.left)
+                    })
+                {
+                    self.cursor = cursor;
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Lays out the rows of the view for `hunks` between texts of `left_len`
+/// and `right_len` lines.
+fn rows(
+    hunks: &[Hunk],
+    left_len: usize,
+    right_len: usize,
+    layout: DiffLayout,
+) -> Vec<Row> {
+    let same =
+        |left, right| Row { left: Some(left), right: Some(right), hunk: None };
+    let mut rows = vec![];
+    let (mut left, mut right) = (0, 0);
+    for (idx, hunk) in hunks.iter().enumerate() {
+        while left < hunk.old.start {
+            rows.push(same(left, right));
+            left += 1;
+            right += 1;
+        }
+        let hunk_row = |left, right| Row { left, right, hunk: Some(idx) };
+        match layout {
+            DiffLayout::SideBySide => {
+                let len = hunk.old.len().max(hunk.new.len());
+                rows.extend((0..len).map(|i| {
+                    hunk_row(
+                        Some(hunk.old.start + i).filter(|l| *l < hunk.old.end),
+                        Some(hunk.new.start + i).filter(|r| *r < hunk.new.end),
+                    )
+                }));
+            }
+            DiffLayout::Inline => {
+                rows.extend(hunk.old.clone().map(|l| hunk_row(Some(l), None)));
+                rows.extend(hunk.new.clone().map(|r| hunk_row(None, Some(r))));
+            }
+        }
+        left = hunk.old.end;
+        right = hunk.new.end;
+    }
+    while left < left_len && right < right_len {
+        rows.push(same(left, right));
+        left += 1;
+        right += 1;
+    }
+    rows
+}
+
+/// `line` in `style`, with the `changed` char ranges reversed. Tabs are
+/// spread to spaces.
+pub fn highlight_changes(
+    line: &str,
+    changed: &[Range<usize>],
+    style: Style,
+) -> Vec<Span<'static>> {
+    let chars: Vec<char> = line.chars().collect();
+    let text = |range: Range<usize>| -> String {
+        chars[range]
+            .iter()
+            .map(|c| match c {
+                '\t' => TAB.to_string(),
+                c => c.to_string(),
+            })
+            .collect()
+    };
+    let mut spans = vec![];
+    let mut start = 0;
+    for range in changed {
+        spans.push(Span::styled(text(start..range.start), style));
+        spans.push(Span::styled(
+            text(range.clone()),
+            style.add_modifier(Modifier::REVERSED),
+        ));
+        start = range.end;
+    }
+    spans.push(Span::styled(text(start..chars.len()), style));
+    spans
+}
+
+/// Digits in the biggest line number, at least three.
+fn number_width(line_count: usize) -> usize {
+    line_count.max(1).to_string().len().max(3)
+}
+
+fn border_style(focused: bool) -> Style {
+    if focused {
+        Style::new().fg(Color::LightGreen)
+    } else {
+        Style::new().fg(Color::DarkGray)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    fn view(layout: DiffLayout) -> DiffView {
+        let left = DiffSide::fixed("saved".into(), lines("a\nb\nc\nd"));
+        let right = DiffSide::buffer(
+            "a.txt".into(),
+            lines("a\nB\nx\nc"),
+            "a.txt".into(),
+            1,
+        );
+        DiffView::new(left, right, layout)
+    }
+
+    fn sides(view: &DiffView) -> Vec<(Option<usize>, Option<usize>)> {
+        view.rows.iter().map(|row| (row.left, row.right)).collect()
+    }
+
+    #[test]
+    fn lines_up_both_sides() {
+        let mut view = view(DiffLayout::SideBySide);
+        assert_eq!(
+            sides(&view),
+            [
+                (Some(0), Some(0)),
+                (Some(1), Some(1)),
+                (None, Some(2)),
+                (Some(2), Some(3)),
+                (Some(3), None),
+            ]
+        );
+        assert_eq!(view.cursor, 1);
+        view.run_action("toggle_layout");
+        assert_eq!(
+            sides(&view),
+            [
+                (Some(0), Some(0)),
+                (Some(1), None),
+                (None, Some(1)),
+                (None, Some(2)),
+                (Some(2), Some(3)),
+                (Some(3), None),
+            ]
+        );
+        view.run_action("next_hunk");
+        assert_eq!(view.cursor, 5);
+        view.run_action("prev_hunk");
+        assert_eq!(view.cursor, 1);
+    }
+
+    #[test]
+    fn copies_hunks_into_buffers_only() {
+        let mut view = view(DiffLayout::SideBySide);
+        view.run_action("obtain");
+        assert_eq!(view.right.lines, lines("a\nb\nc"));
+        assert_eq!(view.hunks.len(), 1);
+        let edits = view.take_edits();
+        assert_eq!(edits, [(PathBuf::from("a.txt"), lines("a\nb\nc"))]);
+        assert!(view.take_edits().is_empty());
+
+        view.run_action("next_hunk");
+        view.run_action("put");
+        assert_eq!(view.left.lines, lines("a\nb\nc\nd"));
+        assert!(view.take_edits().is_empty());
+    }
+
+    #[test]
+    fn copies_into_what_the_buffer_has_now() {
+        let mut view = view(DiffLayout::SideBySide);
+        let typed = lines("z\na\nB\nx\nc");
+        view.refresh(Path::new("a.txt"), 2, &typed);
+        assert_eq!(
+            view.buffers().collect::<Vec<_>>(),
+            [(Path::new("a.txt"), 2)]
+        );
+        view.run_action("next_hunk");
+        let (changed, _) = view.changes(view.cursor, Side::Right);
+        assert_eq!(changed, vec![0..1]);
+        view.run_action("obtain");
+        assert_eq!(view.right.lines, lines("z\na\nb\nc"));
+    }
+}
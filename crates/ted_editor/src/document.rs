@@ -0,0 +1,133 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn detect(text: &str) -> Self {
+        match text.find('\n') {
+            Some(idx) if idx > 0 && text.as_bytes()[idx - 1] == b'\r' => {
+                Self::CrLf
+            }
+            _ => Self::Lf,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Self::Lf => write!(f, "LF"),
+            Self::CrLf => write!(f, "CRLF"),
+        }
+    }
+}
+
+/// The on-disk side of a buffer: where it lives and what it looked like the
+/// last time it was loaded or written, so editors can tell if it is dirty.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    path: Option<PathBuf>,
+    line_ending: LineEnding,
+    saved_lines: Vec<String>,
+}
+
+impl Document {
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<(Self, Vec<String>)> {
+        let path = path.into();
+        let text = if path.exists() {
+            fs::read_to_string(&path)?
+        } else {
+            String::new()
+        };
+        let line_ending = LineEnding::detect(&text);
+        let lines = split_lines(&text);
+        let doc =
+            Self { path: Some(path), line_ending, saved_lines: lines.clone() };
+        Ok((doc, lines))
+    }
+
+    pub fn save(&mut self, lines: &[String]) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no file name",
+            ));
+        };
+        let mut text = lines.join(self.line_ending.as_str());
+        text.push_str(self.line_ending.as_str());
+        fs::write(path, text)?;
+        self.saved_lines = lines.to_vec();
+        Ok(())
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn set_path(&mut self, path: impl Into<PathBuf>) {
+        self.path = Some(path.into());
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    pub fn encoding(&self) -> &'static str {
+        // Files are read with `read_to_string`, so anything loaded is UTF-8.
+        "utf-8"
+    }
+
+    pub fn filetype(&self) -> Option<&str> {
+        self.path.as_deref()?.extension()?.to_str()
+    }
+
+    pub fn is_dirty(&self, lines: &[String]) -> bool {
+        // An empty buffer is a single empty line in both editors.
+        let is_blank = |l: &[String]| l.iter().all(|s| s.is_empty());
+        if self.saved_lines.is_empty() && is_blank(lines) {
+            return false;
+        }
+        self.saved_lines != lines
+    }
+}
+
+fn split_lines(text: &str) -> Vec<String> {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    let text = text.strip_suffix('\r').unwrap_or(text);
+    text.split('\n')
+        .map(|l| l.strip_suffix('\r').unwrap_or(l).to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_line_endings() {
+        assert_eq!(LineEnding::detect("a\r\nb"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\nb"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect(""), LineEnding::Lf);
+    }
+
+    #[test]
+    fn splits_without_trailing_empty_line() {
+        assert_eq!(split_lines("a\r\nb\r\n"), vec!["a", "b"]);
+        assert_eq!(split_lines("a\n\nb"), vec!["a", "", "b"]);
+    }
+}
@@ -0,0 +1,39 @@
+use crate::document::{Document, LineEnding};
+use std::path::PathBuf;
+
+/// A snapshot of everything the status line wants to know about an editor.
+#[derive(Debug, Clone, Default)]
+pub struct EditorStatus {
+    pub mode: String,
+    pub path: Option<PathBuf>,
+    pub dirty: bool,
+    pub filetype: Option<String>,
+    pub encoding: &'static str,
+    pub line_ending: LineEnding,
+    /// Zero-based (row, col) of the cursor.
+    pub cursor: (usize, usize),
+    pub line_count: usize,
+    pub pending_keys: String,
+}
+
+impl EditorStatus {
+    pub(crate) fn new(
+        mode: String,
+        doc: &Document,
+        lines: &[String],
+        cursor: (usize, usize),
+        pending_keys: String,
+    ) -> Self {
+        Self {
+            mode,
+            path: doc.path().map(|p| p.to_path_buf()),
+            dirty: doc.is_dirty(lines),
+            filetype: doc.filetype().map(|s| s.to_string()),
+            encoding: doc.encoding(),
+            line_ending: doc.line_ending(),
+            cursor,
+            line_count: lines.len().max(1),
+            pending_keys,
+        }
+    }
+}
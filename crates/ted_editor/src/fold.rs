@@ -0,0 +1,389 @@
+use crate::syntax::Syntax;
+use crate::text_edit;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::Path;
+use ted_common::state;
+
+/// Indentation counts tabs as this many spaces.
+const TAB_WIDTH: usize = 4;
+
+/// How folds are found in the text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FoldMethod {
+    /// Only the folds made with `zf`.
+    Manual,
+    /// A line folds the more indented lines under it.
+    Indent,
+    /// Folds come from the language server or tree-sitter, falling back to
+    /// indentation for other files.
+    #[default]
+    Syntax,
+}
+
+/// The `[folding]` section of `config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FoldConfig {
+    pub method: FoldMethod,
+}
+
+/// Rows `start` to `end`, both included, that can be closed to show just
+/// `start`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// What is remembered of a file's folds between runs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct FoldState {
+    closed: Vec<usize>,
+    manual: Vec<Fold>,
+}
+
+/// The folds of a buffer and which of them are closed. Folds are kept by
+/// row and follow the text as lines are added and removed.
+#[derive(Default)]
+pub struct Folds {
+    method: FoldMethod,
+    /// What the language server last sent, preferred over tree-sitter.
+    lsp: Option<Vec<Fold>>,
+    manual: Vec<Fold>,
+    /// The folds `method` found.
+    found: Vec<Fold>,
+    /// Every fold found and made by hand, one per start row.
+    folds: Vec<Fold>,
+    /// Start rows of the closed folds.
+    closed: BTreeSet<usize>,
+    /// The text the rows refer to.
+    lines: Vec<String>,
+    stale: bool,
+}
+
+impl Folds {
+    pub fn set_method(&mut self, method: FoldMethod) {
+        self.method = method;
+        self.stale = true;
+    }
+
+    /// Folding ranges from a language server, used by the syntax method.
+    pub fn set_lsp(&mut self, folds: Vec<Fold>) {
+        self.lsp = Some(folds);
+        self.stale = true;
+    }
+
+    /// Catches up with edits to the text, finding the folds again.
+    /// `syntax` is the buffer's highlighter, already up to date with
+    /// `lines`, to fold by its tree.
+    pub fn update(&mut self, lines: Vec<String>, syntax: Option<&Syntax>) {
+        if lines != self.lines {
+            let shift = text_edit::follow_rows(&self.lines, &lines);
+            self.closed = self.closed.iter().map(|row| shift(*row)).collect();
+            for fold in
+                self.manual.iter_mut().chain(self.lsp.iter_mut().flatten())
+            {
+                *fold = Fold { start: shift(fold.start), end: shift(fold.end) };
+            }
+            self.lines = lines;
+            self.stale = true;
+        }
+        if !self.stale {
+            return;
+        }
+        self.stale = false;
+        self.found = match (self.method, &self.lsp, syntax) {
+            (FoldMethod::Manual, _, _) => vec![],
+            (FoldMethod::Syntax, Some(lsp), _) => lsp.clone(),
+            (FoldMethod::Syntax, None, Some(syntax)) => syntax.folds(),
+            (FoldMethod::Syntax | FoldMethod::Indent, _, _) => {
+                by_indent(&self.lines)
+            }
+        };
+        self.merge();
+    }
+
+    fn merge(&mut self) {
+        let mut by_start = BTreeMap::new();
+        for fold in self.found.iter().chain(&self.manual) {
+            if fold.end > fold.start && fold.end < self.lines.len() {
+                let end = by_start.entry(fold.start).or_insert(fold.end);
+                *end = fold.end.max(*end);
+            }
+        }
+        self.folds = by_start
+            .into_iter()
+            .map(|(start, end)| Fold { start, end })
+            .collect();
+    }
+
+    /// The folds around `row`, outermost first.
+    fn containing(&self, row: usize) -> impl Iterator<Item = &Fold> {
+        self.folds
+            .iter()
+            .take_while(move |fold| fold.start <= row)
+            .filter(move |fold| fold.end >= row)
+    }
+
+    fn is_closed(&self, fold: &Fold) -> bool {
+        self.closed.contains(&fold.start)
+    }
+
+    /// The closed fold that hides `row`, if one does.
+    pub fn hiding(&self, row: usize) -> Option<Fold> {
+        self.containing(row)
+            .find(|fold| self.is_closed(fold) && fold.start < row)
+            .copied()
+    }
+
+    pub fn is_hidden(&self, row: usize) -> bool {
+        self.hiding(row).is_some()
+    }
+
+    /// The fold that starts at `row`, if there is one.
+    pub fn starting_at(&self, row: usize) -> Option<Fold> {
+        self.folds.iter().find(|fold| fold.start == row).copied()
+    }
+
+    /// The closed fold shown as the line at `row`, if `row` shows one.
+    pub fn closed_at(&self, row: usize) -> Option<Fold> {
+        self.starting_at(row)
+            .filter(|fold| self.is_closed(fold) && !self.is_hidden(row))
+    }
+
+    /// The first row shown at or after `row`.
+    pub fn next_visible(&self, row: usize) -> Option<usize> {
+        let mut row = row;
+        while let Some(fold) = self.hiding(row) {
+            row = fold.end + 1;
+        }
+        (row < self.lines.len()).then_some(row)
+    }
+
+    /// The row whose line shows `row`: the start of the fold hiding it.
+    pub fn shown_at(&self, row: usize) -> usize {
+        self.hiding(row).map_or(row, |fold| fold.start)
+    }
+
+    /// The rows shown from `row` down.
+    pub fn visible_rows(&self, row: usize) -> impl Iterator<Item = usize> {
+        let first = self.next_visible(self.shown_at(row));
+        std::iter::successors(first, |row| {
+            let after = self.closed_at(*row).map_or(row + 1, |f| f.end + 1);
+            self.next_visible(after)
+        })
+    }
+
+    /// The row shown `count` lines away from `row`, stopping at the first
+    /// or last.
+    pub fn step(&self, row: usize, count: usize, down: bool) -> usize {
+        let mut row = self.shown_at(row);
+        for _ in 0..count {
+            let next = match down {
+                true => self.visible_rows(row).nth(1),
+                false => row.checked_sub(1).map(|r| self.shown_at(r)),
+            };
+            match next {
+                Some(next) => row = next,
+                None => break,
+            }
+        }
+        row
+    }
+
+    /// `zo`: opens the closed fold at `row`.
+    pub fn open(&mut self, row: usize) {
+        let closed = self.containing(row).find(|fold| self.is_closed(fold));
+        if let Some(fold) = closed.copied() {
+            self.closed.remove(&fold.start);
+        }
+    }
+
+    /// `zc`: closes the innermost open fold around `row`.
+    pub fn close(&mut self, row: usize) {
+        let open = self.containing(row).filter(|f| !self.is_closed(f)).last();
+        if let Some(fold) = open.copied() {
+            self.closed.insert(fold.start);
+        }
+    }
+
+    /// `za`: opens the fold at `row` if it is closed, closes it otherwise.
+    pub fn toggle(&mut self, row: usize) {
+        match self.closed_at(self.shown_at(row)) {
+            Some(_) => self.open(row),
+            None => self.close(row),
+        }
+    }
+
+    /// `zR`
+    pub fn open_all(&mut self) {
+        self.closed.clear();
+    }
+
+    /// `zM`
+    pub fn close_all(&mut self) {
+        self.closed = self.folds.iter().map(|fold| fold.start).collect();
+    }
+
+    /// Opens every fold that hides `row`, e.g. where a search landed.
+    pub fn reveal(&mut self, row: usize) {
+        while let Some(fold) = self.hiding(row) {
+            self.closed.remove(&fold.start);
+        }
+    }
+
+    /// `zf`: folds `start` to `end` by hand, closed.
+    pub fn create(&mut self, start: usize, end: usize) {
+        if end > start {
+            self.manual.push(Fold { start, end });
+            self.closed.insert(start);
+            self.merge();
+        }
+    }
+
+    /// `zd`: removes the innermost fold made by hand around `row`. Returns
+    /// whether there was one.
+    pub fn delete(&mut self, row: usize) -> bool {
+        let innermost = self
+            .manual
+            .iter()
+            .enumerate()
+            .filter(|(_, fold)| fold.start <= row && row <= fold.end)
+            .max_by_key(|(_, fold)| fold.start);
+        let Some((idx, _)) = innermost else {
+            return false;
+        };
+        let fold = self.manual.remove(idx);
+        self.closed.remove(&fold.start);
+        self.merge();
+        true
+    }
+
+    /// Puts back the folds saved for `path`, which holds `lines`.
+    pub fn load(&mut self, path: &Path, lines: Vec<String>) {
+        let saved: FoldState = state::load(&file_name(path));
+        self.lines = lines;
+        self.closed = saved.closed.into_iter().collect();
+        self.manual = saved.manual;
+        self.stale = true;
+    }
+
+    /// Remembers the folds of `path` for the next time it is opened.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let saved = FoldState {
+            closed: self
+                .folds
+                .iter()
+                .filter(|fold| self.is_closed(fold))
+                .map(|fold| fold.start)
+                .collect(),
+            manual: self.manual.clone(),
+        };
+        state::save(&file_name(path), &saved)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.into());
+    format!("folds/{}.toml", state::escape_path(&path))
+}
+
+/// Folds each line over the lines under it that are indented deeper,
+/// leaving out blank lines at the end.
+pub fn by_indent(lines: &[String]) -> Vec<Fold> {
+    let mut folds = vec![];
+    // Lines that may start a fold, by indentation.
+    let mut open: Vec<(usize, usize)> = vec![];
+    let mut last = 0;
+    let mut end_folds = |open: &mut Vec<(usize, usize)>, indent, last| {
+        while let Some(&(depth, start)) = open.last()
+            && depth >= indent
+        {
+            open.pop();
+            if last > start {
+                folds.push(Fold { start, end: last });
+            }
+        }
+    };
+    for (row, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent = line
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+            .sum();
+        end_folds(&mut open, indent, last);
+        open.push((indent, row));
+        last = row;
+    }
+    end_folds(&mut open, 0, last);
+    folds.sort();
+    folds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    fn folds(text: &str) -> Folds {
+        let mut folds = Folds::default();
+        folds.set_method(FoldMethod::Indent);
+        folds.update(lines(text), None);
+        folds
+    }
+
+    #[test]
+    fn folds_by_indent() {
+        let text = "a\n  b\n    c\n\n  d\ne\n  f\n\n";
+        assert_eq!(
+            by_indent(&lines(text)),
+            [
+                Fold { start: 0, end: 4 },
+                Fold { start: 1, end: 2 },
+                Fold { start: 5, end: 6 }
+            ]
+        );
+    }
+
+    #[test]
+    fn opens_and_closes() {
+        let mut folds = folds("a\n  b\n    c\n  d\ne");
+        folds.close(2);
+        assert!(folds.is_hidden(2) && !folds.is_hidden(3));
+        folds.close(2);
+        assert!(folds.is_hidden(3));
+        assert_eq!(folds.visible_rows(0).collect::<Vec<_>>(), [0, 4]);
+        assert_eq!(folds.step(4, 1, false), 0);
+        folds.open(0);
+        assert_eq!(folds.visible_rows(0).collect::<Vec<_>>(), [0, 1, 3, 4]);
+        folds.toggle(1);
+        assert!(!folds.is_hidden(2));
+        folds.close_all();
+        folds.reveal(2);
+        assert_eq!(folds.visible_rows(0).count(), 5);
+    }
+
+    #[test]
+    fn follows_edits_and_manual_folds() {
+        let mut folds = folds("a\nb\nc\nd");
+        folds.create(1, 2);
+        assert_eq!(folds.visible_rows(0).collect::<Vec<_>>(), [0, 1, 3]);
+        folds.update(lines("new\na\nb\nc\nd"), None);
+        assert_eq!(folds.closed_at(2), Some(Fold { start: 2, end: 3 }));
+        assert!(folds.delete(3));
+        assert!(!folds.delete(3));
+        assert_eq!(folds.visible_rows(0).count(), 5);
+    }
+}
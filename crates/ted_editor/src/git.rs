@@ -0,0 +1,262 @@
+use crate::diff::{self, Hunk, HunkKind};
+use crate::gutter::{Sign, SignKind};
+use ratatui::style::{Color, Style};
+use serde::Deserialize;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const ADDED: Style = Style::new().fg(Color::Green);
+const MODIFIED: Style = Style::new().fg(Color::Blue);
+const DELETED: Style = Style::new().fg(Color::Red);
+
+/// Which version of a file changes are shown against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GitBase {
+    /// What is staged, so staged hunks stop showing.
+    #[default]
+    Index,
+    Head,
+}
+
+/// The `[git]` section of `config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct GitConfig {
+    pub base: GitBase,
+}
+
+/// Runs git in `dir` and returns what it printed, feeding it `input` if
+/// given.
+fn git(dir: &Path, args: &[&str], input: Option<&str>) -> io::Result<String> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(error.trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The directory git runs in for `path`, and the file's name in it.
+fn split(path: &Path) -> Option<(PathBuf, String)> {
+    let path = std::path::absolute(path).ok()?;
+    let name = path.file_name()?.to_str()?.to_string();
+    Some((path.parent()?.to_path_buf(), name))
+}
+
+/// The lines of `path` as `base` has them. `None` outside a repository,
+/// for files git does not track, or without git at all.
+pub fn base_lines(path: &Path, base: GitBase) -> Option<Vec<String>> {
+    let (dir, name) = split(path)?;
+    let object = match base {
+        GitBase::Index => format!(":./{name}"),
+        GitBase::Head => format!("HEAD:./{name}"),
+    };
+    let text = git(&dir, &["show", &object], None).ok()?;
+    Some(text.lines().map(str::to_string).collect())
+}
+
+/// Stages the hunk at `row` of `lines`, the buffer of `path`, leaving the
+/// rest of the file as it is in the index.
+pub fn stage_hunk(path: &Path, lines: &[String], row: usize) -> io::Result<()> {
+    let (dir, name) = split(path)
+        .ok_or_else(|| io::Error::other("the buffer has no file"))?;
+    let index = base_lines(path, GitBase::Index)
+        .ok_or_else(|| io::Error::other("the file is not tracked by git"))?;
+    let hunk = diff::hunks(&index, lines)
+        .into_iter()
+        .find(|hunk| hunk.touches(row))
+        .ok_or_else(|| io::Error::other("no unstaged change here"))?;
+    let prefix = git(&dir, &["rev-parse", "--show-prefix"], None)?;
+    let file = format!("{}{name}", prefix.trim_end());
+    let patch = patch(&file, &index, lines, &hunk);
+    let top = git(&dir, &["rev-parse", "--show-toplevel"], None)?;
+    git(
+        Path::new(top.trim_end()),
+        &["apply", "--cached", "--unidiff-zero", "-"],
+        Some(&patch),
+    )?;
+    Ok(())
+}
+
+/// A patch for `file` making just `hunk` of the change from `old` to
+/// `new`, without context lines.
+fn patch(file: &str, old: &[String], new: &[String], hunk: &Hunk) -> String {
+    // A range without lines is given by the line before it.
+    let header = |range: &std::ops::Range<usize>| match range.len() {
+        0 => format!("{},0", range.start),
+        len => format!("{},{len}", range.start + 1),
+    };
+    let mut patch = format!(
+        "--- a/{file}\n+++ b/{file}\n@@ -{} +{} @@\n",
+        header(&hunk.old),
+        header(&hunk.new)
+    );
+    for line in &old[hunk.old.clone()] {
+        patch += &format!("-{line}\n");
+    }
+    for line in &new[hunk.new.clone()] {
+        patch += &format!("+{line}\n");
+    }
+    patch
+}
+
+/// How a buffer differs from the version git has, kept up to date as it
+/// is edited.
+#[derive(Default)]
+pub struct GitChanges {
+    base: GitBase,
+    /// The text git has; `None` when the file is not in a repository.
+    base_lines: Option<Vec<String>>,
+    /// The text `hunks` were found for.
+    lines: Vec<String>,
+    hunks: Vec<Hunk>,
+}
+
+impl GitChanges {
+    /// Reads what `path` is compared with again, e.g. after it was staged
+    /// or committed.
+    pub fn load(&mut self, path: Option<&Path>, base: GitBase) {
+        self.base = base;
+        self.set_base_lines(path.and_then(|path| base_lines(path, base)));
+    }
+
+    /// Takes `lines` as what git has, e.g. read by `base_lines` off the UI
+    /// thread.
+    pub fn set_base_lines(&mut self, lines: Option<Vec<String>>) {
+        self.base_lines = lines;
+        self.lines.clear();
+        self.hunks.clear();
+    }
+
+    pub fn base(&self) -> GitBase {
+        self.base
+    }
+
+    /// Catches up with edits to the text.
+    pub fn update(&mut self, lines: &[String]) {
+        let Some(base) = &self.base_lines else {
+            return;
+        };
+        if lines != self.lines {
+            self.hunks = diff::hunks(base, lines);
+            self.lines = lines.to_vec();
+        }
+    }
+
+    pub fn hunks(&self) -> &[Hunk] {
+        &self.hunks
+    }
+
+    pub fn hunk_at(&self, row: usize) -> Option<&Hunk> {
+        self.hunks.iter().find(|hunk| hunk.touches(row))
+    }
+
+    /// The lines `hunk` replaced.
+    pub fn old_lines(&self, hunk: &Hunk) -> &[String] {
+        self.base_lines
+            .as_ref()
+            .and_then(|base| base.get(hunk.old.clone()))
+            .unwrap_or_default()
+    }
+
+    /// The first row of the next hunk after `row`, or of the one before
+    /// it going up.
+    pub fn next(&self, row: usize, down: bool) -> Option<usize> {
+        let starts = self.hunks.iter().map(|hunk| match hunk.kind() {
+            HunkKind::Deleted => hunk.new.start.saturating_sub(1),
+            _ => hunk.new.start,
+        });
+        match down {
+            true => starts.filter(|start| *start > row).min(),
+            false => starts.filter(|start| *start < row).max(),
+        }
+    }
+
+    /// A bar by added and modified rows, and a line where rows were
+    /// deleted.
+    pub fn signs(&self) -> impl Iterator<Item = Sign> {
+        self.hunks.iter().flat_map(|hunk| {
+            let sign = |row, symbol, style| Sign {
+                row,
+                kind: SignKind::Git,
+                symbol,
+                style,
+            };
+            match hunk.kind() {
+                HunkKind::Added => {
+                    hunk.new.clone().map(|row| sign(row, '▎', ADDED)).collect()
+                }
+                HunkKind::Modified => hunk
+                    .new
+                    .clone()
+                    .map(|row| sign(row, '▎', MODIFIED))
+                    .collect(),
+                HunkKind::Deleted => match hunk.new.start {
+                    0 => vec![sign(0, '▔', DELETED)],
+                    start => vec![sign(start - 1, '▁', DELETED)],
+                },
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn stages_one_hunk() {
+        let root = std::env::temp_dir()
+            .join(format!("ted-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        let run = |args: &[&str]| git(&root, args, None).unwrap();
+        run(&["init", "-q"]);
+        run(&["config", "user.email", "ted@example.com"]);
+        run(&["config", "user.name", "ted"]);
+        run(&["config", "commit.gpgsign", "false"]);
+        let path = root.join("src/a.txt");
+        fs::write(&path, "one\ntwo\nthree\nfour\n").unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-qm", "init"]);
+
+        let lines: Vec<String> =
+            ["zero", "one", "2", "three"].map(str::to_string).to_vec();
+        let mut changes = GitChanges::default();
+        changes.load(Some(&path), GitBase::Index);
+        changes.update(&lines);
+        let kinds: Vec<HunkKind> =
+            changes.hunks().iter().map(Hunk::kind).collect();
+        assert_eq!(
+            kinds,
+            [HunkKind::Added, HunkKind::Modified, HunkKind::Deleted]
+        );
+        assert_eq!(changes.next(0, true), Some(2));
+        assert_eq!(changes.next(2, true), Some(3));
+        assert_eq!(changes.next(2, false), Some(0));
+        assert_eq!(changes.old_lines(&changes.hunks()[1]), ["two"]);
+
+        stage_hunk(&path, &lines, 2).unwrap();
+        let staged = run(&["diff", "--cached", "--numstat"]);
+        assert_eq!(staged, "1\t1\tsrc/a.txt\n");
+        changes.load(Some(&path), GitBase::Index);
+        changes.update(&lines);
+        assert_eq!(changes.hunks().len(), 2);
+        assert!(stage_hunk(&path, &lines, 2).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
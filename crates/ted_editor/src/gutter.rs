@@ -0,0 +1,216 @@
+use crate::fold::Folds;
+use ratatui::prelude::*;
+use serde::Deserialize;
+
+/// A sign and a space.
+const SIGN_WIDTH: u16 = 2;
+/// A fold marker and a space.
+const FOLD_WIDTH: u16 = 2;
+/// Line numbers take at least this many digits, so the text does not
+/// shift over while a short file grows.
+const MIN_DIGITS: usize = 3;
+const NUMBER: Style = Style::new().fg(Color::DarkGray);
+const CURSOR_NUMBER: Style = Style::new().fg(Color::Yellow);
+const FOLD_MARKER: Style = Style::new().fg(Color::DarkGray);
+
+/// How rows are numbered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineNumbers {
+    None,
+    Absolute,
+    /// The distance from the cursor line, handy for counts like `5j`.
+    Relative,
+    /// Relative, with the cursor line's own number.
+    #[default]
+    Hybrid,
+}
+
+/// The `[gutter]` section of `config.toml`: what shows left of the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct GutterConfig {
+    pub line_numbers: LineNumbers,
+    /// The column for diagnostics, git changes and marks.
+    pub signs: bool,
+    pub folds: bool,
+}
+
+impl Default for GutterConfig {
+    fn default() -> Self {
+        Self { line_numbers: LineNumbers::default(), signs: true, folds: true }
+    }
+}
+
+/// What put a sign on a row; a row shows the sign of the highest kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SignKind {
+    Git,
+    Mark,
+    Diagnostic,
+}
+
+/// One cell of the sign column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sign {
+    pub row: usize,
+    pub kind: SignKind,
+    pub symbol: char,
+    pub style: Style,
+}
+
+/// The columns left of the text: signs, line numbers and fold markers.
+/// It keeps where it drew them, so clicks on a fold marker can be told
+/// apart from clicks on the text.
+#[derive(Default)]
+pub(crate) struct Gutter {
+    config: GutterConfig,
+    /// The fold column and the screen line and row of each marker in it,
+    /// as of the last draw.
+    fold_column: Option<u16>,
+    markers: Vec<(u16, usize)>,
+}
+
+impl Gutter {
+    pub(crate) fn set_config(&mut self, config: GutterConfig) {
+        self.config = config;
+    }
+
+    /// How wide the gutter is for a text of `line_count` lines.
+    pub(crate) fn width(&self, line_count: usize) -> u16 {
+        let numbers = match self.config.line_numbers {
+            LineNumbers::None => 0,
+            _ => number_width(line_count) + 1,
+        };
+        let signs = if self.config.signs { SIGN_WIDTH } else { 0 };
+        let folds = if self.config.folds { FOLD_WIDTH } else { 0 };
+        signs + numbers + folds
+    }
+
+    /// Draws the gutter in `area`, as wide as `width` asked for, next to
+    /// `rows`: the text rows shown from the top down. Without `folds` the
+    /// fold column stays empty.
+    pub(crate) fn draw(
+        &mut self,
+        area: Rect,
+        rows: &[usize],
+        cursor_row: usize,
+        signs: &[Sign],
+        folds: Option<&Folds>,
+        buf: &mut Buffer,
+    ) {
+        let signs_width = if self.config.signs { SIGN_WIDTH } else { 0 };
+        let folds_width = if self.config.folds { FOLD_WIDTH } else { 0 };
+        let [sign_area, number_area, fold_area] = Layout::horizontal([
+            Constraint::Length(signs_width),
+            Constraint::Fill(1),
+            Constraint::Length(folds_width),
+        ])
+        .areas(area);
+        let numbers = number_area.width;
+        self.fold_column = self.config.folds.then_some(fold_area.x);
+        self.markers.clear();
+
+        let cursor = rows.iter().position(|row| *row == cursor_row);
+        for (idx, (y, &row)) in (area.y..area.bottom()).zip(rows).enumerate() {
+            if self.config.signs {
+                let sign = signs
+                    .iter()
+                    .filter(|sign| sign.row == row)
+                    .max_by_key(|sign| sign.kind);
+                if let Some(sign) = sign {
+                    let symbol = sign.symbol.to_string();
+                    buf.set_string(sign_area.x, y, symbol, sign.style);
+                }
+            }
+            if self.config.line_numbers != LineNumbers::None && numbers > 1 {
+                let distance = match cursor {
+                    Some(cursor) => idx.abs_diff(cursor),
+                    None => row.abs_diff(cursor_row),
+                };
+                let style = if distance == 0 { CURSOR_NUMBER } else { NUMBER };
+                let number = self.number(row, distance, numbers as usize - 1);
+                buf.set_string(number_area.x, y, number, style);
+            }
+            if self.config.folds
+                && let Some(folds) = folds
+            {
+                let marker = match folds.starting_at(row) {
+                    Some(_) if folds.closed_at(row).is_some() => "▸",
+                    Some(_) => "▾",
+                    None => continue,
+                };
+                buf.set_string(fold_area.x, y, marker, FOLD_MARKER);
+                self.markers.push((y, row));
+            }
+        }
+    }
+
+    /// The number shown for `row`, `distance` lines from the cursor line,
+    /// padded to `width`.
+    fn number(&self, row: usize, distance: usize, width: usize) -> String {
+        match self.config.line_numbers {
+            LineNumbers::None => String::new(),
+            LineNumbers::Absolute => format!("{:>width$}", row + 1),
+            LineNumbers::Relative => format!("{distance:>width$}"),
+            // The cursor line's own number sits to the left, like vim's.
+            LineNumbers::Hybrid if distance == 0 => {
+                format!("{:<width$}", row + 1)
+            }
+            LineNumbers::Hybrid => format!("{distance:>width$}"),
+        }
+    }
+
+    /// The row whose fold marker is at `(column, row)` on screen, if there
+    /// is one.
+    pub(crate) fn fold_at(&self, column: u16, row: u16) -> Option<usize> {
+        if Some(column) != self.fold_column {
+            return None;
+        }
+        self.markers.iter().find(|(y, _)| *y == row).map(|(_, row)| *row)
+    }
+}
+
+/// Digits in the biggest line number, at least `MIN_DIGITS`.
+fn number_width(line_count: usize) -> u16 {
+    let digits = line_count.max(1).to_string().len();
+    digits.max(MIN_DIGITS) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(line_numbers: LineNumbers, signs: &[Sign]) -> Vec<String> {
+        let config = GutterConfig { line_numbers, ..Default::default() };
+        let mut gutter = Gutter::default();
+        gutter.set_config(config);
+        let area = Rect::new(0, 0, gutter.width(120), 3);
+        let mut buf = Buffer::empty(area);
+        gutter.draw(area, &[8, 9, 10], 9, signs, None, &mut buf);
+        (0..3)
+            .map(|y| (0..area.width).map(|x| buf[(x, y)].symbol()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn numbers_rows_and_shows_the_strongest_sign() {
+        assert_eq!(Gutter::default().width(99), 2 + 4 + 2);
+        assert_eq!(Gutter::default().width(1200), 2 + 5 + 2);
+        assert_eq!(
+            draw(LineNumbers::Absolute, &[]),
+            ["    9   ", "   10   ", "   11   "]
+        );
+        assert_eq!(
+            draw(LineNumbers::Relative, &[]),
+            ["    1   ", "    0   ", "    1   "]
+        );
+        let sign = |kind, symbol| Sign { row: 10, kind, symbol, style: NUMBER };
+        let signs =
+            [sign(SignKind::Diagnostic, 'E'), sign(SignKind::Mark, 'a')];
+        assert_eq!(
+            draw(LineNumbers::Hybrid, &signs),
+            ["    1   ", "  10    ", "E   1   "]
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+/// The most places remembered; the oldest go first.
+const MAX_JUMPS: usize = 100;
+
+/// A place jumped away from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jump {
+    pub path: PathBuf,
+    pub cursor: (usize, usize),
+}
+
+/// Where jumps like `gd` came from, to go back with `Ctrl-O` and forward
+/// again with `Ctrl-I`, like Vim's jump list.
+#[derive(Debug, Default)]
+pub struct JumpList {
+    back: Vec<Jump>,
+    forward: Vec<Jump>,
+}
+
+impl JumpList {
+    /// Remembers `from` before jumping somewhere new. Places gone back
+    /// over are forgotten.
+    pub fn push(&mut self, from: Jump) {
+        self.forward.clear();
+        if self.back.last() != Some(&from) {
+            self.back.push(from);
+        }
+        if self.back.len() > MAX_JUMPS {
+            self.back.remove(0);
+        }
+    }
+
+    /// The place before `current`, which is kept to come forward to.
+    pub fn back(&mut self, current: Jump) -> Option<Jump> {
+        let jump = self.back.pop()?;
+        self.forward.push(current);
+        Some(jump)
+    }
+
+    /// The place `back` left, with `current` kept to go back to again.
+    pub fn forward(&mut self, current: Jump) -> Option<Jump> {
+        let jump = self.forward.pop()?;
+        self.back.push(current);
+        Some(jump)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(row: usize) -> Jump {
+        Jump { path: PathBuf::from("a.rs"), cursor: (row, 0) }
+    }
+
+    #[test]
+    fn goes_back_and_forward() {
+        let mut jumps = JumpList::default();
+        jumps.push(at(1));
+        jumps.push(at(1));
+        jumps.push(at(2));
+        assert_eq!(jumps.back(at(3)), Some(at(2)));
+        assert_eq!(jumps.back(at(2)), Some(at(1)));
+        assert_eq!(jumps.back(at(1)), None);
+        assert_eq!(jumps.forward(at(1)), Some(at(2)));
+        // A new jump forgets the way forward.
+        jumps.push(at(5));
+        assert_eq!(jumps.forward(at(6)), None);
+        assert_eq!(jumps.back(at(6)), Some(at(5)));
+    }
+}
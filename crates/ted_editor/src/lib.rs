@@ -1,2 +1,18 @@
+pub mod buffer_list;
+pub mod completion;
+pub mod diagnostic;
+pub mod diff;
+pub mod diff_view;
+pub mod document;
+pub mod editor_status;
+pub mod fold;
+pub mod git;
+pub mod gutter;
+pub mod jump_list;
+pub mod mark;
+pub mod snapshot;
+pub mod swap;
+pub mod syntax;
+pub mod text_edit;
 pub mod vim_editor;
 pub mod vim_lite_editor;
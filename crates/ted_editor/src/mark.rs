@@ -0,0 +1,48 @@
+use crate::gutter::{Sign, SignKind};
+use crate::text_edit;
+use ratatui::style::{Color, Style};
+use std::collections::BTreeMap;
+
+const MARK_SIGN: Style = Style::new().fg(Color::Cyan);
+
+/// The positions set with `m{a-z}` in a buffer. Marks are kept by row and
+/// follow the text as lines are added and removed above them.
+#[derive(Default)]
+pub struct Marks {
+    marks: BTreeMap<char, (usize, usize)>,
+    /// The text the rows refer to.
+    lines: Vec<String>,
+}
+
+impl Marks {
+    /// Catches up with edits to the text.
+    pub fn update(&mut self, lines: Vec<String>) {
+        if lines == self.lines {
+            return;
+        }
+        let shift = text_edit::follow_rows(&self.lines, &lines);
+        for (row, _) in self.marks.values_mut() {
+            *row = shift(*row);
+        }
+        self.lines = lines;
+    }
+
+    /// Marks `position` as `name`, replacing where it was before.
+    pub fn set(&mut self, name: char, position: (usize, usize)) {
+        self.marks.insert(name, position);
+    }
+
+    pub fn get(&self, name: char) -> Option<(usize, usize)> {
+        self.marks.get(&name).copied()
+    }
+
+    /// A sign with the mark's letter on each marked row.
+    pub fn signs(&self) -> impl Iterator<Item = Sign> {
+        self.marks.iter().map(|(name, (row, _))| Sign {
+            row: *row,
+            kind: SignKind::Mark,
+            symbol: *name,
+            style: MARK_SIGN,
+        })
+    }
+}
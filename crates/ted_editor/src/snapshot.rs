@@ -0,0 +1,29 @@
+use crate::document::Document;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A buffer as kept in the session: where it came from, where the cursor
+/// was and, for hot exit, the edits that were never saved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufferSnapshot {
+    pub path: Option<PathBuf>,
+    /// `(row, column)`
+    pub cursor: (usize, usize),
+    /// The buffer text, only present while it differs from the file.
+    #[serde(default)]
+    pub lines: Option<Vec<String>>,
+}
+
+impl BufferSnapshot {
+    pub(crate) fn capture(
+        document: &Document,
+        lines: &[String],
+        cursor: (usize, usize),
+    ) -> Self {
+        Self {
+            path: document.path().map(|p| p.to_path_buf()),
+            cursor,
+            lines: document.is_dirty(lines).then(|| lines.to_vec()),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::buffer_list::BufferList;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{fs, io, process};
+use ted_common::state;
+
+/// What a swap file holds. A swap without `lines` only marks the file as
+/// open; one with `lines` also carries unsaved edits.
+#[derive(Debug, Serialize, Deserialize)]
+struct SwapFile {
+    pid: u32,
+    path: PathBuf,
+    #[serde(default)]
+    lines: Option<Vec<String>>,
+}
+
+/// What was found when checking a file for a leftover swap file.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SwapStatus {
+    /// No swap file, or a stale one without edits.
+    Clean,
+    /// Another running ted has the file open.
+    InUse { pid: u32 },
+    /// A ted that is no longer running left unsaved edits behind.
+    Recoverable { lines: Vec<String> },
+}
+
+/// Keeps a swap file for every buffer this process owns under
+/// `$XDG_STATE_HOME/ted/swap`, so unsaved edits survive a crash. Edits are
+/// written at most every `INTERVAL`.
+pub struct SwapFiles {
+    /// Hash of the edits last written for each owned file, `None` while the
+    /// buffer is clean.
+    owned: HashMap<PathBuf, Option<u64>>,
+    last_write: Instant,
+}
+
+impl SwapFiles {
+    pub const INTERVAL: Duration = Duration::from_secs(4);
+
+    pub fn new() -> Self {
+        Self { owned: HashMap::new(), last_write: Instant::now() }
+    }
+
+    pub fn owns(&self, path: &Path) -> bool {
+        self.owned.contains_key(path)
+    }
+
+    /// Looks for a swap file left for `path` by another ted.
+    pub fn check(path: &Path) -> SwapStatus {
+        let Some(swap) = read(path) else {
+            return SwapStatus::Clean;
+        };
+        if swap.pid != process::id() && process_alive(swap.pid) {
+            return SwapStatus::InUse { pid: swap.pid };
+        }
+        match swap.lines {
+            Some(lines) => SwapStatus::Recoverable { lines },
+            None => SwapStatus::Clean,
+        }
+    }
+
+    /// Takes over the swap file for `path`, replacing whatever was there.
+    pub fn claim(&mut self, path: &Path) -> io::Result<()> {
+        write(path, None)?;
+        self.owned.insert(path.to_path_buf(), None);
+        Ok(())
+    }
+
+    /// Removes the swap file of a buffer that was closed cleanly.
+    pub fn release(&mut self, path: &Path) -> io::Result<()> {
+        if self.owned.remove(path).is_some() {
+            remove(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn release_all(&mut self) -> io::Result<()> {
+        for path in std::mem::take(&mut self.owned).into_keys() {
+            remove(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the edits of owned buffers that changed since the last write,
+    /// once `INTERVAL` has passed.
+    pub fn tick(&mut self, buffers: &BufferList) -> io::Result<()> {
+        if self.last_write.elapsed() < Self::INTERVAL {
+            return Ok(());
+        }
+        self.last_write = Instant::now();
+        for buffer in buffers.iter() {
+            let Some(path) = buffer.path() else {
+                continue;
+            };
+            let Some(written) = self.owned.get_mut(path) else {
+                continue;
+            };
+            let lines = buffer.is_dirty().then(|| buffer.lines());
+            let hash = lines.as_ref().map(|lines| {
+                let mut hasher = DefaultHasher::new();
+                lines.hash(&mut hasher);
+                hasher.finish()
+            });
+            if *written != hash {
+                write(path, lines)?;
+                *written = hash;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for SwapFiles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Deletes the swap file for `path` without recovering it.
+pub fn discard(path: &Path) -> io::Result<()> {
+    remove(path)
+}
+
+fn swap_name(path: &Path) -> String {
+    format!("swap/{}.swp", state::escape_path(path))
+}
+
+fn read(path: &Path) -> Option<SwapFile> {
+    let file = state::state_dir()?.join(swap_name(path));
+    toml::from_str(&fs::read_to_string(file).ok()?).ok()
+}
+
+fn write(path: &Path, lines: Option<Vec<String>>) -> io::Result<()> {
+    let swap = SwapFile { pid: process::id(), path: path.to_path_buf(), lines };
+    state::save(&swap_name(path), &swap)
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    let Some(dir) = state::state_dir() else {
+        return Ok(());
+    };
+    match fs::remove_file(dir.join(swap_name(path))) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Without a cheap liveness check every leftover swap is treated as stale.
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    false
+}
@@ -0,0 +1,126 @@
+use super::theme;
+use ratatui::style::Style;
+use std::sync::LazyLock;
+use tree_sitter::{Language, Query};
+
+const RUST_INJECTIONS: &str =
+    include_str!("../../../../assets/queries/rust/injections.scm");
+const RUSTDOC_INJECTIONS: &str =
+    include_str!("../../../../assets/queries/rustdoc/injections.scm");
+const RUST_FOLDS: &str =
+    include_str!("../../../../assets/queries/rust/folds.scm");
+const TOML_FOLDS: &str =
+    include_str!("../../../../assets/queries/toml/folds.scm");
+const JSON_FOLDS: &str =
+    include_str!("../../../../assets/queries/json/folds.scm");
+const MARKDOWN_FOLDS: &str =
+    include_str!("../../../../assets/queries/markdown/folds.scm");
+
+/// A bundled grammar with the queries ted highlights it with.
+pub(crate) struct LanguageConfig {
+    /// What injections call the language, like `rust` in a code fence.
+    pub(crate) name: &'static str,
+    /// Other names for it: file extensions and code fence tags.
+    aliases: &'static [&'static str],
+    pub(crate) language: Language,
+    pub(crate) highlights: Query,
+    pub(crate) injections: Option<Query>,
+    /// Captures `@fold` on the nodes that can be folded.
+    pub(crate) folds: Option<Query>,
+    /// The style of each capture in `highlights`, by capture index.
+    pub(crate) styles: Vec<Option<Style>>,
+}
+
+impl LanguageConfig {
+    fn new(
+        name: &'static str,
+        aliases: &'static [&'static str],
+        language: Language,
+        highlights: &str,
+        injections: &str,
+        folds: &str,
+    ) -> Self {
+        let highlights = Query::new(&language, highlights)
+            .expect("bundled highlight queries are valid");
+        let query = |source: &str| {
+            (!source.is_empty()).then(|| {
+                Query::new(&language, source)
+                    .expect("bundled queries are valid")
+            })
+        };
+        let (injections, folds) = (query(injections), query(folds));
+        let styles = highlights
+            .capture_names()
+            .iter()
+            .map(|n| theme::style(n))
+            .collect();
+        Self { name, aliases, language, highlights, injections, folds, styles }
+    }
+}
+
+static LANGUAGES: LazyLock<Vec<LanguageConfig>> = LazyLock::new(|| {
+    let rust_injections =
+        format!("{}\n{RUST_INJECTIONS}", tree_sitter_rust::INJECTIONS_QUERY);
+    vec![
+        LanguageConfig::new(
+            "rust",
+            &["rs"],
+            tree_sitter_rust::LANGUAGE.into(),
+            tree_sitter_rust::HIGHLIGHTS_QUERY,
+            &rust_injections,
+            RUST_FOLDS,
+        ),
+        LanguageConfig::new(
+            "toml",
+            &[],
+            tree_sitter_toml_ng::LANGUAGE.into(),
+            tree_sitter_toml_ng::HIGHLIGHTS_QUERY,
+            "",
+            TOML_FOLDS,
+        ),
+        LanguageConfig::new(
+            "json",
+            &[],
+            tree_sitter_json::LANGUAGE.into(),
+            tree_sitter_json::HIGHLIGHTS_QUERY,
+            "",
+            JSON_FOLDS,
+        ),
+        LanguageConfig::new(
+            "markdown",
+            &["md"],
+            tree_sitter_md::LANGUAGE.into(),
+            tree_sitter_md::HIGHLIGHT_QUERY_BLOCK,
+            tree_sitter_md::INJECTION_QUERY_BLOCK,
+            MARKDOWN_FOLDS,
+        ),
+        // What doc comments are written in.
+        LanguageConfig::new(
+            "rustdoc",
+            &[],
+            tree_sitter_md::LANGUAGE.into(),
+            tree_sitter_md::HIGHLIGHT_QUERY_BLOCK,
+            &format!(
+                "{}\n{RUSTDOC_INJECTIONS}",
+                tree_sitter_md::INJECTION_QUERY_BLOCK
+            ),
+            "",
+        ),
+        LanguageConfig::new(
+            "markdown_inline",
+            &[],
+            tree_sitter_md::INLINE_LANGUAGE.into(),
+            tree_sitter_md::HIGHLIGHT_QUERY_INLINE,
+            tree_sitter_md::INJECTION_QUERY_INLINE,
+            "",
+        ),
+    ]
+});
+
+/// The language called `name` or one of its aliases, case-insensitively.
+pub(crate) fn find(name: &str) -> Option<&'static LanguageConfig> {
+    let name = name.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|config| config.name == name || config.aliases.contains(&&*name))
+}
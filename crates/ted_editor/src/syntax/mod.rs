@@ -0,0 +1,585 @@
+mod language;
+mod theme;
+
+use crate::fold::Fold;
+use crate::text_edit::RowChange;
+use language::LanguageConfig;
+use ratatui::style::Style;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::Path;
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{InputEdit, Node, Parser, Point, QueryCursor, Tree};
+
+/// Injections inside injections inside injections are left to the colours
+/// of the text around them.
+const MAX_DEPTH: usize = 3;
+
+/// Tree-sitter highlighting for one buffer. The trees are kept between
+/// edits, so a change only reparses the part of the text it touched.
+pub struct Syntax {
+    config: &'static LanguageConfig,
+    parser: Parser,
+    tree: Option<Tree>,
+    /// The rows, each one followed by a line break.
+    source: String,
+    /// Where each row starts in `source`, and where the last one ends.
+    line_starts: Vec<usize>,
+    /// The languages injected into the rows highlighted last.
+    injections: Injections,
+    /// The rows highlighted last and their styles, until the text changes.
+    cache: Option<(Vec<usize>, Vec<Vec<Style>>)>,
+}
+
+impl Syntax {
+    /// Highlighting for the file's language, picked by its extension.
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        Self::for_language(extension)
+    }
+
+    /// Highlighting for a bundled language, like `rust` or `md`.
+    pub fn for_language(name: &str) -> Option<Self> {
+        let config = language::find(name)?;
+        let mut parser = Parser::new();
+        parser.set_language(&config.language).ok()?;
+        Some(Self {
+            config,
+            parser,
+            tree: None,
+            source: String::new(),
+            line_starts: vec![0],
+            injections: Injections::default(),
+            cache: None,
+        })
+    }
+
+    /// Brings the trees up to date with `lines`, working out which rows
+    /// changed. Editors that know what they edited call `edit` instead.
+    pub fn update<S: AsRef<str>>(&mut self, lines: &[S]) {
+        let new: Vec<&str> = lines.iter().map(AsRef::as_ref).collect();
+        let change = {
+            let old: Vec<&str> = self
+                .line_starts
+                .windows(2)
+                .map(|row| &self.source[row[0]..row[1] - 1])
+                .collect();
+            if self.tree.is_some() && old == new {
+                return;
+            }
+            RowChange::between(&old, &new)
+        };
+        self.edit(change, &new[change.above..new.len() - change.below]);
+    }
+
+    /// Replaces the rows between the ones `change` left alone with `rows`,
+    /// and reparses what that touched.
+    pub fn edit<S: AsRef<str>>(
+        &mut self,
+        change: RowChange,
+        rows: impl IntoIterator<Item = S>,
+    ) {
+        let old_rows = self.line_starts.len() - 1;
+        let above = change.above.min(old_rows);
+        let old_end_row = old_rows - change.below.min(old_rows - above);
+        let (start, old_end) =
+            (self.line_starts[above], self.line_starts[old_end_row]);
+        let mut text = String::new();
+        let mut starts = vec![];
+        for row in rows {
+            starts.push(start + text.len());
+            text.push_str(row.as_ref());
+            text.push('\n');
+        }
+        let (new_end, new_end_row) = (start + text.len(), above + starts.len());
+        self.source.replace_range(start..old_end, &text);
+        let tail: Vec<usize> = self.line_starts[old_end_row..]
+            .iter()
+            .map(|&at| at - old_end + new_end)
+            .collect();
+        self.line_starts.truncate(above);
+        self.line_starts.extend(starts);
+        self.line_starts.extend(tail);
+
+        let edit = InputEdit {
+            start_byte: start,
+            old_end_byte: old_end,
+            new_end_byte: new_end,
+            start_position: Point::new(above, 0),
+            old_end_position: Point::new(old_end_row, 0),
+            new_end_position: Point::new(new_end_row, 0),
+        };
+        if let Some(tree) = &mut self.tree {
+            tree.edit(&edit);
+        }
+        self.injections.edit(&edit);
+        self.tree = self.parser.parse(&self.source, self.tree.as_ref());
+        self.cache = None;
+    }
+
+    /// A style per char of each of `rows`, as of the last `update`.
+    pub fn highlight(&mut self, rows: &[usize]) -> Vec<Vec<Style>> {
+        if let Some((cached, styles)) = &self.cache
+            && cached == rows
+        {
+            return styles.clone();
+        }
+        let line = |row: usize| {
+            let Some(&start) = self.line_starts.get(row) else {
+                return self.source.len()..self.source.len();
+            };
+            let end = self
+                .line_starts
+                .get(row + 1)
+                .map_or(self.source.len(), |next| next - 1);
+            start..end
+        };
+        // Runs of adjacent rows are painted as one stretch of text.
+        let mut stretches: Vec<Stretch> = vec![];
+        for (idx, &row) in rows.iter().enumerate() {
+            match stretches.last_mut() {
+                Some(last) if idx > 0 && rows[idx - 1] + 1 == row => {
+                    last.bytes.end = line(row).end
+                }
+                _ => {
+                    stretches.push(Stretch { bytes: line(row), styles: vec![] })
+                }
+            }
+        }
+        for stretch in &mut stretches {
+            stretch.styles = vec![Style::new(); stretch.bytes.len()];
+        }
+        if let Some(tree) = &self.tree {
+            let layer = Layer { config: self.config, tree, ranges: &[ALL] };
+            layer.paint(&self.source, &mut stretches, 0, &mut self.injections);
+            self.injections.done();
+        }
+        let highlights: Vec<Vec<Style>> = rows
+            .iter()
+            .map(|&row| {
+                let range = line(row);
+                let Some(stretch) =
+                    stretches.iter().find(|s| s.bytes.contains(&range.start))
+                else {
+                    return vec![
+                        Style::new();
+                        self.source[range].chars().count()
+                    ];
+                };
+                self.source[range.clone()]
+                    .char_indices()
+                    .map(|(idx, _)| {
+                        stretch.styles[range.start + idx - stretch.bytes.start]
+                    })
+                    .collect()
+            })
+            .collect();
+        self.cache = Some((rows.to_vec(), highlights.clone()));
+        highlights
+    }
+
+    /// The rows of each node the language folds, the widest one where
+    /// several start on the same row.
+    pub fn folds(&self) -> Vec<Fold> {
+        let (Some(query), Some(tree)) = (&self.config.folds, &self.tree) else {
+            return vec![];
+        };
+        let mut ends = BTreeMap::new();
+        let mut cursor = QueryCursor::new();
+        let mut captures =
+            cursor.captures(query, tree.root_node(), self.source.as_bytes());
+        while let Some((found, idx)) = captures.next() {
+            let node = found.captures[*idx].node;
+            let (start, end) = (node.start_position(), node.end_position());
+            // A node ending with its line break ends on the row before.
+            let end = match end.column == 0 && end.row > start.row {
+                true => end.row - 1,
+                false => end.row,
+            };
+            if end > start.row {
+                let widest = ends.entry(start.row).or_insert(end);
+                *widest = end.max(*widest);
+            }
+        }
+        ends.into_iter().map(|(start, end)| Fold { start, end }).collect()
+    }
+}
+
+/// The whole text, as the root layer sees it.
+const ALL: tree_sitter::Range = tree_sitter::Range {
+    start_byte: 0,
+    end_byte: usize::MAX,
+    start_point: Point { row: 0, column: 0 },
+    end_point: Point { row: usize::MAX, column: usize::MAX },
+};
+
+/// Adjacent rows being highlighted, with a style per byte.
+struct Stretch {
+    bytes: Range<usize>,
+    styles: Vec<Style>,
+}
+
+impl Stretch {
+    fn overlaps(&self, range: &tree_sitter::Range) -> bool {
+        range.start_byte < self.bytes.end && range.end_byte > self.bytes.start
+    }
+}
+
+/// A parse of the text, or of the parts of it `ranges` covers when it is
+/// embedded in another language.
+struct Layer<'a> {
+    config: &'static LanguageConfig,
+    tree: &'a Tree,
+    ranges: &'a [tree_sitter::Range],
+}
+
+impl Layer<'_> {
+    /// Styles the `stretches` of `source`, then the languages injected
+    /// into them on top.
+    fn paint(
+        &self,
+        source: &str,
+        stretches: &mut [Stretch],
+        depth: usize,
+        injections: &mut Injections,
+    ) {
+        for stretch in stretches.iter_mut() {
+            self.paint_stretch(source, stretch);
+        }
+        if depth >= MAX_DEPTH {
+            return;
+        }
+        for (config, ranges) in self.injections(source, stretches) {
+            let Some(injected) =
+                injections.parse(source, config, ranges, depth + 1)
+            else {
+                continue;
+            };
+            let layer = Layer {
+                config,
+                tree: &injected.tree,
+                ranges: &injected.ranges,
+            };
+            layer.paint(source, stretches, depth + 1, injections);
+            injections.used.push(injected);
+        }
+    }
+
+    fn paint_stretch(&self, source: &str, stretch: &mut Stretch) {
+        let visible = &stretch.bytes;
+        let query = &self.config.highlights;
+        let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(visible.clone());
+        let mut matches =
+            cursor.matches(query, self.tree.root_node(), source.as_bytes());
+        let mut captures = vec![];
+        while let Some(found) = matches.next() {
+            for capture in found.captures {
+                if let Some(style) = self.config.styles[capture.index as usize]
+                {
+                    let range = capture.node.byte_range();
+                    captures.push((range, found.pattern_index, style));
+                }
+            }
+        }
+        // Outer nodes go first so the nodes inside them win; of several
+        // captures on one node, the first pattern wins.
+        captures.sort_by_key(|(range, pattern, _)| {
+            (range.start, Reverse(range.end), Reverse(*pattern))
+        });
+        for (range, _, style) in captures {
+            for covered in self.ranges {
+                let start =
+                    range.start.max(covered.start_byte).max(visible.start);
+                let end = range.end.min(covered.end_byte).min(visible.end);
+                for byte in start..end {
+                    let at = byte - visible.start;
+                    stretch.styles[at] = stretch.styles[at].patch(style);
+                }
+            }
+        }
+    }
+
+    /// The languages embedded in this layer that show in `stretches`,
+    /// with the ranges each one covers.
+    fn injections(
+        &self,
+        source: &str,
+        stretches: &[Stretch],
+    ) -> Vec<(&'static LanguageConfig, Vec<tree_sitter::Range>)> {
+        let Some(query) = &self.config.injections else {
+            return vec![];
+        };
+        let content = query.capture_index_for_name("injection.content");
+        let language = query.capture_index_for_name("injection.language");
+        let mut found = vec![];
+        // Combined injections are parsed as one text per pattern, so they
+        // are collected over the whole layer rather than what shows.
+        let mut combined = BTreeMap::new();
+        let mut cursor = QueryCursor::new();
+        let mut matches =
+            cursor.matches(query, self.tree.root_node(), source.as_bytes());
+        while let Some(found_match) = matches.next() {
+            let pattern = found_match.pattern_index;
+            let property = |key: &str| {
+                query.property_settings(pattern).iter().find(|p| &*p.key == key)
+            };
+            let name = found_match
+                .captures
+                .iter()
+                .find(|capture| Some(capture.index) == language)
+                .and_then(|capture| source.get(capture.node.byte_range()))
+                .or_else(|| property("injection.language")?.value.as_deref());
+            let Some(config) = name.and_then(language::find) else {
+                continue;
+            };
+            let include_children =
+                property("injection.include-children").is_some();
+            let mut ranges: Vec<tree_sitter::Range> = found_match
+                .captures
+                .iter()
+                .filter(|capture| Some(capture.index) == content)
+                .flat_map(|capture| node_ranges(capture.node, include_children))
+                .collect();
+            ranges = intersect(&ranges, self.ranges);
+            match property("injection.combined") {
+                Some(_) => combined
+                    .entry(pattern)
+                    .or_insert((config, vec![]))
+                    .1
+                    .extend(ranges),
+                None => found.push((config, ranges)),
+            }
+        }
+        found.extend(combined.into_values());
+        found.retain_mut(|(_, ranges)| {
+            ranges.sort_by_key(|range| range.start_byte);
+            ranges
+                .iter()
+                .any(|range| stretches.iter().any(|s| s.overlaps(range)))
+        });
+        found
+    }
+}
+
+/// The trees of injected languages, kept from one highlight to the next
+/// so an edit only reparses the ones it touched.
+#[derive(Default)]
+struct Injections {
+    /// What the last highlight parsed, moved along by edits since.
+    kept: Vec<Injected>,
+    /// What this highlight has parsed so far.
+    used: Vec<Injected>,
+}
+
+struct Injected {
+    depth: usize,
+    config: &'static LanguageConfig,
+    ranges: Vec<tree_sitter::Range>,
+    tree: Tree,
+    /// Whether no edit has touched the ranges since they were parsed.
+    clean: bool,
+}
+
+impl Injections {
+    /// Moves the kept trees along with `edit`, marking the ones it touched.
+    fn edit(&mut self, edit: &InputEdit) {
+        for injected in &mut self.kept {
+            injected.tree.edit(edit);
+            for range in &mut injected.ranges {
+                if range.start_byte >= edit.old_end_byte {
+                    range.start_byte = range.start_byte - edit.old_end_byte
+                        + edit.new_end_byte;
+                    range.end_byte =
+                        range.end_byte - edit.old_end_byte + edit.new_end_byte;
+                    range.start_point = shift(range.start_point, edit);
+                    range.end_point = shift(range.end_point, edit);
+                } else if range.end_byte > edit.start_byte {
+                    injected.clean = false;
+                }
+            }
+        }
+    }
+
+    /// The tree of `config` over `ranges`: the kept one if nothing changed,
+    /// or a reparse of it that starts from where it was.
+    fn parse(
+        &mut self,
+        source: &str,
+        config: &'static LanguageConfig,
+        ranges: Vec<tree_sitter::Range>,
+        depth: usize,
+    ) -> Option<Injected> {
+        let start = ranges.first().map(|range| range.start_byte);
+        let old = self
+            .kept
+            .iter()
+            .position(|kept| {
+                kept.depth == depth
+                    && std::ptr::eq(kept.config, config)
+                    && kept.ranges.first().map(|range| range.start_byte)
+                        == start
+            })
+            .map(|idx| self.kept.swap_remove(idx));
+        if let Some(kept) = &old
+            && kept.clean
+            && kept.ranges == ranges
+        {
+            return old;
+        }
+        let mut parser = Parser::new();
+        parser.set_language(&config.language).ok()?;
+        parser.set_included_ranges(&ranges).ok()?;
+        let tree = parser.parse(source, old.as_ref().map(|old| &old.tree))?;
+        Some(Injected { depth, config, ranges, tree, clean: true })
+    }
+
+    /// Keeps what the highlight just parsed, and drops what it didn't need.
+    fn done(&mut self) {
+        self.kept = std::mem::take(&mut self.used);
+    }
+}
+
+/// Where `point` ends up after `edit`, for a point past what it replaced.
+fn shift(point: Point, edit: &InputEdit) -> Point {
+    let (old_end, new_end) = (edit.old_end_position, edit.new_end_position);
+    match point.row == old_end.row {
+        true => Point::new(
+            new_end.row,
+            point.column - old_end.column + new_end.column,
+        ),
+        false => {
+            Point::new(point.row - old_end.row + new_end.row, point.column)
+        }
+    }
+}
+
+/// The text of `node`, without the text of its named children unless
+/// `include_children`.
+fn node_ranges(node: Node, include_children: bool) -> Vec<tree_sitter::Range> {
+    let mut ranges = vec![];
+    let mut range = node.range();
+    if !include_children {
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            let before = tree_sitter::Range {
+                end_byte: child.start_byte(),
+                end_point: child.start_position(),
+                ..range
+            };
+            if before.start_byte < before.end_byte {
+                ranges.push(before);
+            }
+            range.start_byte = child.end_byte();
+            range.start_point = child.end_position();
+        }
+    }
+    if range.start_byte < range.end_byte {
+        ranges.push(range);
+    }
+    ranges
+}
+
+/// The parts of `ranges` that are also inside one of `within`.
+fn intersect(
+    ranges: &[tree_sitter::Range],
+    within: &[tree_sitter::Range],
+) -> Vec<tree_sitter::Range> {
+    let mut found = vec![];
+    for range in ranges {
+        for outer in within {
+            let mut both = *range;
+            if outer.start_byte > both.start_byte {
+                both.start_byte = outer.start_byte;
+                both.start_point = outer.start_point;
+            }
+            if outer.end_byte < both.end_byte {
+                both.end_byte = outer.end_byte;
+                both.end_point = outer.end_point;
+            }
+            if both.start_byte < both.end_byte {
+                found.push(both);
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styled(syntax: &mut Syntax, row: usize, col: usize) -> Style {
+        syntax.highlight(&[row])[0][col]
+    }
+
+    #[test]
+    fn highlights_and_reparses_on_edits() {
+        let mut syntax = Syntax::for_path(Path::new("lib.rs")).unwrap();
+        syntax.update(&["fn main() {", "    x;", "}"]);
+        let plain = styled(&mut syntax, 1, 4);
+        assert_ne!(styled(&mut syntax, 0, 0), plain);
+        let rows = syntax.highlight(&[0, 2]);
+        assert_eq!((rows.len(), rows[1].len()), (2, 1));
+
+        syntax.update(&["fn main() {", "    let x;", "}"]);
+        assert_eq!(styled(&mut syntax, 1, 4), styled(&mut syntax, 0, 0));
+        assert_eq!(syntax.highlight(&[1])[0].len(), 10);
+    }
+
+    #[test]
+    fn highlights_injected_languages() {
+        let mut syntax = Syntax::for_language("rust").unwrap();
+        syntax.update(&[
+            "/// # Title",
+            "/// ```",
+            "/// fn f() {}",
+            "/// ```",
+            "fn g() {}",
+        ]);
+        let keyword = styled(&mut syntax, 4, 0);
+        let comment = styled(&mut syntax, 1, 5);
+        // `fn` in the doc comment's code fence is Rust again.
+        assert_eq!(styled(&mut syntax, 2, 4), keyword);
+        assert_ne!(styled(&mut syntax, 0, 6), comment);
+
+        let mut syntax = Syntax::for_path(Path::new("README.md")).unwrap();
+        syntax.update(&["text", "", "```toml", "key = \"value\"", "```", ""]);
+        assert_ne!(styled(&mut syntax, 3, 8), styled(&mut syntax, 3, 0));
+    }
+
+    #[test]
+    fn folds_nodes() {
+        for language in ["rs", "toml", "json", "md"] {
+            assert!(Syntax::for_language(language).is_some());
+        }
+        let mut syntax = Syntax::for_language("rust").unwrap();
+        syntax.update(&["fn f() {", "    if x {", "        y", "    }", "}"]);
+        assert_eq!(
+            syntax.folds(),
+            [Fold { start: 0, end: 4 }, Fold { start: 1, end: 3 }]
+        );
+        let mut syntax = Syntax::for_language("md").unwrap();
+        syntax.update(&["# A", "text", "", "# B"]);
+        assert_eq!(syntax.folds(), [Fold { start: 0, end: 2 }]);
+    }
+
+    #[test]
+    fn edits_highlight_like_a_fresh_parse() {
+        let before = ["text", "", "```toml", "key = 1", "```", "", "more"];
+        let after = ["text", "", "```toml", "key = \"value\"", "```", "new"];
+        let rows: Vec<usize> = (0..after.len()).collect();
+        let mut edited = Syntax::for_language("md").unwrap();
+        edited.update(&before);
+        edited.highlight(&rows);
+        edited.edit(RowChange { above: 3, below: 0 }, &after[3..]);
+        let mut fresh = Syntax::for_language("md").unwrap();
+        fresh.update(&after);
+        assert_eq!(edited.highlight(&rows), fresh.highlight(&rows));
+        assert_ne!(styled(&mut edited, 3, 8), styled(&mut edited, 3, 0));
+        // Rows moved along by an edit above keep their injected colours.
+        edited.edit(RowChange { above: 0, below: 5 }, ["title", "", ""]);
+        assert_eq!(styled(&mut edited, 5, 8), styled(&mut fresh, 3, 8));
+    }
+}
@@ -0,0 +1,97 @@
+use edtui::syntect::highlighting::{FontStyle, Highlighter, Theme, ThemeSet};
+use edtui::syntect::parsing::Scope;
+use ratatui::style::{Color, Modifier, Style};
+use std::sync::LazyLock;
+
+static THEME: LazyLock<Theme> = LazyLock::new(|| {
+    let mut themes = ThemeSet::load_defaults().themes;
+    themes.remove("base16-ocean.dark").expect("bundled with syntect")
+});
+
+/// Tree-sitter capture names and the TextMate scopes themes know them by.
+/// A capture takes the scope of its longest listed prefix, so
+/// `function.method` falls back to `function`.
+const SCOPES: &[(&str, &str)] = &[
+    ("attribute", "entity.other.attribute-name"),
+    ("boolean", "constant.language"),
+    ("character", "constant.character"),
+    ("comment", "comment"),
+    ("constant", "constant"),
+    ("constant.builtin", "constant.language"),
+    ("constructor", "entity.name.class"),
+    ("escape", "constant.character.escape"),
+    ("function", "entity.name.function"),
+    ("function.macro", "entity.name.function.macro"),
+    ("keyword", "keyword"),
+    ("label", "entity.name.label"),
+    ("number", "constant.numeric"),
+    ("operator", "keyword.operator"),
+    ("property", "variable.other.member"),
+    ("punctuation", "punctuation"),
+    ("string", "string"),
+    ("string.escape", "constant.character.escape"),
+    ("string.special", "string.regexp"),
+    ("tag", "entity.name.tag"),
+    ("text.emphasis", "markup.italic"),
+    ("text.literal", "markup.raw.inline"),
+    ("text.reference", "string.other.link"),
+    ("text.strong", "markup.bold"),
+    ("text.title", "entity.name.section"),
+    ("text.uri", "meta.link"),
+    ("type", "entity.name.class"),
+    ("type.builtin", "storage.type"),
+    ("variable.builtin", "variable.language"),
+    ("variable.parameter", "variable.parameter"),
+];
+
+/// The theme's scope for a capture name, if ted knows one.
+pub(crate) fn scope(capture: &str) -> Option<&'static str> {
+    SCOPES
+        .iter()
+        .filter(|(prefix, _)| {
+            capture == *prefix
+                || capture
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, scope)| *scope)
+}
+
+/// How the theme draws a capture; `None` leaves the text as it is, so an
+/// enclosing capture shows through.
+pub(crate) fn style(capture: &str) -> Option<Style> {
+    let scope = Scope::new(scope(capture)?).ok()?;
+    let found = Highlighter::new(&THEME).style_mod_for_stack(&[scope]);
+    let mut style = Style::new();
+    if let Some(fg) = found.foreground {
+        style = style.fg(Color::Rgb(fg.r, fg.g, fg.b));
+    }
+    let font = found.font_style.unwrap_or(FontStyle::empty());
+    for (font_style, modifier) in [
+        (FontStyle::BOLD, Modifier::BOLD),
+        (FontStyle::ITALIC, Modifier::ITALIC),
+        (FontStyle::UNDERLINE, Modifier::UNDERLINED),
+    ] {
+        if font.contains(font_style) {
+            style = style.add_modifier(modifier);
+        }
+    }
+    (style != Style::new()).then_some(style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_captures_to_scopes() {
+        assert_eq!(scope("function.method"), Some("entity.name.function"));
+        assert_eq!(scope("function.macro"), Some("entity.name.function.macro"));
+        assert_eq!(scope("string.escape"), Some("constant.character.escape"));
+        assert_eq!(scope("typeface"), None);
+        assert_eq!(scope("none"), None);
+        assert!(style("keyword").is_some_and(|s| s.fg.is_some()));
+        assert_eq!(style("none"), None);
+    }
+}
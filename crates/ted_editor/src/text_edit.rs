@@ -0,0 +1,141 @@
+/// Replaces the text from `start` up to `end` with `text`. Positions are
+/// `(row, column)` in chars, like the editor cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub text: String,
+}
+
+impl TextEdit {
+    /// How many rows the edit adds, or removes if negative.
+    pub fn row_delta(&self) -> isize {
+        let added = self.text.matches('\n').count() as isize;
+        added - (self.end.0 - self.start.0) as isize
+    }
+
+    /// Where the inserted text ends once the edit is applied.
+    pub fn end_after(&self) -> (usize, usize) {
+        end_of(self.start, &self.text)
+    }
+}
+
+/// Where `text` ends if inserted at `start`.
+pub fn end_of(start: (usize, usize), text: &str) -> (usize, usize) {
+    match text.rsplit_once('\n') {
+        Some((before, last)) => {
+            (start.0 + before.matches('\n').count() + 1, last.chars().count())
+        }
+        None => (start.0, start.1 + text.chars().count()),
+    }
+}
+
+/// Applies `edits` to `lines`. Edits must not overlap; they are applied
+/// back to front so each one's positions refer to the original text.
+/// Positions past the end are clamped to it.
+pub fn apply(lines: &mut Vec<String>, edits: &[TextEdit]) {
+    let mut edits: Vec<&TextEdit> = edits.iter().collect();
+    edits.sort_by_key(|edit| (edit.start, edit.end));
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    for edit in edits.into_iter().rev() {
+        let clamp = |(row, col): (usize, usize)| {
+            let row = row.min(lines.len() - 1);
+            (row, col.min(lines[row].chars().count()))
+        };
+        let (start, end) = (clamp(edit.start), clamp(edit.end.max(edit.start)));
+        let before: String = lines[start.0].chars().take(start.1).collect();
+        let after: String = lines[end.0].chars().skip(end.1).collect();
+        let text = format!("{before}{}{after}", edit.text);
+        let replacement: Vec<String> =
+            text.split('\n').map(str::to_string).collect();
+        lines.splice(start.0..=end.0, replacement);
+    }
+}
+
+/// The rows an edit left alone at either end of the text; everything
+/// between them was replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowChange {
+    pub above: usize,
+    pub below: usize,
+}
+
+impl RowChange {
+    /// A change that may have touched every row.
+    pub const ALL: Self = Self { above: 0, below: 0 };
+
+    /// The change from `old` to `new`: whatever lies between the rows they
+    /// start with and the rows they end with.
+    pub fn between<T: PartialEq>(old: &[T], new: &[T]) -> Self {
+        let above = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let room = old.len().min(new.len()) - above;
+        let below = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take(room)
+            .take_while(|(a, b)| a == b)
+            .count();
+        Self { above, below }
+    }
+
+    /// This change followed by `next`, as one change.
+    pub fn then(self, next: Self) -> Self {
+        Self {
+            above: self.above.min(next.above),
+            below: self.below.min(next.below),
+        }
+    }
+}
+
+/// Where rows of `old` went in `new`, taking what changed to be the lines
+/// between the ones they start and end with.
+pub(crate) fn follow_rows(
+    old: &[String],
+    new: &[String],
+) -> impl Fn(usize) -> usize + use<> {
+    let RowChange { above: prefix, below: suffix } =
+        RowChange::between(old, new);
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    move |row| {
+        if row < prefix {
+            row
+        } else if row >= old_end {
+            row - old_end + new_end
+        } else {
+            prefix + (row - prefix).min((new_end - prefix).saturating_sub(1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_edits_back_to_front() {
+        let mut lines = vec!["fn main() {".to_string(), "    foo();".into()];
+        let edits = [
+            TextEdit { start: (1, 4), end: (1, 7), text: "bar".into() },
+            TextEdit { start: (0, 0), end: (0, 0), text: "use x;\n".into() },
+            TextEdit { start: (1, 10), end: (1, 99), text: "\n}".into() },
+        ];
+        apply(&mut lines, &edits);
+        assert_eq!(lines, ["use x;", "fn main() {", "    bar();", "}"]);
+        assert_eq!(edits[1].row_delta(), 1);
+        assert_eq!(edits[2].end_after(), (2, 1));
+    }
+
+    #[test]
+    fn finds_the_rows_a_change_left_alone() {
+        let change = RowChange::between(&["a", "b", "c"], &["a", "x", "c"]);
+        assert_eq!(change, RowChange { above: 1, below: 1 });
+        // Repeated rows count once, on whichever side comes first.
+        let change = RowChange::between(&["a", "a"], &["a", "a", "a"]);
+        assert_eq!(change, RowChange { above: 2, below: 0 });
+        let next = RowChange { above: 0, below: 2 };
+        assert_eq!(change.then(next), RowChange::ALL);
+    }
+}
@@ -1 +1,3 @@
-pub mod vim_editor;
\ No newline at end of file
+mod view;
+#[allow(clippy::module_inception)]
+pub mod vim_editor;
@@ -0,0 +1,391 @@
+use crate::diagnostic::Diagnostic;
+use crate::fold::Folds;
+use crate::gutter::{Gutter, GutterConfig, Sign, SignKind};
+use crate::syntax::Syntax;
+use edtui::{EditorMode, EditorState, EditorStatusLine, EditorTheme, Index2};
+use ratatui::prelude::*;
+use std::path::Path;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const TAB_WIDTH: usize = 4;
+/// Space between the end of a line and its diagnostic message.
+const VIRTUAL_TEXT_GAP: usize = 2;
+const FOLD_MARKER: Style = Style::new().fg(Color::DarkGray);
+
+/// Draws an edtui `EditorState` with ted's own scrolling. edtui's
+/// `EditorView` keeps its scroll offset private, which leaves no way to put
+/// diagnostics or other decorations on the text it drew.
+#[derive(Default)]
+pub(crate) struct View {
+    /// First visible row.
+    top: usize,
+    /// First visible display column.
+    left: usize,
+    /// Where the text went on the last draw, for mouse hits and paging.
+    text_area: Rect,
+    syntax: Option<Syntax>,
+    gutter: Gutter,
+}
+
+impl View {
+    /// Picks the language to highlight by the file's extension.
+    pub(crate) fn set_path(&mut self, path: Option<&Path>) {
+        self.syntax = path.and_then(Syntax::for_path);
+    }
+
+    pub(crate) fn set_gutter(&mut self, config: GutterConfig) {
+        self.gutter.set_config(config);
+    }
+
+    /// The row whose fold marker was drawn at a screen cell, if any.
+    pub(crate) fn fold_at(&self, column: u16, row: u16) -> Option<usize> {
+        self.gutter.fold_at(column, row)
+    }
+
+    /// The highlighter, which also knows where the file folds.
+    pub(crate) fn syntax(&mut self) -> Option<&mut Syntax> {
+        self.syntax.as_mut()
+    }
+
+    /// Rows of text on screen as of the last draw.
+    pub(crate) fn height(&self) -> usize {
+        self.text_area.height as usize
+    }
+
+    /// The text position under a screen cell, clamped to the text.
+    pub(crate) fn position_at(
+        &self,
+        state: &EditorState,
+        folds: &Folds,
+        column: u16,
+        row: u16,
+    ) -> Index2 {
+        let area = self.text_area;
+        let y = row.saturating_sub(area.y) as usize;
+        let row =
+            folds.visible_rows(self.top).take(y + 1).last().unwrap_or_default();
+        let x = self.left + column.saturating_sub(area.x) as usize;
+        let chars =
+            state.lines.iter_row().nth(row).cloned().unwrap_or_default();
+        let mut width = 0;
+        let col = chars
+            .iter()
+            .position(|ch| {
+                width += char_width(*ch, width);
+                width > x
+            })
+            .unwrap_or(chars.len());
+        Index2::new(row, col.min(chars.len().saturating_sub(1)))
+    }
+
+    /// Where the char at `(row, col)` was drawn, if it was on screen.
+    pub(crate) fn screen_position(
+        &self,
+        state: &EditorState,
+        folds: &Folds,
+        row: usize,
+        col: usize,
+    ) -> Option<Position> {
+        let y = folds
+            .visible_rows(self.top)
+            .take(self.text_area.height as usize)
+            .position(|shown| shown == row)?;
+        let chars = state.lines.iter_row().nth(row)?;
+        let x = self.screen_x(display_width(&chars[..col.min(chars.len())]))?;
+        Some(Position::new(x, self.text_area.y + y as u16))
+    }
+
+    /// Draws the text with `signs` in the gutter, besides those of the
+    /// diagnostics.
+    pub(crate) fn draw(
+        &mut self,
+        state: &EditorState,
+        diagnostics: &[Diagnostic],
+        signs: &[Sign],
+        folds: &Folds,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
+        let theme = EditorTheme::default();
+        buf.set_style(area, theme.base);
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)])
+                .areas(area);
+        let line_count = state.lines.len();
+        let [gutter, text] = Layout::horizontal([
+            Constraint::Length(self.gutter.width(line_count)),
+            Constraint::Min(0),
+        ])
+        .areas(main);
+        self.text_area = text;
+
+        let cursor = clamped_cursor(state);
+        self.scroll_to(state, folds, cursor);
+        let shown: Vec<usize> =
+            folds.visible_rows(self.top).take(main.height as usize).collect();
+        let mut by_severity: Vec<&Diagnostic> = diagnostics.iter().collect();
+        by_severity.sort_by_key(|d| d.severity);
+        // Of signs alike the gutter shows the last, so the worst wins.
+        let signs: Vec<Sign> = signs
+            .iter()
+            .copied()
+            .chain(by_severity.iter().map(|d| Sign {
+                row: d.start.0,
+                kind: SignKind::Diagnostic,
+                symbol: d.severity.sign(),
+                style: Style::new().fg(d.severity.color()),
+            }))
+            .collect();
+        self.gutter.draw(gutter, &shown, cursor.row, &signs, Some(folds), buf);
+        let highlights = match &mut self.syntax {
+            Some(syntax) => syntax.highlight(&shown),
+            None => vec![],
+        };
+        let pattern: Vec<char> = match state.mode {
+            EditorMode::Search => state.search_pattern().chars().collect(),
+            _ => vec![],
+        };
+
+        let rows: Vec<&Vec<char>> = state.lines.iter_row().collect();
+        for (idx, (y, &row)) in (main.y..).zip(&shown).enumerate() {
+            let chars = rows[row];
+            let mut styles = highlights
+                .get(idx)
+                .cloned()
+                .unwrap_or_else(|| vec![Style::new(); chars.len()]);
+            for (col, style) in styles.iter_mut().enumerate() {
+                let index = Index2::new(row, col);
+                if state.selection.as_ref().is_some_and(|s| s.contains(&index))
+                {
+                    *style = style.patch(theme.selection_style);
+                }
+                let worst = diagnostics
+                    .iter()
+                    .filter(|d| d.covers(row, col))
+                    .map(|d| d.severity)
+                    .max();
+                if let Some(severity) = worst {
+                    *style = style
+                        .add_modifier(Modifier::UNDERLINED)
+                        .underline_color(severity.color());
+                }
+            }
+            for start in matches(chars, &pattern) {
+                for style in &mut styles[start..start + pattern.len()] {
+                    *style = style.patch(theme.selection_style);
+                }
+            }
+
+            let mut end = self.draw_line(chars, &styles, y, buf);
+            if let Some(fold) = folds.closed_at(row) {
+                let summary = format!("⋯ {} lines", fold.end - fold.start);
+                end = self.draw_after(end, y, &summary, FOLD_MARKER, buf);
+            }
+            if row == cursor.row {
+                let x = display_width(&chars[..cursor.col.min(chars.len())]);
+                if let Some(x) = self.screen_x(x) {
+                    buf.set_style(Rect::new(x, y, 1, 1), theme.cursor_style);
+                }
+            }
+
+            let worst = diagnostics
+                .iter()
+                .filter(|d| d.start.0 == row)
+                .max_by_key(|d| d.severity);
+            if let Some(diagnostic) = worst {
+                let style = Style::new().fg(diagnostic.severity.color());
+                let message =
+                    diagnostic.message.lines().next().unwrap_or_default();
+                let style =
+                    style.add_modifier(Modifier::DIM | Modifier::ITALIC);
+                self.draw_after(end, y, &format!("■ {message}"), style, buf);
+            }
+        }
+
+        EditorStatusLine::default()
+            .mode(state.mode.name())
+            .search(
+                (state.mode == EditorMode::Search)
+                    .then(|| state.search_pattern()),
+            )
+            .render(status, buf);
+    }
+
+    /// Draws one row of text and returns the display width it takes.
+    fn draw_line(
+        &self,
+        chars: &[char],
+        styles: &[Style],
+        y: u16,
+        buf: &mut Buffer,
+    ) -> usize {
+        let mut x = 0;
+        for (ch, style) in chars.iter().zip(styles) {
+            let width = char_width(*ch, x);
+            let symbol = match ch {
+                '\t' => " ".repeat(width),
+                ch => ch.to_string(),
+            };
+            if let Some(screen_x) = self.screen_x(x)
+                && self.screen_x(x + width - 1).is_some()
+            {
+                buf.set_stringn(screen_x, y, symbol, width, *style);
+            }
+            x += width;
+        }
+        x
+    }
+
+    /// Puts `text` after the end of a row drawn up to display column
+    /// `end`, like a diagnostic message, and returns where it ends.
+    fn draw_after(
+        &self,
+        end: usize,
+        y: u16,
+        text: &str,
+        style: Style,
+        buf: &mut Buffer,
+    ) -> usize {
+        let x = end + VIRTUAL_TEXT_GAP;
+        if let Some(screen_x) = self.screen_x(x) {
+            let room = (self.text_area.right() - screen_x) as usize;
+            buf.set_stringn(screen_x, y, text, room, style);
+        }
+        x + text.width()
+    }
+
+    /// Where display column `x` lands on screen, if it is visible.
+    fn screen_x(&self, x: usize) -> Option<u16> {
+        let x = x.checked_sub(self.left)?;
+        (x < self.text_area.width as usize).then(|| self.text_area.x + x as u16)
+    }
+
+    /// Scrolls just enough to show the cursor, counting a closed fold as
+    /// one line.
+    fn scroll_to(
+        &mut self,
+        state: &EditorState,
+        folds: &Folds,
+        cursor: Index2,
+    ) {
+        let height = self.text_area.height as usize;
+        let width = self.text_area.width as usize;
+        let row = folds.shown_at(cursor.row);
+        self.top = folds.shown_at(self.top);
+        if row < self.top {
+            self.top = row;
+        } else if height > 0
+            && folds.visible_rows(self.top).take_while(|r| *r < row).count()
+                >= height
+        {
+            self.top = folds.step(row, height - 1, false);
+        }
+        let chars = state.lines.iter_row().nth(cursor.row);
+        let x =
+            chars.map_or(0, |c| display_width(&c[..cursor.col.min(c.len())]));
+        if x < self.left {
+            self.left = x;
+        } else if width > 0 && x >= self.left + width {
+            self.left = x + 1 - width;
+        }
+    }
+}
+
+/// The whole text, to parse.
+/// The cursor as drawn: past the last char only in insert mode.
+fn clamped_cursor(state: &EditorState) -> Index2 {
+    let len = state.lines.iter_row().nth(state.cursor.row).map_or(0, Vec::len);
+    let max = match state.mode {
+        EditorMode::Insert => len,
+        _ => len.saturating_sub(1),
+    };
+    Index2::new(state.cursor.row, state.cursor.col.min(max))
+}
+
+/// How wide `ch` is when drawn at display column `x`; tabs stretch to the
+/// next tab stop.
+fn char_width(ch: char, x: usize) -> usize {
+    match ch {
+        '\t' => TAB_WIDTH - x % TAB_WIDTH,
+        ch => ch.width().unwrap_or(0).max(1),
+    }
+}
+
+fn display_width(chars: &[char]) -> usize {
+    chars.iter().fold(0, |x, ch| x + char_width(*ch, x))
+}
+
+/// Where `pattern` starts in `chars`, without overlaps.
+fn matches(chars: &[char], pattern: &[char]) -> Vec<usize> {
+    let mut found = vec![];
+    let mut start = 0;
+    while !pattern.is_empty() && start + pattern.len() <= chars.len() {
+        if chars[start..].starts_with(pattern) {
+            found.push(start);
+            start += pattern.len();
+        } else {
+            start += 1;
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Severity;
+    use edtui::Lines;
+
+    #[test]
+    fn draws_diagnostics_on_the_text() {
+        let mut state = EditorState::new(Lines::from("let x;\n\tfoo()"));
+        state.cursor = Index2::new(1, 1);
+        let diagnostics = [Diagnostic {
+            start: (1, 1),
+            end: (1, 4),
+            severity: Severity::Error,
+            message: "no foo\nsecond line".to_string(),
+            source: None,
+        }];
+        let area = Rect::new(0, 0, 30, 4);
+        let mut buf = Buffer::empty(area);
+        let mut folds = Folds::default();
+        folds.update(vec!["let x;".into(), "\tfoo()".into()], None);
+        let mut view = View::default();
+        view.draw(&state, &diagnostics, &[], &folds, area, &mut buf);
+
+        assert_eq!(buf[(0, 1)].symbol(), "E");
+        // The tab takes the first four columns after the gutter.
+        assert_eq!(buf[(12, 1)].symbol(), "f");
+        assert!(buf[(12, 1)].modifier.contains(Modifier::UNDERLINED));
+        assert!(!buf[(15, 1)].modifier.contains(Modifier::UNDERLINED));
+        let text: String =
+            (19..27).map(|x| buf[(x, 1)].symbol().to_string()).collect();
+        assert_eq!(text, "■ no foo");
+        assert_eq!(view.position_at(&state, &folds, 13, 1), Index2::new(1, 2));
+    }
+
+    #[test]
+    fn draws_closed_folds_as_one_line() {
+        let text = "fn a() {\n    b;\n    c;\n}\nfn d() {}";
+        let state = EditorState::new(Lines::from(text));
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        let mut folds = Folds::default();
+        folds.update(lines, None);
+        folds.close(0);
+        let area = Rect::new(0, 0, 30, 4);
+        let mut buf = Buffer::empty(area);
+        let mut view = View::default();
+        view.draw(&state, &[], &[], &folds, area, &mut buf);
+
+        let row = |y| -> String {
+            (0..30).map(|x| buf[(x, y)].symbol().to_string()).collect()
+        };
+        assert_eq!(row(0).trim_end(), "  1   ▸ fn a() {  ⋯ 2 lines");
+        assert_eq!(row(1).trim_end(), "    1   }");
+        assert_eq!(row(2).trim_end(), "    2   fn d() {}");
+        assert_eq!(view.position_at(&state, &folds, 9, 1), Index2::new(3, 0));
+        assert_eq!(view.fold_at(6, 0), Some(0));
+        assert_eq!(view.fold_at(6, 1), None);
+    }
+}
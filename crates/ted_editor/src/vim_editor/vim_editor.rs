@@ -1,10 +1,56 @@
-use edtui::{EditorEventHandler, EditorState, EditorView, SyntaxHighlighter};
-use ratatui::crossterm::event::Event;
+use super::view::View;
+use crate::diagnostic::Diagnostic;
+use crate::diff::Hunk;
+use crate::document::Document;
+use crate::editor_status::EditorStatus;
+use crate::fold::{Fold, FoldMethod, Folds};
+use crate::git::{self, GitBase, GitChanges};
+use crate::gutter::{GutterConfig, Sign};
+use crate::mark::Marks;
+use crate::snapshot::BufferSnapshot;
+use crate::text_edit::{self, RowChange, TextEdit};
+use edtui::actions::motion::{MoveToFirstRow, MoveToLastRow};
+use edtui::actions::{
+    Action, DeleteLine, Execute, MoveBackward, MoveDown, MoveForward,
+    MoveToEndOfLine, MoveToStartOfLine, MoveUp, MoveWordBackward,
+    MoveWordForward, Paste, Redo, SwitchMode, Undo,
+};
+use edtui::{EditorEventHandler, EditorMode, EditorState, Index2, Lines};
+use ratatui::crossterm::event::{
+    Event, KeyCode, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
 use ratatui::prelude::*;
+use ratatui::widgets::Block;
+use std::io;
+use std::path::{Path, PathBuf};
+use ted_common::bus::{Message, Publisher};
+use ted_common::component::{Component, EventResult};
+use ted_common::keymap::KeyContext;
+use ted_common::notify;
 
 pub struct VimEditor {
     pub state: EditorState,
     pub event_handler: EditorEventHandler,
+    document: Document,
+    // edtui keeps its key lookup private, so we mirror it: keys that left the
+    // state untouched are assumed to be the start of a sequence like `gg`.
+    pending_keys: String,
+    focused: bool,
+    view: View,
+    diagnostics: Vec<Diagnostic>,
+    folds: Folds,
+    marks: Marks,
+    git: GitChanges,
+    bus: Publisher,
+    /// Goes up with every change to the text, so whatever follows the text
+    /// can skip its work while this stays the same.
+    version: u64,
+    /// The text as of `version`.
+    versioned: Lines,
+    /// The `version` folds, marks and git changes last caught up with.
+    refreshed: Option<u64>,
+    /// The rows edited since the highlighter last saw the text.
+    unhighlighted: Option<RowChange>,
 }
 
 impl VimEditor {
@@ -12,24 +58,720 @@ impl VimEditor {
         Self {
             state: EditorState::default(),
             event_handler: EditorEventHandler::default(),
+            document: Document::default(),
+            pending_keys: String::new(),
+            focused: false,
+            view: View::default(),
+            diagnostics: vec![],
+            folds: Folds::default(),
+            marks: Marks::default(),
+            git: GitChanges::default(),
+            bus: Publisher::default(),
+            version: 0,
+            versioned: EditorState::default().lines,
+            refreshed: None,
+            unhighlighted: None,
         }
     }
 
-    pub fn handle_events(&mut self, event: Event) {
-        self.event_handler.on_event(event, &mut self.state);
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let (document, lines) = Document::open(path)?;
+        let mut editor = Self::new();
+        editor.state = EditorState::new(Lines::from(lines.join("\n")));
+        editor.track_changes();
+        editor.document = document;
+        if let Some(path) = editor.document.path() {
+            editor.view.set_path(Some(path));
+            editor.unhighlighted = Some(RowChange::ALL);
+            editor.folds.load(path, lines);
+        }
+        Ok(editor)
+    }
+
+    /// An unnamed buffer holding `lines`, e.g. to show recovered text.
+    pub fn scratch(lines: &[String]) -> Self {
+        let mut editor = Self::new();
+        editor.set_lines(lines);
+        editor
+    }
+
+    /// Replaces the whole text, keeping the document (and so the dirty
+    /// state) as it was.
+    pub fn set_lines(&mut self, lines: &[String]) {
+        self.state.lines = Lines::from(lines.join("\n").as_str());
+        self.set_cursor(self.cursor());
+        self.track_changes();
+    }
+
+    /// Counts up the text's version when it changed since the last call.
+    /// Comparing is cheap next to copying the text out with `lines`.
+    fn track_changes(&mut self) {
+        if self.state.lines != self.versioned {
+            let change = RowChange::between(
+                self.versioned.iter_row().as_slice(),
+                self.state.lines.iter_row().as_slice(),
+            );
+            self.unhighlighted = Some(match self.unhighlighted {
+                Some(earlier) => earlier.then(change),
+                None => change,
+            });
+            self.versioned = self.state.lines.clone();
+            self.version += 1;
+        }
+    }
+
+    /// Changes whenever the text does, e.g. to tell a language server
+    /// only about buffers that were edited.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Replaces the whole text as one edit, which `undo` takes back.
+    pub fn edit_lines(&mut self, lines: &[String]) {
+        self.checkpoint();
+        self.set_lines(lines);
+    }
+
+    pub const ACTIONS: &'static [&'static str] = &[
+        "move_left",
+        "move_right",
+        "move_up",
+        "move_down",
+        "word_forward",
+        "word_backward",
+        "line_start",
+        "line_end",
+        "first_line",
+        "last_line",
+        "half_page_down",
+        "half_page_up",
+        "delete_line",
+        "paste",
+        "undo",
+        "redo",
+        "normal_mode",
+        "insert_mode",
+        "visual_mode",
+        "fold_open",
+        "fold_close",
+        "fold_toggle",
+        "fold_open_all",
+        "fold_close_all",
+        "fold_create",
+        "fold_delete",
+        "next_hunk",
+        "prev_hunk",
+        "stage_hunk",
+        "reset_hunk",
+    ];
+
+    pub fn lines(&self) -> Vec<String> {
+        self.state.lines.iter_row().map(|row| row.iter().collect()).collect()
+    }
+
+    /// The text of one row, if the buffer has it.
+    pub fn line(&self, row: usize) -> Option<String> {
+        self.state.lines.iter_row().nth(row).map(|row| row.iter().collect())
+    }
+
+    /// Whether typed text goes into the buffer.
+    pub fn is_inserting(&self) -> bool {
+        self.state.mode == EditorMode::Insert
+    }
+
+    /// The cursor as `(row, column)`.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.state.cursor.row, self.state.cursor.col)
+    }
+
+    /// The visual selection as a range from `start` up to `end`, in order.
+    /// A line-wise selection covers its lines whole.
+    pub fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        if self.state.mode != EditorMode::Visual {
+            return None;
+        }
+        let selection = self.state.selection.as_ref()?;
+        let (start, end) = match selection.start <= selection.end {
+            true => (selection.start, selection.end),
+            false => (selection.end, selection.start),
+        };
+        match selection.line_mode {
+            true => Some(((start.row, 0), (end.row + 1, 0))),
+            false => Some(((start.row, start.col), (end.row, end.col + 1))),
+        }
+    }
+
+    /// Moves the cursor, clamped to the text; in insert mode it may sit
+    /// just past the end of a line. The view follows the cursor on the next
+    /// draw.
+    pub fn set_cursor(&mut self, (row, col): (usize, usize)) {
+        let lines = self.lines();
+        let row = row.min(lines.len().saturating_sub(1));
+        let len = lines.get(row).map_or(0, |l| l.chars().count());
+        let max = match self.state.mode {
+            EditorMode::Insert => len,
+            _ => len.saturating_sub(1),
+        };
+        self.state.cursor = Index2::new(row, col.min(max));
+    }
+
+    /// Where `(row, col)` was drawn on screen, if it was visible.
+    pub fn screen_position(
+        &self,
+        (row, col): (usize, usize),
+    ) -> Option<Position> {
+        self.view.screen_position(&self.state, &self.folds, row, col)
+    }
+
+    /// Applies `edits` as one undo step. The cursor stays on the same text
+    /// when rows are added or removed above it.
+    pub fn apply_edits(&mut self, edits: &[TextEdit]) {
+        self.checkpoint();
+        let (row, col) = self.cursor();
+        let shift: isize = edits
+            .iter()
+            .filter(|edit| edit.end.0 < row)
+            .map(TextEdit::row_delta)
+            .sum();
+        let mut lines = self.lines();
+        text_edit::apply(&mut lines, edits);
+        self.set_lines(&lines);
+        self.set_cursor((row.saturating_add_signed(shift), col));
+    }
+
+    /// Lets `undo` go back to the text as it is now. edtui records undo
+    /// points on its own only, entering insert mode being one of them, so
+    /// an edit made while inserting is undone with the rest of the insert.
+    fn checkpoint(&mut self) {
+        if self.state.mode == EditorMode::Insert {
+            return;
+        }
+        let (mode, selection) = (self.state.mode, self.state.selection.clone());
+        self.state.mode = EditorMode::Normal;
+        SwitchMode(EditorMode::Insert).execute(&mut self.state);
+        self.state.mode = mode;
+        self.state.selection = selection;
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.document.path()
+    }
+
+    /// The file name, or `[No Name]` for a scratch buffer.
+    pub fn title(&self) -> String {
+        self.path()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "[No Name]".to_string())
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.document.is_dirty(&self.lines())
+    }
+
+    pub fn snapshot(&self) -> BufferSnapshot {
+        BufferSnapshot::capture(&self.document, &self.lines(), self.cursor())
+    }
+
+    /// Puts back the cursor and any unsaved edits from `snapshot`; the
+    /// buffer stays dirty against the file it was opened from.
+    pub fn restore(&mut self, snapshot: &BufferSnapshot) {
+        if let Some(lines) = &snapshot.lines {
+            self.set_lines(lines);
+        }
+        self.set_cursor(snapshot.cursor);
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        let lines = self.lines();
+        self.document.save(&lines)?;
+        if let Some(path) = self.path() {
+            self.bus.publish(Message::FileSaved(path.to_path_buf()));
+        }
+        self.save_folds();
+        self.reload_git();
+        Ok(())
+    }
+
+    /// Where `save` tells others about the file it wrote.
+    pub fn set_publisher(&mut self, bus: Publisher) {
+        self.bus = bus;
+    }
+
+    /// Shows changes against `base`, reading it from git.
+    pub fn set_git_base(&mut self, base: GitBase) {
+        self.git.load(self.document.path(), base);
+        self.refreshed = None;
+    }
+
+    /// Reads the version changes are shown against again, which commits
+    /// and staging outside ted move.
+    pub fn reload_git(&mut self) {
+        self.set_git_base(self.git.base());
+    }
+
+    /// Takes `lines` as what git has for the file, read elsewhere with
+    /// the base this buffer shows changes against.
+    pub fn set_git_lines(&mut self, lines: Option<Vec<String>>) {
+        self.git.set_base_lines(lines);
+        self.refreshed = None;
+    }
+
+    /// The change on `row` against git, with the lines it replaced.
+    pub fn hunk_at(&mut self, row: usize) -> Option<(Hunk, Vec<String>)> {
+        self.refresh();
+        let hunk = self.git.hunk_at(row)?;
+        Some((hunk.clone(), self.git.old_lines(hunk).to_vec()))
+    }
+
+    /// `]c` and `[c`.
+    fn goto_hunk(&mut self, down: bool) {
+        self.refresh();
+        let (row, _) = self.cursor();
+        match self.git.next(row, down) {
+            Some(row) => self.set_cursor((row, 0)),
+            None => notify::info("no more changes"),
+        }
+    }
+
+    fn stage_hunk(&mut self) {
+        let Some(path) = self.document.path().map(Path::to_path_buf) else {
+            return;
+        };
+        match git::stage_hunk(&path, &self.lines(), self.cursor().0) {
+            Ok(()) => self.reload_git(),
+            Err(e) => notify::warn(format!("stage hunk: {e}")),
+        }
+    }
+
+    /// Puts back what git has for the change at the cursor, as one undo
+    /// step.
+    fn reset_hunk(&mut self) {
+        let Some((hunk, old)) = self.hunk_at(self.cursor().0) else {
+            notify::warn("no change here");
+            return;
+        };
+        let mut lines = self.lines();
+        lines.splice(hunk.new.clone(), old);
+        self.edit_lines(&lines);
+        self.set_cursor((hunk.new.start, 0));
+    }
+
+    pub fn set_fold_method(&mut self, method: FoldMethod) {
+        self.folds.set_method(method);
+        self.refreshed = None;
+    }
+
+    /// Replaces the folds the language server found.
+    pub fn set_lsp_folds(&mut self, folds: Vec<Fold>) {
+        self.folds.set_lsp(folds);
+        self.refreshed = None;
     }
 
-    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+    pub fn set_gutter(&mut self, config: GutterConfig) {
+        self.view.set_gutter(config);
+    }
+
+    /// Brings the highlighter, folds, marks and git changes up to date
+    /// with the text.
+    fn refresh(&mut self) {
+        self.sync_syntax();
+        if self.refreshed == Some(self.version) {
+            return;
+        }
+        self.refreshed = Some(self.version);
+        let lines = self.lines();
+        self.git.update(&lines);
+        self.marks.update(lines.clone());
+        self.folds.update(lines, self.view.syntax().map(|syntax| &*syntax));
+    }
+
+    /// Hands the highlighter the rows edited since it last saw the text.
+    fn sync_syntax(&mut self) {
+        let Some(change) = self.unhighlighted.take() else {
+            return;
+        };
+        let Some(syntax) = self.view.syntax() else {
+            return;
+        };
+        let rows = self.state.lines.iter_row().as_slice();
+        let edited = &rows[change.above..rows.len() - change.below];
+        syntax.edit(change, edited.iter().map(String::from_iter));
+    }
+
+    /// `m{a-z}` sets a mark at the cursor, `'{a-z}` jumps to the first
+    /// non-blank of its line and `` `{a-z} `` to the mark itself. edtui
+    /// binds none of these. Returns whether `c` completed one.
+    fn on_mark_key(&mut self, c: char) -> bool {
+        if !c.is_ascii_lowercase() || self.state.mode != EditorMode::Normal {
+            return false;
+        }
+        match self.pending_keys.as_str() {
+            "m" => {
+                self.refresh();
+                self.marks.set(c, self.cursor());
+            }
+            "'" | "`" => {
+                let Some((row, col)) = self.marks.get(c) else {
+                    notify::warn(format!("mark {c} is not set"));
+                    self.pending_keys.clear();
+                    return true;
+                };
+                let col = match self.pending_keys.as_str() {
+                    "'" => self.line(row).map_or(0, |line| {
+                        line.chars().take_while(|c| c.is_whitespace()).count()
+                    }),
+                    _ => col,
+                };
+                self.set_cursor((row, col));
+            }
+            _ => return false,
+        }
+        self.pending_keys.clear();
+        true
+    }
+
+    /// Remembers which folds are closed. Rows only mean the same thing the
+    /// next time the file is opened if it is saved, so edited buffers wait
+    /// for their next save.
+    fn save_folds(&self) {
+        let Some(path) = self.path() else {
+            return;
+        };
+        if self.is_dirty() {
+            return;
+        }
+        if let Err(e) = self.folds.save(path) {
+            notify::warn(format!("could not save folds: {e}"));
+        }
+    }
+
+    /// The `z` commands. The cursor moves out of a fold that closes over it.
+    fn run_fold_action(&mut self, action: &str) -> bool {
+        self.refresh();
+        let row = self.state.cursor.row;
+        match action {
+            "fold_open" => self.folds.open(row),
+            "fold_close" => self.folds.close(row),
+            "fold_toggle" => self.folds.toggle(row),
+            "fold_open_all" => self.folds.open_all(),
+            "fold_close_all" => self.folds.close_all(),
+            "fold_create" => {
+                let Some(((start, _), (end, col))) = self.selection() else {
+                    return true;
+                };
+                // A line-wise selection ends at the start of the next row.
+                let end = if col == 0 && end > start { end - 1 } else { end };
+                self.folds.create(start, end);
+                SwitchMode(EditorMode::Normal).execute(&mut self.state);
+            }
+            "fold_delete" => {
+                if !self.folds.delete(row) {
+                    notify::warn("no manual fold here");
+                }
+            }
+            _ => return false,
+        }
+        let (row, col) = self.cursor();
+        self.set_cursor((self.folds.shown_at(row), col));
+        self.save_folds();
+        true
+    }
+
+    /// Makes a line motion from `before` skip over closed folds: moving down
+    /// from a fold goes past it, moving onto one stops at its first line.
+    fn skip_folds(&mut self, before: usize) {
+        let (row, col) = self.cursor();
+        if row == before || !self.folds.is_hidden(row) {
+            return;
+        }
+        let shown = self.folds.shown_at(row);
+        let row = match row > before && shown <= before {
+            true => self.folds.next_visible(row).unwrap_or(before),
+            false => shown,
+        };
+        self.set_cursor((row, col));
+    }
+
+    /// Replaces what is underlined and listed in the sign column.
+    pub fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics = diagnostics;
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Moves half the visible rows. edtui's own half page moves go by a
+    /// view height it only knows when it draws the text itself.
+    fn half_page(&mut self, down: bool) {
+        let rows = (self.view.height() / 2).max(1);
+        let (row, col) = self.cursor();
+        self.set_cursor((self.folds.step(row, rows, down), col));
+    }
+
+    /// Clicks move the cursor and dragging selects, like edtui does for its
+    /// own view.
+    fn on_mouse(&mut self, mouse: &MouseEvent) {
+        if mouse.kind == MouseEventKind::Down(MouseButton::Left)
+            && let Some(row) = self.view.fold_at(mouse.column, mouse.row)
+        {
+            self.folds.toggle(row);
+            let (row, col) = self.cursor();
+            self.set_cursor((self.folds.shown_at(row), col));
+            self.save_folds();
+            return;
+        }
+        let position = self.view.position_at(
+            &self.state,
+            &self.folds,
+            mouse.column,
+            mouse.row,
+        );
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.state.selection = None;
+                if self.state.mode == EditorMode::Visual {
+                    self.state.mode = EditorMode::Normal;
+                }
+                self.state.cursor = position;
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if self.state.mode != EditorMode::Visual {
+                    SwitchMode(EditorMode::Visual).execute(&mut self.state);
+                }
+                if let Some(selection) = &mut self.state.selection {
+                    selection.end = position;
+                }
+                self.state.cursor = position;
+            }
+            _ => {}
+        }
+    }
+
+    /// The actions edtui implements for us.
+    fn run_edtui_action(&mut self, action: &str) -> bool {
+        let row = self.state.cursor.row;
+        let mut action: Action = match action {
+            "move_left" => MoveBackward(1).into(),
+            "move_right" => MoveForward(1).into(),
+            "move_up" => MoveUp(1).into(),
+            "move_down" => MoveDown(1).into(),
+            "word_forward" => MoveWordForward(1).into(),
+            "word_backward" => MoveWordBackward(1).into(),
+            "line_start" => MoveToStartOfLine().into(),
+            "line_end" => MoveToEndOfLine().into(),
+            "first_line" => MoveToFirstRow().into(),
+            "last_line" => MoveToLastRow().into(),
+            "delete_line" => DeleteLine(1).into(),
+            "paste" => Paste.into(),
+            "undo" => Undo.into(),
+            "redo" => Redo.into(),
+            "normal_mode" => SwitchMode(EditorMode::Normal).into(),
+            "insert_mode" => SwitchMode(EditorMode::Insert).into(),
+            "visual_mode" => SwitchMode(EditorMode::Visual).into(),
+            _ => return false,
+        };
+        action.execute(&mut self.state);
+        self.skip_folds(row);
+        self.pending_keys.clear();
+        true
+    }
+
+    pub fn status(&self) -> EditorStatus {
+        let cursor = (self.state.cursor.row, self.state.cursor.col);
+        EditorStatus::new(
+            self.state.mode.name().to_uppercase(),
+            &self.document,
+            &self.lines(),
+            cursor,
+            self.pending_keys.clone(),
+        )
+    }
+}
+
+impl Component for VimEditor {
+    fn id(&self) -> &str {
+        "editor"
+    }
+
+    fn handle_event(&mut self, event: &Event) -> EventResult {
+        let before = (self.state.cursor, self.state.mode, self.version);
+        let key = match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                Some(key.code)
+            }
+            _ => None,
+        };
+        if let Some(KeyCode::Char(c)) = key
+            && self.on_mark_key(c)
+        {
+            return EventResult::Consumed;
+        }
+        match event {
+            Event::Mouse(mouse) => self.on_mouse(mouse),
+            event => {
+                self.event_handler.on_event(event.clone(), &mut self.state)
+            }
+        }
+        if let Some(KeyCode::Char('j' | 'k') | KeyCode::Up | KeyCode::Down) =
+            key
+            && self.state.mode != EditorMode::Insert
+        {
+            self.skip_folds(before.0.row);
+        }
+        self.track_changes();
+        let after = (self.state.cursor, self.state.mode, self.version);
+        match key {
+            Some(KeyCode::Char(c))
+                if before == after && self.state.mode != EditorMode::Insert =>
+            {
+                // Like edtui, a key that can't go on the sequence drops it.
+                self.pending_keys.push(c);
+                if !waits_on(self.state.mode, &self.pending_keys) {
+                    self.pending_keys.clear();
+                }
+            }
+            Some(_) => self.pending_keys.clear(),
+            None => {}
+        }
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                EventResult::Consumed
+            }
+            Event::Mouse(mouse) if mouse.kind != MouseEventKind::Moved => {
+                EventResult::Consumed
+            }
+            Event::Paste(_) => EventResult::Consumed,
+            _ => EventResult::Ignored,
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) {
         frame.render_widget(self, area);
     }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    /// Runs a keymap action, returning `false` if the name is unknown.
+    fn run_action(&mut self, action: &str) -> bool {
+        match action {
+            "half_page_down" => self.half_page(true),
+            "half_page_up" => self.half_page(false),
+            "next_hunk" => self.goto_hunk(true),
+            "prev_hunk" => self.goto_hunk(false),
+            "stage_hunk" => self.stage_hunk(),
+            "reset_hunk" => self.reset_hunk(),
+            action if action.starts_with("fold_") => {
+                return self.run_fold_action(action);
+            }
+            _ => {
+                let known = self.run_edtui_action(action);
+                self.track_changes();
+                return known;
+            }
+        }
+        self.pending_keys.clear();
+        true
+    }
+
+    fn has_pending_keys(&self) -> bool {
+        !self.pending_keys.is_empty()
+    }
+
+    fn key_context(&self) -> KeyContext {
+        match self.state.mode {
+            EditorMode::Insert | EditorMode::Search => KeyContext::Insert,
+            EditorMode::Visual => KeyContext::Visual,
+            EditorMode::Normal => KeyContext::Normal,
+        }
+    }
+
+    fn actions(&self) -> &'static [&'static str] {
+        Self::ACTIONS
+    }
+}
+
+impl Default for VimEditor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Widget for &mut VimEditor {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let syntax_highlighter = SyntaxHighlighter::new("dracula", "rs");
-        EditorView::new(&mut self.state)
-            .wrap(true)
-            .syntax_highlighter(Some(syntax_highlighter))
-            .render(area, buf)
+        let block = Block::bordered().border_style(if self.focused {
+            Style::new().fg(Color::LightGreen)
+        } else {
+            Style::new().fg(Color::DarkGray)
+        });
+        let inner = block.inner(area);
+        block.render(area, buf);
+        self.refresh();
+        self.folds.reveal(self.state.cursor.row);
+        let signs: Vec<Sign> =
+            self.git.signs().chain(self.marks.signs()).collect();
+        self.view.draw(
+            &self.state,
+            &self.diagnostics,
+            &signs,
+            &self.folds,
+            inner,
+            buf,
+        );
+    }
+}
+
+/// Whether `keys` start one of edtui's sequences in `mode`, or a mark.
+fn waits_on(mode: EditorMode, keys: &str) -> bool {
+    match mode {
+        EditorMode::Normal => {
+            matches!(keys, "g" | "d" | "c" | "ci" | "y" | "m" | "'" | "`")
+        }
+        EditorMode::Visual => matches!(keys, "g" | "i"),
+        EditorMode::Insert | EditorMode::Search => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyEvent;
+
+    #[test]
+    fn counts_a_version_only_when_the_text_changes() {
+        let lines = ["one", "two"].map(str::to_string);
+        let mut editor = VimEditor::scratch(&lines);
+        let version = editor.version();
+        let press = |editor: &mut VimEditor, c| {
+            editor.handle_event(&Event::Key(KeyEvent::from(KeyCode::Char(c))));
+            editor.version()
+        };
+        assert_eq!(press(&mut editor, 'j'), version);
+        assert_eq!(press(&mut editor, 'x'), version + 1);
+        assert_eq!(editor.lines(), ["one", "wo"]);
+        editor.run_action("undo");
+        assert_eq!(editor.version(), version + 2);
+        editor.set_lines(&editor.lines());
+        assert_eq!(editor.version(), version + 2);
+    }
+
+    #[test]
+    fn waits_only_on_keys_that_start_a_sequence() {
+        let mut editor = VimEditor::scratch(&["f(a[b])".to_string()]);
+        let press = |editor: &mut VimEditor, c| {
+            editor.handle_event(&Event::Key(KeyEvent::from(KeyCode::Char(c))));
+            editor.has_pending_keys()
+        };
+        editor.set_cursor((0, 4));
+        // `k` moves nowhere on the first row, but waits on nothing either.
+        assert!(!press(&mut editor, 'k'));
+        assert!(press(&mut editor, 'c'));
+        assert!(press(&mut editor, 'i'));
+        // `[` starts keymap sequences too, but after `ci` the editor has it.
+        assert!(!press(&mut editor, '['));
+        assert_eq!(editor.lines(), ["f(a[])"]);
     }
 }
@@ -1,12 +1,27 @@
+use crate::document::Document;
+use crate::editor_status::EditorStatus;
+use crate::gutter::{Gutter, GutterConfig};
+use crate::snapshot::BufferSnapshot;
+use crate::syntax::Syntax;
 use crate::vim_lite_editor::vim_state::{Mode, Transition, VimState};
 use ratatui::Frame;
 use ratatui::crossterm::event::Event;
 use ratatui::prelude::*;
-use tui_textarea::TextArea;
+use std::io;
+use std::path::PathBuf;
+use ted_common::component::{Component, EventResult};
+use tui_textarea::{CursorMove, TextArea};
+use unicode_width::UnicodeWidthChar;
 
 pub struct VimLiteEditor<'a> {
     textarea: TextArea<'a>,
     vim_state: VimState,
+    document: Document,
+    syntax: Option<Syntax>,
+    /// First visible display column, worked out the way tui-textarea
+    /// scrolls since it keeps its own to itself.
+    left: usize,
+    gutter: Gutter,
 }
 
 impl<'a> VimLiteEditor<'a> {
@@ -15,17 +30,144 @@ impl<'a> VimLiteEditor<'a> {
         textarea.set_block(Mode::Normal.block());
         textarea.set_cursor_style(Mode::Normal.cursor_style());
         let vim_state = VimState::new(Mode::Normal);
-        Self { textarea, vim_state }
+        let mut editor = Self {
+            textarea,
+            vim_state,
+            document: Document::default(),
+            syntax: None,
+            left: 0,
+            gutter: Gutter::default(),
+        };
+        editor.set_gutter(GutterConfig::default());
+        editor
     }
 
-    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
-        frame.render_widget(self, area);
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let (document, lines) = Document::open(path)?;
+        let mut editor = Self::new();
+        editor.set_lines(lines);
+        editor.syntax = document.path().and_then(Syntax::for_path);
+        editor.document = document;
+        Ok(editor)
+    }
+
+    fn set_lines(&mut self, lines: Vec<String>) {
+        let mode = self.vim_state.mode();
+        self.textarea = TextArea::new(lines);
+        self.textarea.set_block(mode.block());
+        self.textarea.set_cursor_style(mode.cursor_style());
+    }
+
+    pub fn snapshot(&self) -> BufferSnapshot {
+        BufferSnapshot::capture(
+            &self.document,
+            self.textarea.lines(),
+            self.textarea.cursor(),
+        )
+    }
+
+    /// Puts back the cursor and any unsaved edits from `snapshot`; the
+    /// buffer stays dirty against the file it was opened from.
+    pub fn restore(&mut self, snapshot: &BufferSnapshot) {
+        if let Some(lines) = &snapshot.lines {
+            self.set_lines(lines.clone());
+        }
+        let (row, col) = snapshot.cursor;
+        self.textarea.move_cursor(CursorMove::Jump(
+            row.min(u16::MAX as usize) as u16,
+            col.min(u16::MAX as usize) as u16,
+        ));
+    }
+
+    pub fn status(&self) -> EditorStatus {
+        EditorStatus::new(
+            self.vim_state.mode().to_string(),
+            &self.document,
+            self.textarea.lines(),
+            self.textarea.cursor(),
+            self.vim_state.pending_keys(),
+        )
+    }
+
+    /// Line numbers only: this editor tracks no diagnostics, git changes
+    /// or folds, so it leaves out the sign and fold columns.
+    pub fn set_gutter(&mut self, config: GutterConfig) {
+        self.gutter.set_config(GutterConfig {
+            signs: false,
+            folds: false,
+            ..config
+        });
     }
 
-    pub fn handle_event(&mut self, event: Event) {
+    /// Colours the text tui-textarea drew in `text` from row `top` down.
+    fn highlight(&mut self, text: Rect, top: usize, buf: &mut Buffer) {
+        if self.syntax.is_none() {
+            return;
+        }
+        let cursor = self.textarea.cursor();
+        self.left = match cursor.1 {
+            col if col < self.left => col,
+            col if col >= self.left + text.width as usize => {
+                col + 1 - text.width as usize
+            }
+            _ => self.left,
+        };
+
+        let Some(syntax) = &mut self.syntax else {
+            return;
+        };
+        syntax.update(self.textarea.lines());
+        let shown: Vec<usize> = (top..self.textarea.lines().len())
+            .take(text.height as usize)
+            .collect();
+        let rows = syntax.highlight(&shown);
+        let tab = self.textarea.tab_length() as usize;
+        let lines = self.textarea.lines().iter().skip(top);
+        for (y, (line, styles)) in lines.zip(rows).enumerate() {
+            let mut x = 0;
+            for ((col, ch), style) in line.chars().enumerate().zip(styles) {
+                let width = match ch {
+                    '\t' => tab - x % tab,
+                    ch => ch.width().unwrap_or(0),
+                };
+                let shown = x >= self.left
+                    && x + width <= self.left + text.width as usize;
+                if shown && (top + y, col) != cursor {
+                    for dx in x..x + width {
+                        let screen_x = text.x + (dx - self.left) as u16;
+                        buf[(screen_x, text.y + y as u16)].set_style(style);
+                    }
+                }
+                x += width;
+            }
+        }
+    }
+
+    /// The first row tui-textarea drew. It keeps its scroll position to
+    /// itself, but moving the cursor into view from the top finds it.
+    fn top_row(&mut self) -> usize {
+        let (row, col) = self.textarea.cursor();
+        self.textarea.move_cursor(CursorMove::Jump(0, 0));
+        self.textarea.move_cursor(CursorMove::InViewport);
+        let top = self.textarea.cursor().0;
+        self.textarea.move_cursor(CursorMove::Jump(
+            row.min(u16::MAX as usize) as u16,
+            col.min(u16::MAX as usize) as u16,
+        ));
+        top
+    }
+}
+
+impl Component for VimLiteEditor<'_> {
+    fn id(&self) -> &str {
+        "editor"
+    }
+
+    fn handle_event(&mut self, event: &Event) -> EventResult {
         let curr_state = self.vim_state.clone();
+        let mut result = EventResult::Consumed;
         self.vim_state = match curr_state
-            .transition(event.into(), &mut self.textarea)
+            .transition(event.clone().into(), &mut self.textarea)
         {
             Transition::Mode(next_mode) if curr_state.mode() != next_mode => {
                 self.textarea.set_cursor_style(next_mode.cursor_style());
@@ -33,13 +175,113 @@ impl<'a> VimLiteEditor<'a> {
             }
             Transition::Nop | Transition::Mode(_) => curr_state,
             Transition::Pending(input) => curr_state.with_pending(input),
-            Transition::Quit => curr_state,
-        }
+            Transition::Quit => {
+                result = EventResult::Action("quit".into());
+                curr_state
+            }
+        };
+        result
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(self, area);
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        let border = if focused { Color::LightGreen } else { Color::DarkGray };
+        self.textarea.set_block(
+            self.vim_state.mode().block().border_style(Style::new().fg(border)),
+        );
+    }
+}
+
+impl Default for VimLiteEditor<'_> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl<'a> Widget for &mut VimLiteEditor<'a> {
+    /// Draws the mode's block, then the gutter and text inside it.
+    /// tui-textarea would draw the block around the text alone.
     fn render(self, area: Rect, buf: &mut Buffer) {
-        self.textarea.render(area, buf);
+        let block = self.textarea.block().cloned();
+        let inner = block.as_ref().map_or(area, |block| block.inner(area));
+        let lines = self.textarea.lines().len();
+        let [gutter, text] = Layout::horizontal([
+            Constraint::Length(self.gutter.width(lines)),
+            Constraint::Min(0),
+        ])
+        .areas(inner);
+        if let Some(block) = block {
+            (&block).render(area, buf);
+            self.textarea.remove_block();
+            self.textarea.render(text, buf);
+            self.textarea.set_block(block);
+        } else {
+            self.textarea.render(text, buf);
+        }
+
+        let top = self.top_row();
+        self.highlight(text, top, buf);
+        let rows: Vec<usize> =
+            (top..lines).take(text.height as usize).collect();
+        let cursor = self.textarea.cursor().0;
+        self.gutter.draw(gutter, &rows, cursor, &[], None, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_the_rows_on_screen() {
+        let mut editor = VimLiteEditor::new();
+        let lines = (0..40)
+            .map(|n| match n % 3 {
+                0 => "let x = 1;".to_string(),
+                _ => "x = 1;".to_string(),
+            })
+            .collect();
+        editor.set_lines(lines);
+        editor.syntax = Syntax::for_language("rust");
+        editor.textarea.move_cursor(CursorMove::Jump(30, 0));
+        let area = Rect::new(0, 0, 20, 7);
+        let mut buf = Buffer::empty(area);
+        Widget::render(&mut editor, area, &mut buf);
+
+        // Rows 26 to 30 show, so `let` starts rows 27 and 30. The text
+        // starts after the border and the line numbers, with no sign or
+        // fold column.
+        assert_eq!(editor.textarea.cursor(), (30, 0));
+        let keyword = buf[(5, 2)].fg;
+        assert_ne!(keyword, Color::Reset);
+        assert_eq!(buf[(6, 5)].fg, keyword);
+        assert_ne!(buf[(4, 3)].fg, keyword);
+        let number: String =
+            (1..4).map(|x| buf[(x, 5)].symbol().to_string()).collect();
+        assert_eq!(number, "31 ");
+    }
+
+    #[test]
+    fn restores_unsaved_edits_from_a_snapshot() {
+        let path = std::env::temp_dir()
+            .join(format!("ted-lite-snapshot-{}.txt", std::process::id()));
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+        let mut editor = VimLiteEditor::open(&path).unwrap();
+        assert_eq!(editor.snapshot().lines, None);
+
+        editor.set_lines(vec!["one".into(), "2".into(), "three".into()]);
+        editor.textarea.move_cursor(CursorMove::Jump(2, 3));
+        let snapshot = editor.snapshot();
+        let mut reopened = VimLiteEditor::open(&path).unwrap();
+        reopened.restore(&snapshot);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(reopened.textarea.lines(), ["one", "2", "three"]);
+        assert_eq!(reopened.textarea.cursor(), (2, 3));
+        assert!(reopened.document.is_dirty(reopened.textarea.lines()));
+        assert_eq!(reopened.snapshot(), snapshot);
     }
 }
@@ -66,6 +66,14 @@ impl VimState {
         Self { mode: self.mode, pending }
     }
 
+    pub(crate) fn pending_keys(&self) -> String {
+        match self.pending {
+            Input { key: Key::Char(c), ctrl: true, .. } => format!("^{}", c),
+            Input { key: Key::Char(c), .. } => c.to_string(),
+            _ => String::new(),
+        }
+    }
+
     pub(crate) fn transition(
         &self,
         input: Input,
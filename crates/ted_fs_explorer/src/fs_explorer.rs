@@ -1,54 +1,163 @@
-use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind, MouseEventKind};
+use crate::fs_icon_manager::FsIconManager;
+use ratatui::crossterm::event::{Event, MouseEventKind};
 use ratatui::prelude::*;
 use ratatui::widgets::{Block, Scrollbar, ScrollbarOrientation};
+use serde::{Deserialize, Serialize};
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use ted_common::bus::{Message, Publisher};
+use ted_common::component::{Component, EventResult};
+use ted_common::keymap::KeyContext;
+use ted_common::notify;
 use tui_tree_widget::{Tree, TreeItem, TreeState};
 
+/// The part of the tree state worth keeping between sessions. Nodes are
+/// identified by their path from the root, one full path per level.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FsExplorerSession {
+    pub opened: Vec<Vec<String>>,
+    pub selected: Vec<String>,
+}
+
 pub struct FsExplorer {
     state: TreeState<String>,
     root_item: Vec<TreeItem<'static, String>>,
+    root_path: PathBuf,
+    focused: bool,
+    /// The file picked first for a diff, waiting for the second one.
+    diff_pick: Option<PathBuf>,
+    bus: Publisher,
 }
 impl FsExplorer {
-    pub fn new(root_path: PathBuf) -> io::Result<FsExplorer> {
-        fn build_items(
-            path: &PathBuf,
-            curr_level: u8,
-            max_level: u8,
-        ) -> io::Result<TreeItem<'static, String>> {
-            let file_name =
-                path.file_name().unwrap().to_owned().into_string().unwrap();
-            let path_str = path.to_str().expect("");
-            if path.is_dir() && curr_level <= max_level {
-                let mut children = vec![];
-                let dir = path.read_dir()?;
-                for entry in dir {
-                    let entry = entry?;
-                    let path_in_dir = entry.path();
-                    let child_item =
-                        build_items(&path_in_dir, curr_level + 1, max_level)?;
-                    children.push(child_item);
+    pub fn new(root_path: PathBuf, bus: Publisher) -> io::Result<FsExplorer> {
+        let root_path = if root_path.is_dir() {
+            root_path
+        } else {
+            // A bare file name has an empty parent: the current directory.
+            match root_path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => {
+                    parent.to_path_buf()
                 }
-                TreeItem::new(path_str.to_string(), file_name, children)
-            } else {
-                let ext = path.extension().unwrap().to_str().unwrap();
-                Ok(TreeItem::new_leaf(path_str.to_string(), file_name))
+                _ => PathBuf::from("."),
             }
+        };
+        let root_item = build_items(&FsIconManager::new(), &root_path, 1, 2)?;
+        Ok(Self {
+            state: TreeState::default(),
+            root_item: vec![root_item],
+            root_path,
+            focused: false,
+            diff_pick: None,
+            bus,
+        })
+    }
+
+    /// Re-reads the tree from disk. Open and selected nodes are kept as
+    /// long as their paths still exist.
+    pub fn refresh(&mut self) -> io::Result<()> {
+        if self.root_item.is_empty() {
+            return Ok(());
         }
+        let root_item =
+            build_items(&FsIconManager::new(), &self.root_path, 1, 2)?;
+        self.root_item = vec![root_item];
+        Ok(())
+    }
 
-        let root_item = if root_path.is_dir() {
-            build_items(&root_path, 1, 2)?
-        } else {
-            let parent = root_path.parent().unwrap();
-            build_items(&parent.into(), 1, 2)?
+    /// The selected node, if it is a file rather than a directory.
+    fn selected_file(&self) -> Option<PathBuf> {
+        let path = PathBuf::from(self.state.selected().last()?);
+        path.is_file().then_some(path)
+    }
+
+    pub fn session(&self) -> FsExplorerSession {
+        let mut opened: Vec<_> = self.state.opened().iter().cloned().collect();
+        opened.sort();
+        FsExplorerSession { opened, selected: self.state.selected().to_vec() }
+    }
+
+    /// Reopens and reselects nodes from an earlier session. Paths that no
+    /// longer exist are harmless, the tree simply never shows them.
+    pub fn restore_session(&mut self, session: FsExplorerSession) {
+        for node in session.opened {
+            self.state.open(node);
+        }
+        self.state.select(session.selected);
+    }
+
+    pub const ACTIONS: &'static [&'static str] = &[
+        "open",
+        "toggle",
+        "left",
+        "right",
+        "down",
+        "up",
+        "unselect",
+        "first",
+        "last",
+        "page_down",
+        "page_up",
+        "diff",
+    ];
+
+    /// Picks the selected file to compare; picking a second one asks for
+    /// the diff of the two.
+    fn pick_for_diff(&mut self) -> bool {
+        let Some(path) = self.selected_file() else {
+            return false;
         };
-        Ok(Self { state: TreeState::default(), root_item: vec![root_item] })
+        match self.diff_pick.take() {
+            Some(first) if first != path => {
+                self.bus.publish(Message::DiffFiles(first, path));
+            }
+            Some(_) => notify::info("diff cancelled"),
+            None => {
+                let name = path.file_name().unwrap_or_default();
+                notify::info(format!(
+                    "comparing {}; pick the other file with the same key",
+                    name.to_string_lossy()
+                ));
+                self.diff_pick = Some(path);
+            }
+        }
+        true
     }
+}
 
-    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+impl Component for FsExplorer {
+    fn id(&self) -> &str {
+        "explorer"
+    }
+
+    /// Handles mouse and resize events; keys go through `run_action`.
+    fn handle_event(&mut self, event: &Event) -> EventResult {
+        let update = match event {
+            Event::Mouse(mouse) => match mouse.kind {
+                MouseEventKind::ScrollDown => self.state.scroll_down(1),
+                MouseEventKind::ScrollUp => self.state.scroll_up(1),
+                MouseEventKind::Down(_button) => {
+                    self.state.click_at(Position::new(mouse.column, mouse.row))
+                }
+                _ => false,
+            },
+            Event::Resize(_, _) => true,
+            _ => false,
+        };
+        match update {
+            true => EventResult::Consumed,
+            false => EventResult::Ignored,
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame, area: Rect) {
         let widget = Tree::new(&self.root_item)
             .expect("all item identifiers are unique")
-            .block(Block::bordered().title("project"))
+            .block(
+                Block::bordered()
+                    .title("project")
+                    .border_style(focus_border_style(self.focused)),
+            )
             .experimental_scrollbar(Some(
                 Scrollbar::new(ScrollbarOrientation::VerticalRight)
                     .begin_symbol(None)
@@ -64,34 +173,136 @@ impl FsExplorer {
         frame.render_stateful_widget(widget, area, &mut self.state);
     }
 
-    pub fn handle_event(&mut self, event: Event) {
-        let update = match event {
-            Event::Key(key) if !matches!(key.kind, KeyEventKind::Press) => {
-                false
-            }
-            Event::Key(key) => match key.code {
-                KeyCode::Char('\n' | ' ') => self.state.toggle_selected(),
-                KeyCode::Left => self.state.key_left(),
-                KeyCode::Right => self.state.key_right(),
-                KeyCode::Down => self.state.key_down(),
-                KeyCode::Up => self.state.key_up(),
-                KeyCode::Esc => self.state.select(Vec::new()),
-                KeyCode::Home => self.state.select_first(),
-                KeyCode::End => self.state.select_last(),
-                KeyCode::PageDown => self.state.scroll_down(3),
-                KeyCode::PageUp => self.state.scroll_up(3),
-                _ => false,
-            },
-            Event::Mouse(mouse) => match mouse.kind {
-                MouseEventKind::ScrollDown => self.state.scroll_down(1),
-                MouseEventKind::ScrollUp => self.state.scroll_up(1),
-                MouseEventKind::Down(_button) => {
-                    self.state.click_at(Position::new(mouse.column, mouse.row))
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn key_context(&self) -> KeyContext {
+        KeyContext::Explorer
+    }
+
+    fn on_message(&mut self, message: &Message) {
+        if let Message::FileSaved(path) = message
+            && std::path::absolute(path)
+                .is_ok_and(|path| path.starts_with(&self.root_path))
+            && let Err(e) = self.refresh()
+        {
+            notify::warn(format!("could not refresh the explorer: {e}"));
+        }
+    }
+
+    fn actions(&self) -> &'static [&'static str] {
+        Self::ACTIONS
+    }
+
+    /// Runs a keymap action, returning whether anything changed.
+    fn run_action(&mut self, action: &str) -> bool {
+        match action {
+            "open" => match self.selected_file() {
+                Some(path) => {
+                    self.bus.publish(Message::OpenFile(path));
+                    true
                 }
-                _ => false,
+                None => self.state.toggle_selected(),
             },
-            Event::Resize(_, _) => true,
+            "toggle" => self.state.toggle_selected(),
+            "left" => self.state.key_left(),
+            "right" => self.state.key_right(),
+            "down" => self.state.key_down(),
+            "up" => self.state.key_up(),
+            "unselect" => self.state.select(Vec::new()),
+            "first" => self.state.select_first(),
+            "last" => self.state.select_last(),
+            "page_down" => self.state.scroll_down(3),
+            "page_up" => self.state.scroll_up(3),
+            "diff" => self.pick_for_diff(),
             _ => false,
-        };
+        }
+    }
+}
+
+/// An explorer with nothing in it, e.g. when the workspace can't be read.
+impl Default for FsExplorer {
+    fn default() -> Self {
+        Self {
+            state: TreeState::default(),
+            root_item: vec![],
+            root_path: PathBuf::new(),
+            focused: false,
+            diff_pick: None,
+            bus: Publisher::default(),
+        }
+    }
+}
+
+fn build_items(
+    icons: &FsIconManager,
+    path: &Path,
+    curr_level: u8,
+    max_level: u8,
+) -> io::Result<TreeItem<'static, String>> {
+    // `/` has no file name, so it is shown as itself.
+    let mut file_name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned();
+    if let Some(icon) = icons.get_icon(path) {
+        file_name = format!("{} {}", icon, file_name);
+    }
+    let path_str = path.to_string_lossy();
+    if path.is_dir() && curr_level <= max_level {
+        let mut children = vec![];
+        let dir = path.read_dir()?;
+        for entry in dir {
+            let entry = entry?;
+            let path_in_dir = entry.path();
+            let child_item =
+                build_items(icons, &path_in_dir, curr_level + 1, max_level)?;
+            children.push(child_item);
+        }
+        TreeItem::new(path_str.to_string(), file_name, children)
+    } else {
+        Ok(TreeItem::new_leaf(path_str.to_string(), file_name))
+    }
+}
+
+fn focus_border_style(focused: bool) -> Style {
+    if focused {
+        Style::new().fg(Color::LightGreen)
+    } else {
+        Style::new().fg(Color::DarkGray)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn builds_items_for_the_filesystem_root() {
+        let item =
+            build_items(&FsIconManager::new(), Path::new("/"), 1, 0).unwrap();
+        assert_eq!(item.identifier(), "/");
+    }
+
+    #[test]
+    fn builds_items_for_non_utf8_file_names() {
+        let dir = std::env::temp_dir()
+            .join(format!("ted-explorer-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join(OsStr::from_bytes(b"bad-\xff"));
+        fs::write(&file, "").unwrap();
+        let item = build_items(&FsIconManager::new(), &dir, 1, 1);
+        fs::remove_dir_all(&dir).unwrap();
+        let item = item.unwrap();
+        let child = &item.children()[0];
+        assert_eq!(
+            child.identifier(),
+            &format!("{}/bad-\u{fffd}", dir.display())
+        );
     }
 }
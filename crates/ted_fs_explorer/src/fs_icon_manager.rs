@@ -1,11 +1,54 @@
-pub struct FsIconManager {}
+use std::collections::HashMap;
+use std::path::Path;
+
+const DEFAULT_ICONS: &str =
+    include_str!("../../../assets/font_icons/default.toml");
+
+/// Maps paths to nerd-font glyphs using an icon theme such as
+/// `assets/font_icons/default.toml`.
+pub struct FsIconManager {
+    icons: HashMap<String, char>,
+}
 
 impl FsIconManager {
-    pub(crate) fn new() -> Self {
-        Self {}
+    pub fn new() -> Self {
+        Self::from_toml(DEFAULT_ICONS)
+            .unwrap_or_else(|| Self { icons: HashMap::new() })
+    }
+
+    fn from_toml(text: &str) -> Option<Self> {
+        let table = text.parse::<toml::Table>().ok()?;
+        let extensions = table.get("file_extensions")?.as_table()?;
+        let icons = extensions
+            .iter()
+            .filter_map(|(name, code)| {
+                let code = u32::from_str_radix(code.as_str()?, 16).ok()?;
+                Some((name.clone(), char::from_u32(code)?))
+            })
+            .collect();
+        Some(Self { icons })
     }
 
-    pub(crate) fn get_icon() -> String {
-        todo!()
+    pub fn get_icon(&self, path: &Path) -> Option<char> {
+        if path.is_dir() {
+            return self.get_dir_icon(false);
+        }
+        let key = match path.extension() {
+            Some(ext) => ext.to_str()?,
+            // Dotfiles like `.gitignore` have no extension, only a stem.
+            None => path.file_name()?.to_str()?.trim_start_matches('.'),
+        };
+        self.icons.get(key).copied()
+    }
+
+    pub fn get_dir_icon(&self, opened: bool) -> Option<char> {
+        let key = if opened { "dir_opened" } else { "dir_closed" };
+        self.icons.get(key).copied()
+    }
+}
+
+impl Default for FsIconManager {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -1,2 +1,2 @@
 pub mod fs_explorer;
-mod fs_icon_manager;
\ No newline at end of file
+pub mod fs_icon_manager;
@@ -0,0 +1,190 @@
+use ratatui::crossterm::event::{Event, MouseButton, MouseEventKind};
+use ratatui::prelude::*;
+
+const MAX_HISTORY: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaneId {
+    AsideLeft,
+    Content(usize),
+    AsideRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusDirection {
+    Left,
+    Down,
+    Up,
+    Right,
+}
+
+/// Tracks which pane owns the keyboard. Pane rects are refreshed from the
+/// layout every frame so directional moves and clicks follow resizes.
+pub struct FocusManager {
+    panes: Vec<(PaneId, Rect)>,
+    focused: PaneId,
+    history: Vec<PaneId>,
+}
+
+impl FocusManager {
+    pub fn new(focused: PaneId) -> Self {
+        Self { panes: vec![], focused, history: vec![] }
+    }
+
+    pub fn set_panes(
+        &mut self,
+        panes: impl IntoIterator<Item = (PaneId, Rect)>,
+    ) {
+        self.panes = panes.into_iter().filter(|(_, r)| !r.is_empty()).collect();
+    }
+
+    pub fn focused(&self) -> PaneId {
+        self.focused
+    }
+
+    pub fn is_focused(&self, id: PaneId) -> bool {
+        self.focused == id
+    }
+
+    pub fn focus(&mut self, id: PaneId) {
+        if self.focused == id {
+            return;
+        }
+        self.history.retain(|h| *h != self.focused);
+        self.history.push(self.focused);
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+        self.focused = id;
+    }
+
+    /// Goes back to the most recently focused pane that still exists.
+    pub fn focus_previous(&mut self) {
+        while let Some(id) = self.history.pop() {
+            if self.panes.iter().any(|(p, _)| *p == id) {
+                let current = self.focused;
+                self.focused = id;
+                self.history.push(current);
+                return;
+            }
+        }
+    }
+
+    pub fn focus_next(&mut self) {
+        let Some(idx) = self.panes.iter().position(|(p, _)| *p == self.focused)
+        else {
+            if let Some((id, _)) = self.panes.first() {
+                self.focus(*id);
+            }
+            return;
+        };
+        let (next, _) = self.panes[(idx + 1) % self.panes.len()];
+        self.focus(next);
+    }
+
+    pub fn focus_direction(&mut self, direction: FocusDirection) {
+        let Some(from) = self.rect_of(self.focused) else {
+            return;
+        };
+        let target = self
+            .panes
+            .iter()
+            .filter(|(id, _)| *id != self.focused)
+            .filter_map(|(id, rect)| {
+                distance(from, *rect, direction).map(|d| (d, *id))
+            })
+            .min_by_key(|(d, _)| *d)
+            .map(|(_, id)| id);
+        if let Some(id) = target {
+            self.focus(id);
+        }
+    }
+
+    pub fn pane_at(&self, column: u16, row: u16) -> Option<PaneId> {
+        let pos = Position::new(column, row);
+        self.panes.iter().find(|(_, r)| r.contains(pos)).map(|(id, _)| *id)
+    }
+
+    pub fn rect_of(&self, id: PaneId) -> Option<Rect> {
+        self.panes.iter().find(|(p, _)| *p == id).map(|(_, r)| *r)
+    }
+
+    /// Focuses the pane under a left click. Clicks are never consumed so
+    /// the pane still sees them.
+    pub fn handle_event(&mut self, event: &Event) {
+        if let Event::Mouse(mouse) = event
+            && mouse.kind == MouseEventKind::Down(MouseButton::Left)
+            && let Some(id) = self.pane_at(mouse.column, mouse.row)
+        {
+            self.focus(id);
+        }
+    }
+}
+
+/// How far `to` is from `from` when moving in `direction`, or `None` if it
+/// is not in that direction at all. Panes overlapping on the cross axis win.
+fn distance(from: Rect, to: Rect, direction: FocusDirection) -> Option<u32> {
+    let (gap, overlap) = match direction {
+        FocusDirection::Left if to.right() <= from.x => (
+            from.x - to.right(),
+            span_overlap(from.y, from.bottom(), to.y, to.bottom()),
+        ),
+        FocusDirection::Right if to.x >= from.right() => (
+            to.x - from.right(),
+            span_overlap(from.y, from.bottom(), to.y, to.bottom()),
+        ),
+        FocusDirection::Up if to.bottom() <= from.y => (
+            from.y - to.bottom(),
+            span_overlap(from.x, from.right(), to.x, to.right()),
+        ),
+        FocusDirection::Down if to.y >= from.bottom() => (
+            to.y - from.bottom(),
+            span_overlap(from.x, from.right(), to.x, to.right()),
+        ),
+        _ => return None,
+    };
+    let penalty = if overlap { 0 } else { u16::MAX as u32 };
+    Some(gap as u32 + penalty)
+}
+
+fn span_overlap(a_start: u16, a_end: u16, b_start: u16, b_end: u16) -> bool {
+    a_start < b_end && b_start < a_end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager() -> FocusManager {
+        let mut manager = FocusManager::new(PaneId::Content(0));
+        manager.set_panes([
+            (PaneId::AsideLeft, Rect::new(0, 0, 20, 40)),
+            (PaneId::Content(0), Rect::new(20, 0, 60, 20)),
+            (PaneId::Content(1), Rect::new(20, 20, 60, 20)),
+            (PaneId::AsideRight, Rect::new(80, 0, 20, 40)),
+        ]);
+        manager
+    }
+
+    #[test]
+    fn moves_focus_by_direction() {
+        let mut manager = manager();
+        manager.focus_direction(FocusDirection::Down);
+        assert_eq!(manager.focused(), PaneId::Content(1));
+        manager.focus_direction(FocusDirection::Left);
+        assert_eq!(manager.focused(), PaneId::AsideLeft);
+        manager.focus_direction(FocusDirection::Left);
+        assert_eq!(manager.focused(), PaneId::AsideLeft);
+    }
+
+    #[test]
+    fn remembers_history() {
+        let mut manager = manager();
+        manager.focus(PaneId::AsideRight);
+        manager.focus(PaneId::AsideLeft);
+        manager.focus_previous();
+        assert_eq!(manager.focused(), PaneId::AsideRight);
+        manager.focus_previous();
+        assert_eq!(manager.focused(), PaneId::AsideLeft);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::focus_manager::PaneId;
+use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::prelude::*;
+use ratatui::widgets::Clear;
+
+pub type LayerId = usize;
+
+/// What a floating layer is positioned against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    /// Centred on the whole screen.
+    Screen,
+    /// Centred on a pane, or on the screen if the pane isn't shown.
+    Pane(PaneId),
+    /// Just below a screen position, or above it when there's no room.
+    Cursor(Position),
+}
+
+/// A floating window. The stack only places it; `content` says what the
+/// owner draws inside.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer<T> {
+    pub content: T,
+    pub anchor: Anchor,
+    pub width: u16,
+    pub height: u16,
+    /// Modal layers take every key and mouse event until dismissed.
+    pub modal: bool,
+}
+
+impl<T> Layer<T> {
+    pub fn new(content: T, anchor: Anchor, width: u16, height: u16) -> Self {
+        Self { content, anchor, width, height, modal: false }
+    }
+
+    pub fn modal(mut self) -> Self {
+        self.modal = true;
+        self
+    }
+}
+
+pub enum LayerEvent<T> {
+    /// Esc closed the top layer, which is handed back. A modal layer is
+    /// left or it was one itself, so the key goes no further.
+    Dismissed(T),
+    /// Esc closed the top layer, which is handed back, and no modal layer
+    /// is left. The key still goes to the panes, e.g. to leave insert mode.
+    DismissedAndForwarded(T),
+    /// A layer is modal and the event belongs to the top one.
+    Captured,
+    /// No layer wants the event; it goes to the panes as usual.
+    Ignored,
+}
+
+/// Floating windows drawn over the tiled layout, bottom to top.
+pub struct LayerStack<T> {
+    layers: Vec<(LayerId, Layer<T>)>,
+    next_id: LayerId,
+}
+
+impl<T> LayerStack<T> {
+    pub fn new() -> Self {
+        Self { layers: vec![], next_id: 0 }
+    }
+
+    /// Puts `layer` on top of the stack.
+    pub fn push(&mut self, layer: Layer<T>) -> LayerId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.layers.push((id, layer));
+        id
+    }
+
+    pub fn remove(&mut self, id: LayerId) -> Option<Layer<T>> {
+        let idx = self.layers.iter().position(|(l, _)| *l == id)?;
+        Some(self.layers.remove(idx).1)
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        self.layers.retain(|(_, layer)| keep(&layer.content));
+    }
+
+    pub fn contains(&self, mut matches: impl FnMut(&T) -> bool) -> bool {
+        self.layers.iter().any(|(_, layer)| matches(&layer.content))
+    }
+
+    pub fn top(&self) -> Option<&Layer<T>> {
+        self.layers.last().map(|(_, layer)| layer)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Where every layer goes on `screen`, bottom to top. `pane` looks up
+    /// the rect of a pane, e.g. `FocusManager::rect_of`.
+    pub fn layout(
+        &self,
+        screen: Rect,
+        pane: impl Fn(PaneId) -> Option<Rect>,
+    ) -> Vec<(LayerId, Rect)> {
+        self.layers
+            .iter()
+            .map(|(id, layer)| (*id, place(layer, screen, &pane)))
+            .collect()
+    }
+
+    /// Clears the area under each layer and lets `draw` fill it in, bottom
+    /// to top so higher layers cover lower ones.
+    pub fn render(
+        &self,
+        frame: &mut Frame,
+        pane: impl Fn(PaneId) -> Option<Rect>,
+        mut draw: impl FnMut(&mut Frame, &T, Rect),
+    ) {
+        let screen = frame.area();
+        for (_, layer) in &self.layers {
+            let area = place(layer, screen, &pane);
+            frame.render_widget(Clear, area);
+            draw(frame, &layer.content, area);
+        }
+    }
+
+    /// Esc dismisses the top layer, and reaches the panes too unless that
+    /// layer or one under it is modal. Anything else is captured while any
+    /// layer is modal and ignored otherwise.
+    pub fn handle_event(&mut self, event: &Event) -> LayerEvent<T> {
+        if self.layers.is_empty() {
+            return LayerEvent::Ignored;
+        }
+        let modal = self.layers.iter().any(|(_, layer)| layer.modal);
+        match event {
+            Event::Key(key)
+                if key.code == KeyCode::Esc
+                    && key.kind == KeyEventKind::Press =>
+            {
+                let (_, layer) = self.layers.pop().expect("checked above");
+                if modal {
+                    LayerEvent::Dismissed(layer.content)
+                } else {
+                    LayerEvent::DismissedAndForwarded(layer.content)
+                }
+            }
+            Event::Key(_) | Event::Mouse(_) | Event::Paste(_) if modal => {
+                LayerEvent::Captured
+            }
+            _ => LayerEvent::Ignored,
+        }
+    }
+}
+
+impl<T> Default for LayerStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn place<T>(
+    layer: &Layer<T>,
+    screen: Rect,
+    pane: &impl Fn(PaneId) -> Option<Rect>,
+) -> Rect {
+    let width = layer.width.min(screen.width);
+    let height = layer.height.min(screen.height);
+    let centre = |area: Rect| {
+        Position::new(
+            area.x + area.width.saturating_sub(width) / 2,
+            area.y + area.height.saturating_sub(height) / 2,
+        )
+    };
+    let origin = match layer.anchor {
+        Anchor::Screen => centre(screen),
+        Anchor::Pane(id) => centre(pane(id).unwrap_or(screen)),
+        Anchor::Cursor(cursor) => {
+            let below = cursor.y.saturating_add(1);
+            let y = if below.saturating_add(height) <= screen.bottom() {
+                below
+            } else {
+                cursor.y.saturating_sub(height)
+            };
+            Position::new(cursor.x, y)
+        }
+    };
+    // Keep the whole layer on screen.
+    let x = origin.x.clamp(screen.x, screen.right() - width);
+    let y = origin.y.clamp(screen.y, screen.bottom() - height);
+    Rect::new(x, y, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyEvent;
+
+    const SCREEN: Rect = Rect::new(0, 0, 80, 24);
+
+    fn rect_of(stack: &LayerStack<char>, id: LayerId) -> Rect {
+        let pane = |_| Some(Rect::new(40, 0, 40, 24));
+        let layout = stack.layout(SCREEN, pane);
+        layout.into_iter().find(|(l, _)| *l == id).unwrap().1
+    }
+
+    #[test]
+    fn places_and_clamps_layers() {
+        let mut stack = LayerStack::new();
+        let centred = stack.push(Layer::new('a', Anchor::Screen, 20, 4));
+        assert_eq!(rect_of(&stack, centred), Rect::new(30, 10, 20, 4));
+        let pane = stack.push(Layer::new(
+            'b',
+            Anchor::Pane(PaneId::Content(0)),
+            20,
+            4,
+        ));
+        assert_eq!(rect_of(&stack, pane), Rect::new(50, 10, 20, 4));
+        let cursor = stack.push(Layer::new(
+            'c',
+            Anchor::Cursor(Position::new(75, 22)),
+            20,
+            4,
+        ));
+        // No room below or to the right: flips up and shifts left.
+        assert_eq!(rect_of(&stack, cursor), Rect::new(60, 18, 20, 4));
+        let huge = stack.push(Layer::new('d', Anchor::Screen, 200, 100));
+        assert_eq!(rect_of(&stack, huge), SCREEN);
+    }
+
+    #[test]
+    fn modal_layers_capture_until_dismissed() {
+        let mut stack = LayerStack::new();
+        stack.push(Layer::new('a', Anchor::Screen, 10, 3));
+        let key = |code| Event::Key(KeyEvent::from(code));
+        assert!(matches!(
+            stack.handle_event(&key(KeyCode::Char('x'))),
+            LayerEvent::Ignored
+        ));
+        stack.push(Layer::new('b', Anchor::Screen, 10, 3).modal());
+        assert!(matches!(
+            stack.handle_event(&key(KeyCode::Char('x'))),
+            LayerEvent::Captured
+        ));
+        assert!(matches!(
+            stack.handle_event(&key(KeyCode::Esc)),
+            LayerEvent::Dismissed('b')
+        ));
+        assert!(matches!(
+            stack.handle_event(&key(KeyCode::Esc)),
+            LayerEvent::DismissedAndForwarded('a')
+        ));
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn esc_on_a_non_modal_layer_still_reaches_the_panes() {
+        let mut stack = LayerStack::new();
+        stack.push(Layer::new('a', Anchor::Screen, 10, 3));
+        stack.push(Layer::new('b', Anchor::Screen, 10, 3));
+        let esc = Event::Key(KeyEvent::from(KeyCode::Esc));
+        // Only the top layer goes.
+        assert!(matches!(
+            stack.handle_event(&esc),
+            LayerEvent::DismissedAndForwarded('b')
+        ));
+        assert!(matches!(
+            stack.handle_event(&esc),
+            LayerEvent::DismissedAndForwarded('a')
+        ));
+        assert!(matches!(stack.handle_event(&esc), LayerEvent::Ignored));
+    }
+
+    #[test]
+    fn a_modal_layer_keeps_keys_from_the_panes_under_other_layers() {
+        let mut stack = LayerStack::new();
+        stack.push(Layer::new('a', Anchor::Screen, 10, 3).modal());
+        stack.push(Layer::new('b', Anchor::Screen, 10, 3));
+        let key = |code| Event::Key(KeyEvent::from(code));
+        assert!(matches!(
+            stack.handle_event(&key(KeyCode::Char('x'))),
+            LayerEvent::Captured
+        ));
+        // The non-modal top layer goes, but the key stays with the modal.
+        assert!(matches!(
+            stack.handle_event(&key(KeyCode::Esc)),
+            LayerEvent::Dismissed('b')
+        ));
+        assert!(matches!(
+            stack.handle_event(&key(KeyCode::Esc)),
+            LayerEvent::Dismissed('a')
+        ));
+    }
+}
@@ -1,4 +1,29 @@
+pub mod focus_manager;
+pub mod layer_stack;
+pub mod resize_controller;
+pub mod split_tree;
+
 use ratatui::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const MIN_ASIDE_WIDTH: u16 = 10;
+pub const MAX_ASIDE_WIDTH: u16 = 80;
+pub const DEFAULT_ASIDE_WIDTH: u16 = 20;
+
+/// Aside widths as remembered between sessions. A hidden aside is stored as
+/// zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsideSizes {
+    pub left: u16,
+    pub right: u16,
+}
+
+impl Default for AsideSizes {
+    fn default() -> Self {
+        Self { left: DEFAULT_ASIDE_WIDTH, right: 0 }
+    }
+}
 
 pub struct TedLayout {
     pub header_left: Rect,
@@ -54,13 +79,57 @@ impl TedLayoutManager {
             footer_right,
         }
     }
+    pub fn left_aside_width(&self) -> u16 {
+        self.left_aside_width
+    }
+    pub fn right_aside_width(&self) -> u16 {
+        self.right_aside_width
+    }
     pub fn set_left_aside_width(&mut self, width: u16) {
         self.prev_left_aside_width = self.left_aside_width;
-        self.left_aside_width = width.max(1);
+        self.left_aside_width = clamp_aside(width);
     }
     pub fn set_right_aside_width(&mut self, width: u16) {
         self.prev_right_aside_width = self.right_aside_width;
-        self.right_aside_width = width.max(1);
+        self.right_aside_width = clamp_aside(width);
+    }
+
+    /// Grows or shrinks a visible aside; hidden asides stay hidden.
+    pub fn resize_left_aside(&mut self, delta: i32) {
+        if self.left_aside_width > 0 {
+            self.left_aside_width =
+                clamp_aside(offset(self.left_aside_width, delta));
+        }
+    }
+    pub fn resize_right_aside(&mut self, delta: i32) {
+        if self.right_aside_width > 0 {
+            self.right_aside_width =
+                clamp_aside(offset(self.right_aside_width, delta));
+        }
+    }
+    pub fn reset_left_aside(&mut self) {
+        if self.left_aside_width > 0 {
+            self.left_aside_width = DEFAULT_ASIDE_WIDTH;
+        }
+    }
+    pub fn reset_right_aside(&mut self) {
+        if self.right_aside_width > 0 {
+            self.right_aside_width = DEFAULT_ASIDE_WIDTH;
+        }
+    }
+
+    pub fn sizes(&self) -> AsideSizes {
+        AsideSizes {
+            left: self.left_aside_width,
+            right: self.right_aside_width,
+        }
+    }
+    pub fn restore_sizes(&mut self, sizes: AsideSizes) {
+        let restore = |width| if width > 0 { clamp_aside(width) } else { 0 };
+        self.left_aside_width = restore(sizes.left);
+        self.right_aside_width = restore(sizes.right);
+        self.prev_left_aside_width = DEFAULT_ASIDE_WIDTH;
+        self.prev_right_aside_width = DEFAULT_ASIDE_WIDTH;
     }
 
     pub fn hide_left_aside(&mut self) {
@@ -78,3 +147,41 @@ impl TedLayoutManager {
         self.right_aside_width = self.prev_right_aside_width;
     }
 }
+
+fn clamp_aside(width: u16) -> u16 {
+    width.clamp(MIN_ASIDE_WIDTH, MAX_ASIDE_WIDTH)
+}
+
+fn offset(width: u16, delta: i32) -> u16 {
+    (width as i32 + delta).clamp(0, u16::MAX as i32) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resizes_asides_from_the_keyboard_within_bounds() {
+        let mut manager = TedLayoutManager::default();
+        manager.restore_sizes(AsideSizes { left: 500, right: 0 });
+        assert_eq!(
+            manager.sizes(),
+            AsideSizes { left: MAX_ASIDE_WIDTH, right: 0 }
+        );
+        manager.resize_left_aside(-75);
+        assert_eq!(manager.left_aside_width(), MIN_ASIDE_WIDTH);
+        manager.resize_left_aside(3);
+        assert_eq!(manager.left_aside_width(), MIN_ASIDE_WIDTH + 3);
+        // A hidden aside doesn't come back by resizing or resetting it.
+        manager.resize_right_aside(5);
+        manager.reset_right_aside();
+        assert_eq!(manager.right_aside_width(), 0);
+        manager.hide_left_aside();
+        manager.resize_left_aside(5);
+        assert_eq!(manager.left_aside_width(), 0);
+        manager.show_left_aside();
+        assert_eq!(manager.left_aside_width(), MIN_ASIDE_WIDTH + 3);
+        manager.reset_left_aside();
+        assert_eq!(manager.left_aside_width(), DEFAULT_ASIDE_WIDTH);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::split_tree::{SplitBorder, SplitDirection, SplitTree};
+use crate::{TedLayout, TedLayoutManager};
+use ratatui::crossterm::event::{Event, MouseButton, MouseEventKind};
+use ratatui::prelude::*;
+use std::time::{Duration, Instant};
+
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DragTarget {
+    LeftAside,
+    RightAside,
+    Split(SplitBorder),
+}
+
+/// Turns mouse drags on the aside edges and split borders into resizes. A
+/// double-click on a border puts it back to its default position.
+#[derive(Default)]
+pub struct ResizeController {
+    drag: Option<DragTarget>,
+    last_click: Option<(Instant, u16, u16)>,
+}
+
+impl ResizeController {
+    /// Returns `true` when the event grabbed, moved or released a border
+    /// and should not reach the pane underneath.
+    pub fn handle_event<T: Clone>(
+        &mut self,
+        event: &Event,
+        layout: &TedLayout,
+        manager: &mut TedLayoutManager,
+        splits: &mut SplitTree<T>,
+    ) -> bool {
+        let Event::Mouse(mouse) = event else {
+            return false;
+        };
+        let (column, row) = (mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(target) = hit_test(layout, splits, column, row) else {
+                    self.last_click = None;
+                    return false;
+                };
+                let now = Instant::now();
+                let double = self.last_click.is_some_and(|(at, c, r)| {
+                    now.duration_since(at) < DOUBLE_CLICK
+                        && (c, r) == (column, row)
+                });
+                if double {
+                    self.last_click = None;
+                    self.drag = None;
+                    match target {
+                        DragTarget::LeftAside => manager.reset_left_aside(),
+                        DragTarget::RightAside => manager.reset_right_aside(),
+                        DragTarget::Split(border) => {
+                            splits.reset_border(layout.content, &border)
+                        }
+                    }
+                } else {
+                    self.last_click = Some((now, column, row));
+                    self.drag = Some(target);
+                }
+                true
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let Some(target) = &self.drag else {
+                    return false;
+                };
+                match target {
+                    DragTarget::LeftAside => manager.set_left_aside_width(
+                        column.saturating_sub(layout.aside_left.x) + 1,
+                    ),
+                    DragTarget::RightAside => manager.set_right_aside_width(
+                        layout.aside_right.right().saturating_sub(column),
+                    ),
+                    DragTarget::Split(border) => {
+                        let position = match border.direction {
+                            SplitDirection::Vertical => column,
+                            SplitDirection::Horizontal => row,
+                        };
+                        splits.move_border(layout.content, border, position);
+                    }
+                }
+                true
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.drag.take().is_some(),
+            _ => false,
+        }
+    }
+}
+
+fn hit_test<T: Clone>(
+    layout: &TedLayout,
+    splits: &SplitTree<T>,
+    column: u16,
+    row: u16,
+) -> Option<DragTarget> {
+    let pos = Position::new(column, row);
+    let left = layout.aside_left;
+    if !left.is_empty() && left.contains(pos) && column == left.right() - 1 {
+        return Some(DragTarget::LeftAside);
+    }
+    let right = layout.aside_right;
+    if !right.is_empty() && right.contains(pos) && column == right.x {
+        return Some(DragTarget::RightAside);
+    }
+    splits
+        .borders(layout.content)
+        .into_iter()
+        .find(|b| b.area.contains(pos))
+        .map(DragTarget::Split)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AsideSizes, DEFAULT_ASIDE_WIDTH, MAX_ASIDE_WIDTH, MIN_ASIDE_WIDTH,
+    };
+    use ratatui::crossterm::event::{KeyModifiers, MouseEvent};
+
+    const AREA: Rect = Rect::new(0, 0, 120, 40);
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    /// Sends `events` through a controller, laying out again before each
+    /// one as a frame would.
+    fn drag(
+        manager: &mut TedLayoutManager,
+        splits: &mut SplitTree<char>,
+        events: &[Event],
+    ) -> Vec<bool> {
+        let mut controller = ResizeController::default();
+        events
+            .iter()
+            .map(|event| {
+                let layout = manager.build(AREA);
+                controller.handle_event(event, &layout, manager, splits)
+            })
+            .collect()
+    }
+
+    fn with_left_aside() -> TedLayoutManager {
+        let mut manager = TedLayoutManager::default();
+        manager.restore_sizes(AsideSizes::default());
+        manager
+    }
+
+    #[test]
+    fn drags_the_aside_edge_within_bounds() {
+        let mut manager = with_left_aside();
+        let mut splits = SplitTree::new('a');
+        let edge = DEFAULT_ASIDE_WIDTH - 1;
+        let handled = drag(
+            &mut manager,
+            &mut splits,
+            &[
+                mouse(MouseEventKind::Down(MouseButton::Left), edge, 10),
+                mouse(MouseEventKind::Drag(MouseButton::Left), 29, 10),
+            ],
+        );
+        assert_eq!(handled, [true, true]);
+        assert_eq!(manager.left_aside_width(), 30);
+
+        let clamped = [(2, MIN_ASIDE_WIDTH), (200, MAX_ASIDE_WIDTH)];
+        for (column, width) in clamped {
+            let edge = manager.left_aside_width() - 1;
+            drag(
+                &mut manager,
+                &mut splits,
+                &[
+                    mouse(MouseEventKind::Down(MouseButton::Left), edge, 20),
+                    mouse(MouseEventKind::Drag(MouseButton::Left), column, 20),
+                    mouse(MouseEventKind::Up(MouseButton::Left), column, 20),
+                ],
+            );
+            assert_eq!(manager.left_aside_width(), width);
+        }
+    }
+
+    #[test]
+    fn leaves_clicks_off_the_borders_alone() {
+        let mut manager = with_left_aside();
+        let mut splits = SplitTree::new('a');
+        let handled = drag(
+            &mut manager,
+            &mut splits,
+            &[
+                mouse(MouseEventKind::Down(MouseButton::Left), 5, 10),
+                mouse(MouseEventKind::Drag(MouseButton::Left), 40, 10),
+                mouse(MouseEventKind::Up(MouseButton::Left), 40, 10),
+            ],
+        );
+        assert_eq!(handled, [false, false, false]);
+        assert_eq!(manager.left_aside_width(), DEFAULT_ASIDE_WIDTH);
+    }
+
+    #[test]
+    fn drags_split_borders_and_double_click_evens_them() {
+        let mut manager = with_left_aside();
+        let mut splits = SplitTree::new('a');
+        splits.split(0, SplitDirection::Vertical);
+        let content = manager.build(AREA).content;
+        let border = splits.borders(content)[0].area;
+        drag(
+            &mut manager,
+            &mut splits,
+            &[
+                mouse(MouseEventKind::Down(MouseButton::Left), border.x, 10),
+                mouse(MouseEventKind::Drag(MouseButton::Left), 39, 10),
+                mouse(MouseEventKind::Up(MouseButton::Left), 39, 10),
+            ],
+        );
+        let first = splits.layout(content)[0].1;
+        assert_eq!(first.right() - 1, 39);
+
+        let border = splits.borders(content)[0].area;
+        let click =
+            mouse(MouseEventKind::Down(MouseButton::Left), border.x, 10);
+        drag(&mut manager, &mut splits, &[click.clone(), click]);
+        let widths: Vec<u16> =
+            splits.layout(content).iter().map(|(_, r)| r.width).collect();
+        assert_eq!(widths, [content.width / 2, content.width / 2]);
+    }
+}
@@ -0,0 +1,602 @@
+use ratatui::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Views never shrink below this many cells along a split.
+pub const MIN_VIEW_SIZE: u16 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitDirection {
+    /// Views stacked on top of each other, like Vim's `:split`.
+    Horizontal,
+    /// Views side by side, like Vim's `:vsplit`.
+    Vertical,
+}
+
+/// The draggable edge between two neighbouring children of a split.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitBorder {
+    path: Vec<usize>,
+    index: usize,
+    pub direction: SplitDirection,
+    /// The one-cell-wide strip that grabs the border.
+    pub area: Rect,
+}
+
+/// A serializable snapshot of a `SplitTree`. View ids are not kept; they
+/// are handed out again in leaf order on restore.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitLayout<T> {
+    View(T),
+    Split {
+        direction: SplitDirection,
+        weights: Vec<u16>,
+        children: Vec<SplitLayout<T>>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Node<T> {
+    Leaf {
+        id: usize,
+        value: T,
+    },
+    Split {
+        direction: SplitDirection,
+        children: Vec<Node<T>>,
+        weights: Vec<u16>,
+    },
+}
+
+impl<T> Node<T> {
+    fn contains(&self, target: usize) -> bool {
+        match self {
+            Node::Leaf { id, .. } => *id == target,
+            Node::Split { children, .. } => {
+                children.iter().any(|c| c.contains(target))
+            }
+        }
+    }
+
+    fn first_leaf(&self) -> usize {
+        match self {
+            Node::Leaf { id, .. } => *id,
+            Node::Split { children, .. } => children[0].first_leaf(),
+        }
+    }
+
+    fn collect_leaves<'a>(&'a self, out: &mut Vec<(usize, &'a T)>) {
+        match self {
+            Node::Leaf { id, value } => out.push((*id, value)),
+            Node::Split { children, .. } => {
+                children.iter().for_each(|c| c.collect_leaves(out))
+            }
+        }
+    }
+
+    fn collect_leaves_mut<'a>(&'a mut self, out: &mut Vec<&'a mut T>) {
+        match self {
+            Node::Leaf { value, .. } => out.push(value),
+            Node::Split { children, .. } => {
+                children.iter_mut().for_each(|c| c.collect_leaves_mut(out))
+            }
+        }
+    }
+
+    fn layout(&self, area: Rect, out: &mut Vec<(usize, Rect)>) {
+        match self {
+            Node::Leaf { id, .. } => out.push((*id, area)),
+            Node::Split { direction, children, weights } => {
+                let constraints = weights.iter().map(|w| Constraint::Fill(*w));
+                let layout = match direction {
+                    SplitDirection::Horizontal => Layout::vertical(constraints),
+                    SplitDirection::Vertical => Layout::horizontal(constraints),
+                };
+                let rects = layout.split(area);
+                for (child, rect) in children.iter().zip(rects.iter()) {
+                    child.layout(*rect, out);
+                }
+            }
+        }
+    }
+
+    fn child_rects(&self, area: Rect) -> Vec<Rect> {
+        let Node::Split { direction, weights, .. } = self else {
+            return vec![area];
+        };
+        let constraints = weights.iter().map(|w| Constraint::Fill(*w));
+        let layout = match direction {
+            SplitDirection::Horizontal => Layout::vertical(constraints),
+            SplitDirection::Vertical => Layout::horizontal(constraints),
+        };
+        layout.split(area).to_vec()
+    }
+
+    fn collect_borders(
+        &self,
+        area: Rect,
+        path: &mut Vec<usize>,
+        out: &mut Vec<SplitBorder>,
+    ) {
+        let Node::Split { direction, children, .. } = self else {
+            return;
+        };
+        let rects = self.child_rects(area);
+        for (idx, (child, rect)) in children.iter().zip(&rects).enumerate() {
+            if idx + 1 < children.len() {
+                // The last row/column of a view is where its border sits.
+                let strip = match direction {
+                    SplitDirection::Vertical => Rect::new(
+                        rect.right().saturating_sub(1),
+                        rect.y,
+                        1,
+                        rect.height,
+                    ),
+                    SplitDirection::Horizontal => Rect::new(
+                        rect.x,
+                        rect.bottom().saturating_sub(1),
+                        rect.width,
+                        1,
+                    ),
+                };
+                out.push(SplitBorder {
+                    path: path.clone(),
+                    index: idx,
+                    direction: *direction,
+                    area: strip,
+                });
+            }
+            path.push(idx);
+            child.collect_borders(*rect, path, out);
+            path.pop();
+        }
+    }
+
+    fn path_to(&self, target: usize, path: &mut Vec<usize>) -> bool {
+        match self {
+            Node::Leaf { id, .. } => *id == target,
+            Node::Split { children, .. } => {
+                for (idx, child) in children.iter().enumerate() {
+                    path.push(idx);
+                    if child.path_to(target, path) {
+                        return true;
+                    }
+                    path.pop();
+                }
+                false
+            }
+        }
+    }
+
+    /// Resolves `path` to a node and the area it occupies within `area`.
+    fn at_path_mut(
+        &mut self,
+        area: Rect,
+        path: &[usize],
+    ) -> Option<(&mut Node<T>, Rect)> {
+        let Some((first, rest)) = path.split_first() else {
+            return Some((self, area));
+        };
+        let rect = *self.child_rects(area).get(*first)?;
+        match self {
+            Node::Split { children, .. } => {
+                children.get_mut(*first)?.at_path_mut(rect, rest)
+            }
+            Node::Leaf { .. } => None,
+        }
+    }
+
+    /// Moves the boundary after child `index` of this split by `delta`
+    /// cells, taking the space from its neighbour.
+    fn shift_boundary(&mut self, area: Rect, index: usize, delta: i32) {
+        let rects = self.child_rects(area);
+        let Node::Split { direction, weights, .. } = self else {
+            return;
+        };
+        if index + 1 >= weights.len() {
+            return;
+        }
+        let mut sizes: Vec<i32> = rects
+            .iter()
+            .map(|r| match direction {
+                SplitDirection::Horizontal => r.height as i32,
+                SplitDirection::Vertical => r.width as i32,
+            })
+            .collect();
+        let min = MIN_VIEW_SIZE as i32;
+        let delta = delta.max(min - sizes[index]).min(sizes[index + 1] - min);
+        if delta == 0 || sizes[index] + delta < min {
+            return;
+        }
+        sizes[index] += delta;
+        sizes[index + 1] -= delta;
+        // Fill weights proportional to cell sizes reproduce them exactly.
+        for (weight, size) in weights.iter_mut().zip(sizes) {
+            *weight = size.max(1) as u16;
+        }
+    }
+
+    fn to_layout(&self) -> SplitLayout<T>
+    where
+        T: Clone,
+    {
+        match self {
+            Node::Leaf { value, .. } => SplitLayout::View(value.clone()),
+            Node::Split { direction, children, weights } => {
+                SplitLayout::Split {
+                    direction: *direction,
+                    weights: weights.clone(),
+                    children: children.iter().map(Node::to_layout).collect(),
+                }
+            }
+        }
+    }
+
+    fn from_layout(
+        layout: SplitLayout<T>,
+        next_id: &mut usize,
+    ) -> Option<Self> {
+        match layout {
+            SplitLayout::View(value) => {
+                *next_id += 1;
+                Some(Node::Leaf { id: *next_id - 1, value })
+            }
+            SplitLayout::Split { direction, weights, children } => {
+                let mut children: Vec<Node<T>> = children
+                    .into_iter()
+                    .map(|c| Node::from_layout(c, next_id))
+                    .collect::<Option<_>>()?;
+                match children.len() {
+                    0 => None,
+                    1 => children.pop(),
+                    n => {
+                        let weights = if weights.len() == n {
+                            weights.iter().map(|w| (*w).max(1)).collect()
+                        } else {
+                            vec![1; n]
+                        };
+                        Some(Node::Split { direction, children, weights })
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds the split whose direct child contains `target`.
+    fn parent_of(&mut self, target: usize) -> Option<&mut Node<T>> {
+        let Node::Split { children, .. } = self else {
+            return None;
+        };
+        let is_parent = children
+            .iter()
+            .any(|c| matches!(c, Node::Leaf { id, .. } if *id == target));
+        if is_parent {
+            return Some(self);
+        }
+        let Node::Split { children, .. } = self else { unreachable!() };
+        children.iter_mut().find_map(|c| c.parent_of(target))
+    }
+
+    fn equalize(&mut self) {
+        if let Node::Split { children, weights, .. } = self {
+            weights.iter_mut().for_each(|w| *w = 1);
+            children.iter_mut().for_each(|c| c.equalize());
+        }
+    }
+}
+
+/// Recursive layout of editor views inside the content area. Each leaf is a
+/// view with a stable id and carries a value, typically the buffer it shows.
+#[derive(Debug, Clone)]
+pub struct SplitTree<T> {
+    root: Node<T>,
+    next_id: usize,
+}
+
+impl<T: Clone> SplitTree<T> {
+    pub fn new(value: T) -> Self {
+        Self { root: Node::Leaf { id: 0, value }, next_id: 1 }
+    }
+
+    pub fn to_layout(&self) -> SplitLayout<T> {
+        self.root.to_layout()
+    }
+
+    /// Rebuilds a tree from a snapshot, numbering views `0..n` in the same
+    /// order as `views()`. Returns `None` for a layout without any view.
+    pub fn from_layout(layout: SplitLayout<T>) -> Option<Self> {
+        let mut next_id = 0;
+        let root = Node::from_layout(layout, &mut next_id)?;
+        Some(Self { root, next_id })
+    }
+
+    /// Splits view `target`, placing a copy of it after itself. Returns the
+    /// id of the new view.
+    pub fn split(
+        &mut self,
+        target: usize,
+        direction: SplitDirection,
+    ) -> Option<usize> {
+        let new_id = self.next_id;
+        let value = self.get(target)?.clone();
+        let new_leaf = Node::Leaf { id: new_id, value };
+
+        if let Some(Node::Split { direction: d, children, weights }) =
+            self.root.parent_of(target)
+            && *d == direction
+        {
+            let idx = children.iter().position(|c| c.contains(target))?;
+            children.insert(idx + 1, new_leaf);
+            weights.insert(idx + 1, weights[idx]);
+        } else {
+            let leaf = self.find_leaf_mut(target)?;
+            let old = std::mem::replace(
+                leaf,
+                Node::Split {
+                    direction,
+                    children: vec![],
+                    weights: vec![1, 1],
+                },
+            );
+            if let Node::Split { children, .. } = leaf {
+                children.push(old);
+                children.push(new_leaf);
+            }
+        }
+        self.next_id += 1;
+        Some(new_id)
+    }
+
+    /// Closes view `target`. The last remaining view can't be closed.
+    /// Returns the view that should take focus.
+    pub fn close(&mut self, target: usize) -> Option<usize> {
+        let parent = self.root.parent_of(target)?;
+        let Node::Split { children, weights, .. } = parent else {
+            return None;
+        };
+        let idx = children.iter().position(|c| c.contains(target))?;
+        children.remove(idx);
+        weights.remove(idx);
+        let next = children[idx.min(children.len() - 1)].first_leaf();
+        if children.len() == 1 {
+            let only = children.pop()?;
+            *parent = only;
+        }
+        Some(next)
+    }
+
+    /// Makes `target` the only view.
+    pub fn only(&mut self, target: usize) {
+        if let Some(Node::Leaf { id, value }) = self.find_leaf_mut(target) {
+            self.root = Node::Leaf { id: *id, value: value.clone() };
+        }
+    }
+
+    pub fn equalize(&mut self) {
+        self.root.equalize();
+    }
+
+    /// Rotates the views that share a split with `target` forwards, like
+    /// Vim's `Ctrl-W r`.
+    pub fn rotate(&mut self, target: usize) {
+        if let Some(Node::Split { children, weights, .. }) =
+            self.root.parent_of(target)
+        {
+            children.rotate_right(1);
+            weights.rotate_right(1);
+        }
+    }
+
+    pub fn layout(&self, area: Rect) -> Vec<(usize, Rect)> {
+        let mut out = vec![];
+        self.root.layout(area, &mut out);
+        out
+    }
+
+    pub fn views(&self) -> Vec<(usize, &T)> {
+        let mut out = vec![];
+        self.root.collect_leaves(&mut out);
+        out
+    }
+
+    pub fn values_mut(&mut self) -> Vec<&mut T> {
+        let mut out = vec![];
+        self.root.collect_leaves_mut(&mut out);
+        out
+    }
+
+    pub fn get(&self, target: usize) -> Option<&T> {
+        self.views().into_iter().find(|(id, _)| *id == target).map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, target: usize) -> Option<&mut T> {
+        match self.find_leaf_mut(target)? {
+            Node::Leaf { value, .. } => Some(value),
+            Node::Split { .. } => None,
+        }
+    }
+
+    pub fn borders(&self, area: Rect) -> Vec<SplitBorder> {
+        let mut out = vec![];
+        self.root.collect_borders(area, &mut vec![], &mut out);
+        out
+    }
+
+    /// Drags `border` so it sits at `position`, a column for vertical splits
+    /// and a row for horizontal ones.
+    pub fn move_border(
+        &mut self,
+        area: Rect,
+        border: &SplitBorder,
+        position: u16,
+    ) {
+        let Some((node, rect)) = self.root.at_path_mut(area, &border.path)
+        else {
+            return;
+        };
+        let current = node.child_rects(rect)[border.index];
+        let edge = match border.direction {
+            SplitDirection::Vertical => current.right().saturating_sub(1),
+            SplitDirection::Horizontal => current.bottom().saturating_sub(1),
+        };
+        node.shift_boundary(rect, border.index, position as i32 - edge as i32);
+    }
+
+    /// Evens out the split that owns `border`, used when it is double-clicked.
+    pub fn reset_border(&mut self, area: Rect, border: &SplitBorder) {
+        if let Some((Node::Split { weights, .. }, _)) =
+            self.root.at_path_mut(area, &border.path)
+        {
+            weights.iter_mut().for_each(|w| *w = 1);
+        }
+    }
+
+    /// Grows (or with a negative `delta`, shrinks) view `target` along
+    /// `direction`, using the closest enclosing split in that direction.
+    pub fn resize_view(
+        &mut self,
+        area: Rect,
+        target: usize,
+        direction: SplitDirection,
+        delta: i32,
+    ) {
+        let mut path = vec![];
+        if !self.root.path_to(target, &mut path) {
+            return;
+        }
+        for depth in (0..path.len()).rev() {
+            let Some((node, rect)) =
+                self.root.at_path_mut(area, &path[..depth])
+            else {
+                return;
+            };
+            let Node::Split { direction: d, children, .. } = &*node else {
+                continue;
+            };
+            if *d != direction {
+                continue;
+            }
+            let idx = path[depth];
+            if idx + 1 < children.len() {
+                node.shift_boundary(rect, idx, delta);
+            } else {
+                node.shift_boundary(rect, idx - 1, -delta);
+            }
+            return;
+        }
+    }
+
+    fn find_leaf_mut(&mut self, target: usize) -> Option<&mut Node<T>> {
+        fn find<T>(node: &mut Node<T>, target: usize) -> Option<&mut Node<T>> {
+            match node {
+                Node::Leaf { id, .. } if *id == target => Some(node),
+                Node::Leaf { .. } => None,
+                Node::Split { children, .. } => {
+                    children.iter_mut().find_map(|c| find(c, target))
+                }
+            }
+        }
+        find(&mut self.root, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(tree: &SplitTree<char>) -> Vec<usize> {
+        tree.views().into_iter().map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn splits_and_lays_out() {
+        let mut tree = SplitTree::new('a');
+        let right = tree.split(0, SplitDirection::Vertical).unwrap();
+        let below = tree.split(right, SplitDirection::Horizontal).unwrap();
+        assert_eq!(ids(&tree), vec![0, right, below]);
+        let rects = tree.layout(Rect::new(0, 0, 100, 40));
+        assert_eq!(rects[0].1, Rect::new(0, 0, 50, 40));
+        assert_eq!(rects[1].1, Rect::new(50, 0, 50, 20));
+        assert_eq!(rects[2].1, Rect::new(50, 20, 50, 20));
+    }
+
+    #[test]
+    fn same_direction_splits_stay_flat() {
+        let mut tree = SplitTree::new('a');
+        tree.split(0, SplitDirection::Vertical);
+        tree.split(0, SplitDirection::Vertical);
+        let widths: Vec<u16> = tree
+            .layout(Rect::new(0, 0, 90, 10))
+            .iter()
+            .map(|(_, r)| r.width)
+            .collect();
+        assert_eq!(widths, vec![30, 30, 30]);
+    }
+
+    #[test]
+    fn close_collapses_single_child_splits() {
+        let mut tree = SplitTree::new('a');
+        let right = tree.split(0, SplitDirection::Vertical).unwrap();
+        let below = tree.split(right, SplitDirection::Horizontal).unwrap();
+        assert_eq!(tree.close(below), Some(right));
+        assert_eq!(tree.close(0), Some(right));
+        assert_eq!(ids(&tree), vec![right]);
+        assert_eq!(tree.close(right), None);
+    }
+
+    #[test]
+    fn drags_and_resets_borders() {
+        let area = Rect::new(0, 0, 100, 40);
+        let mut tree = SplitTree::new('a');
+        let right = tree.split(0, SplitDirection::Vertical).unwrap();
+        let borders = tree.borders(area);
+        assert_eq!(borders.len(), 1);
+        assert_eq!(borders[0].area, Rect::new(49, 0, 1, 40));
+        tree.move_border(area, &borders[0], 29);
+        assert_eq!(tree.layout(area)[0].1.width, 30);
+        tree.move_border(area, &borders[0], 99);
+        assert_eq!(tree.layout(area)[1].1.width, MIN_VIEW_SIZE);
+        tree.reset_border(area, &borders[0]);
+        assert_eq!(tree.layout(area)[0].1.width, 50);
+
+        tree.resize_view(area, right, SplitDirection::Vertical, 10);
+        assert_eq!(tree.layout(area)[1].1.width, 60);
+        tree.resize_view(area, right, SplitDirection::Horizontal, 10);
+        assert_eq!(tree.layout(area)[1].1.width, 60);
+    }
+
+    #[test]
+    fn round_trips_layouts() {
+        let mut tree = SplitTree::new('a');
+        let right = tree.split(0, SplitDirection::Vertical).unwrap();
+        tree.split(right, SplitDirection::Horizontal).unwrap();
+        let layout = tree.to_layout();
+        let restored = SplitTree::from_layout(layout.clone()).unwrap();
+        assert_eq!(ids(&restored), vec![0, 1, 2]);
+        assert_eq!(restored.to_layout(), layout);
+        let area = Rect::new(0, 0, 100, 40);
+        let rects = |t: &SplitTree<char>| -> Vec<Rect> {
+            t.layout(area).into_iter().map(|(_, r)| r).collect()
+        };
+        assert_eq!(rects(&restored), rects(&tree));
+
+        let empty = SplitLayout::<char>::Split {
+            direction: SplitDirection::Vertical,
+            weights: vec![],
+            children: vec![],
+        };
+        assert!(SplitTree::from_layout(empty).is_none());
+    }
+
+    #[test]
+    fn rotates_and_only() {
+        let mut tree = SplitTree::new('a');
+        let b = tree.split(0, SplitDirection::Vertical).unwrap();
+        *tree.get_mut(b).unwrap() = 'b';
+        tree.rotate(0);
+        assert_eq!(ids(&tree), vec![b, 0]);
+        tree.only(0);
+        assert_eq!(tree.views(), vec![(0, &'a')]);
+    }
+}
@@ -0,0 +1,393 @@
+//! A tiny language server for tests: it keeps the documents it is sent in
+//! sync, incremental edits included, and reports a warning for every `TODO`
+//! and an error for every `FIXME` in them. Completion always offers
+//! `mock_fn`, which comes with an import. A word is defined and implemented
+//! where `fn word` first appears, and referenced wherever it does; renaming
+//! it renames it in every open document. Formatting trims trailing spaces,
+//! and a `FIXME` can be removed with a quick fix or, through a command the
+//! server runs, marked done. How the client answered that edit comes back
+//! as a hint on the document. Braces spanning lines fold.
+
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{self, BufReader, Write};
+use ted_lsp::transport::{read_message, write_message};
+
+fn main() -> io::Result<()> {
+    let mut input = BufReader::new(io::stdin().lock());
+    let mut output = io::stdout().lock();
+    let mut documents: HashMap<String, String> = HashMap::new();
+    // The document a `DONE` edit was sent for, until the client answers.
+    let mut applying: Option<String> = None;
+    while let Some(message) = read_message(&mut input)? {
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        if message["id"] == "mock-apply"
+            && message.get("method").is_none()
+            && let Some(uri) = applying.take()
+        {
+            let text = documents.get(&uri).map_or("", String::as_str);
+            let result = &message["result"];
+            let note = match result["applied"].as_bool() {
+                Some(true) if text.contains("DONE") => "edit applied".into(),
+                Some(true) => "edit applied before it was synced".into(),
+                _ => format!(
+                    "edit failed: {}",
+                    result["failureReason"].as_str().unwrap_or_default()
+                ),
+            };
+            publish(&mut output, &uri, text, Some(&note))?;
+            continue;
+        }
+        match message["method"].as_str().unwrap_or_default() {
+            "initialize" => {
+                let capabilities = json!({
+                    "textDocumentSync": { "change": 2 },
+                    "foldingRangeProvider": true,
+                });
+                reply(
+                    &mut output,
+                    &message,
+                    json!({ "capabilities": capabilities }),
+                )?;
+            }
+            "shutdown" => reply(&mut output, &message, Value::Null)?,
+            "textDocument/completion" => {
+                let text = documents.get(uri).map_or("", String::as_str);
+                let items = completion(text, &params["position"]);
+                reply(&mut output, &message, items)?;
+            }
+            "textDocument/definition" | "textDocument/implementation" => {
+                let text = documents.get(uri).map_or("", String::as_str);
+                let word = word_at(text, &params["position"]);
+                let found = occurrences(text, &format!("fn {word}"));
+                let location = found
+                    .first()
+                    .map(|&(row, col)| location(uri, row, col + 3, word.len()));
+                reply(&mut output, &message, json!(location))?;
+            }
+            "textDocument/references" => {
+                let text = documents.get(uri).map_or("", String::as_str);
+                let word = word_at(text, &params["position"]);
+                let locations: Vec<Value> = occurrences(text, &word)
+                    .into_iter()
+                    .map(|(row, col)| location(uri, row, col, word.len()))
+                    .collect();
+                reply(&mut output, &message, json!(locations))?;
+            }
+            "textDocument/hover" => {
+                let text = documents.get(uri).map_or("", String::as_str);
+                let word = word_at(text, &params["position"]);
+                let value = format!(
+                    "```rust\nfn {word}()\n```\n\nThe **{word}** function."
+                );
+                let contents = json!({ "kind": "markdown", "value": value });
+                reply(&mut output, &message, json!({ "contents": contents }))?;
+            }
+            "textDocument/rename" => {
+                let word = documents
+                    .get(uri)
+                    .map(|text| word_at(text, &params["position"]))
+                    .unwrap_or_default();
+                let new_name = params["newName"].as_str().unwrap_or_default();
+                let changes: serde_json::Map<String, Value> = documents
+                    .iter()
+                    .map(|(uri, text)| {
+                        let edits: Vec<Value> = occurrences(text, &word)
+                            .into_iter()
+                            .map(|(row, col)| {
+                                edit(row, col, col + word.len(), new_name)
+                            })
+                            .collect();
+                        (uri.clone(), json!(edits))
+                    })
+                    .collect();
+                reply(&mut output, &message, json!({ "changes": changes }))?;
+            }
+            "textDocument/formatting" | "textDocument/rangeFormatting" => {
+                let text = documents.get(uri).map_or("", String::as_str);
+                let rows = match params.get("range") {
+                    Some(range) => {
+                        let row = |key: &str| {
+                            range[key]["line"].as_u64().unwrap_or(0) as usize
+                        };
+                        row("start")..row("end") + 1
+                    }
+                    None => 0..usize::MAX,
+                };
+                let edits: Vec<Value> = text
+                    .lines()
+                    .enumerate()
+                    .filter(|(row, line)| {
+                        rows.contains(row) && line.ends_with(' ')
+                    })
+                    .map(|(row, line)| {
+                        edit(row, line.trim_end().len(), line.len(), "")
+                    })
+                    .collect();
+                reply(&mut output, &message, json!(edits))?;
+            }
+            "textDocument/foldingRange" => {
+                let text = documents.get(uri).map_or("", String::as_str);
+                reply(&mut output, &message, folding_ranges(text))?;
+            }
+            "textDocument/codeAction" => {
+                let text = documents.get(uri).map_or("", String::as_str);
+                let actions = code_actions(uri, text, &params["context"]);
+                reply(&mut output, &message, actions)?;
+            }
+            "workspace/executeCommand" => {
+                let arguments = &params["arguments"];
+                let (uri, row) = (&arguments[0], &arguments[1]);
+                applying = uri.as_str().map(str::to_string);
+                let col = arguments[2].as_u64().unwrap_or(0) as usize;
+                let edits = [edit(
+                    row.as_u64().unwrap_or(0) as usize,
+                    col,
+                    col + 5,
+                    "DONE",
+                )];
+                write_message(
+                    &mut output,
+                    &json!({
+                        "jsonrpc": "2.0",
+                        "id": "mock-apply",
+                        "method": "workspace/applyEdit",
+                        "params": {
+                            "label": "mark done",
+                            "edit": { "changes": { uri.as_str().unwrap_or_default(): edits } },
+                        },
+                    }),
+                )?;
+                reply(&mut output, &message, Value::Null)?;
+            }
+            "exit" => return Ok(()),
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str();
+                documents
+                    .insert(uri.to_string(), text.unwrap_or_default().into());
+                publish(&mut output, uri, &documents[uri], None)?;
+            }
+            "textDocument/didChange" => {
+                let Some(text) = documents.get_mut(uri) else {
+                    continue;
+                };
+                for change in
+                    params["contentChanges"].as_array().into_iter().flatten()
+                {
+                    apply(text, change);
+                }
+                publish(&mut output, uri, &documents[uri], None)?;
+            }
+            "textDocument/didClose" => {
+                documents.remove(uri);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn reply(
+    output: &mut impl Write,
+    request: &Value,
+    result: Value,
+) -> io::Result<()> {
+    let id = &request["id"];
+    write_message(
+        output,
+        &json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+    )
+}
+
+fn completion(text: &str, position: &Value) -> Value {
+    let row = position["line"].as_u64().unwrap_or(0);
+    let col = position["character"].as_u64().unwrap_or(0) as usize;
+    let line = text.lines().nth(row as usize).unwrap_or_default();
+    let before = &line[..col.min(line.len())];
+    let start = before
+        .rfind(|c: char| !c.is_alphanumeric() && c != '_')
+        .map_or(0, |idx| idx + 1);
+    json!([{
+        "label": "mock_fn",
+        "kind": 3,
+        "detail": "fn mock_fn(x: u32)",
+        "insertTextFormat": 2,
+        "textEdit": {
+            "range": {
+                "start": { "line": row, "character": start },
+                "end": { "line": row, "character": col },
+            },
+            "newText": "mock_fn(${1:x})",
+        },
+        "additionalTextEdits": [{
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 0, "character": 0 },
+            },
+            "newText": "use mock::mock_fn;\n",
+        }],
+    }])
+}
+
+/// The word around an LSP position.
+fn word_at(text: &str, position: &Value) -> String {
+    let row = position["line"].as_u64().unwrap_or(0) as usize;
+    let col = position["character"].as_u64().unwrap_or(0) as usize;
+    let line = text.lines().nth(row).unwrap_or_default();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let col = col.min(line.len());
+    let start = line[..col].rfind(|c| !is_word(c)).map_or(0, |idx| idx + 1);
+    let end =
+        line[col..].find(|c| !is_word(c)).map_or(line.len(), |idx| col + idx);
+    line[start..end].to_string()
+}
+
+/// Where `needle` appears in `text`, as `(row, column)`.
+fn occurrences(text: &str, needle: &str) -> Vec<(usize, usize)> {
+    if needle.is_empty() {
+        return vec![];
+    }
+    text.lines()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            line.match_indices(needle).map(move |(col, _)| (row, col))
+        })
+        .collect()
+}
+
+fn edit(row: usize, start: usize, end: usize, text: &str) -> Value {
+    json!({
+        "range": {
+            "start": { "line": row, "character": start },
+            "end": { "line": row, "character": end },
+        },
+        "newText": text,
+    })
+}
+
+/// For each `FIXME` diagnostic handed back: a quick fix removing it, and a
+/// command marking it done.
+fn code_actions(uri: &str, text: &str, context: &Value) -> Value {
+    let mut actions = vec![];
+    for diagnostic in context["diagnostics"].as_array().into_iter().flatten() {
+        if diagnostic["severity"] != 1 {
+            continue;
+        }
+        let start = &diagnostic["range"]["start"];
+        let row = start["line"].as_u64().unwrap_or(0) as usize;
+        let col = start["character"].as_u64().unwrap_or(0) as usize;
+        if !text.lines().nth(row).is_some_and(|l| l[col..].starts_with("FIXME"))
+        {
+            continue;
+        }
+        actions.push(json!({
+            "title": "Remove FIXME",
+            "kind": "quickfix",
+            "isPreferred": true,
+            "diagnostics": [diagnostic],
+            "edit": { "changes": { uri: [edit(row, col, col + 5, "")] } },
+        }));
+        actions.push(json!({
+            "title": "Mark FIXME done",
+            "command": "mock.markDone",
+            "arguments": [uri, row, col],
+        }));
+    }
+    json!(actions)
+}
+
+/// Every `{` with its `}` on a later line.
+fn folding_ranges(text: &str) -> Value {
+    let mut open = vec![];
+    let mut ranges = vec![];
+    for (row, line) in text.lines().enumerate() {
+        for c in line.chars() {
+            match c {
+                '{' => open.push(row),
+                '}' => match open.pop() {
+                    Some(start) if start < row => ranges
+                        .push(json!({ "startLine": start, "endLine": row })),
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+    json!(ranges)
+}
+
+fn location(uri: &str, row: usize, col: usize, len: usize) -> Value {
+    json!({
+        "uri": uri,
+        "range": {
+            "start": { "line": row, "character": col },
+            "end": { "line": row, "character": col + len },
+        },
+    })
+}
+
+fn apply(text: &mut String, change: &Value) {
+    let new_text = change["text"].as_str().unwrap_or_default();
+    if change.get("range").is_none() {
+        *text = new_text.to_string();
+        return;
+    }
+    let start = offset(text, &change["range"]["start"]);
+    let end = offset(text, &change["range"]["end"]);
+    text.replace_range(start..end, new_text);
+}
+
+/// The byte offset of an LSP position. Good enough for ASCII test files.
+fn offset(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+    let line_start: usize =
+        text.split_inclusive('\n').take(line).map(str::len).sum();
+    (line_start + character).min(text.len())
+}
+
+/// The diagnostics of `text`, with `note` as a hint at its start.
+fn publish(
+    output: &mut impl Write,
+    uri: &str,
+    text: &str,
+    note: Option<&str>,
+) -> io::Result<()> {
+    let mut diagnostics = vec![];
+    if let Some(note) = note {
+        diagnostics.push(json!({
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 0, "character": 0 },
+            },
+            "severity": 4,
+            "message": note,
+            "source": "mock",
+        }));
+    }
+    for (row, line) in text.lines().enumerate() {
+        for (word, severity) in [("TODO", 2), ("FIXME", 1)] {
+            if let Some(col) = line.find(word) {
+                diagnostics.push(json!({
+                    "range": {
+                        "start": { "line": row, "character": col },
+                        "end": { "line": row, "character": col + word.len() },
+                    },
+                    "severity": severity,
+                    "message": format!("{word} left in the code"),
+                    "source": "mock",
+                }));
+            }
+        }
+    }
+    let params = json!({ "uri": uri, "diagnostics": diagnostics });
+    write_message(
+        output,
+        &json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": params,
+        }),
+    )
+}
@@ -0,0 +1,91 @@
+use crate::edit::WorkspaceEdit;
+use serde_json::Value;
+use std::path::Path;
+
+/// A fix or refactoring a server offers at the cursor.
+#[derive(Debug, Clone)]
+pub struct CodeAction {
+    pub title: String,
+    /// Like `quickfix` or `refactor.extract`.
+    pub kind: Option<String>,
+    /// The fix the server thinks is most likely wanted.
+    pub preferred: bool,
+    /// Why the action can't be run right now, if it can't.
+    pub disabled: Option<String>,
+    pub edit: Option<WorkspaceEdit>,
+    /// A `Command` for the server to run, after `edit` if both are given.
+    pub(crate) command: Option<Value>,
+    /// The server that offered the action, which runs its command.
+    pub(crate) language: String,
+}
+
+/// The actions in a `textDocument/codeAction` result, preferred ones first.
+/// Bare `Command`s count as actions that only run a command.
+pub fn actions(
+    result: &Value,
+    language: &str,
+    mut lines_of: impl FnMut(&Path) -> Vec<String>,
+) -> Vec<CodeAction> {
+    let mut actions: Vec<CodeAction> = result
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|action| {
+            let title = action["title"].as_str()?.to_string();
+            // A `Command` has its command name where an action has an
+            // object.
+            let command = match &action["command"] {
+                Value::String(_) => Some(action.clone()),
+                Value::Null => None,
+                command => Some(command.clone()),
+            };
+            let edit = action
+                .get("edit")
+                .map(|edit| WorkspaceEdit::parse(edit, &mut lines_of));
+            Some(CodeAction {
+                title,
+                kind: action["kind"].as_str().map(str::to_string),
+                preferred: action["isPreferred"].as_bool().unwrap_or(false),
+                disabled: action["disabled"]["reason"]
+                    .as_str()
+                    .map(str::to_string),
+                edit,
+                command,
+                language: language.to_string(),
+            })
+        })
+        .collect();
+    actions
+        .sort_by_key(|action| (action.disabled.is_some(), !action.preferred));
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_actions_and_commands() {
+        let result = json!([
+            { "title": "Run", "command": "run", "arguments": [1] },
+            {
+                "title": "Import x",
+                "kind": "quickfix",
+                "isPreferred": true,
+                "edit": { "changes": { "file:///a.rs": [] } },
+            },
+            { "title": "Extract", "disabled": { "reason": "no selection" } },
+        ]);
+        let actions = actions(&result, "rust", |_| vec![]);
+        let titles: Vec<&str> =
+            actions.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, ["Import x", "Run", "Extract"]);
+        assert!(actions[0].edit.is_some() && actions[0].command.is_none());
+        assert_eq!(
+            actions[1].command.as_ref().unwrap()["arguments"],
+            json!([1])
+        );
+        assert_eq!(actions[2].disabled.as_deref(), Some("no selection"));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::sync;
+use serde_json::Value;
+use ted_editor::completion::{CompletionItem, Source, word_start};
+
+/// The items of a `textDocument/completion` result for the cursor at
+/// `(row, col)`. `lines` is the text the request was made against.
+pub fn items(
+    result: &Value,
+    lines: &[String],
+    (row, col): (usize, usize),
+) -> Vec<CompletionItem> {
+    let items = match result {
+        Value::Array(items) => items,
+        result => match result["items"].as_array() {
+            Some(items) => items,
+            None => return vec![],
+        },
+    };
+    let line = lines.get(row).map_or("", String::as_str);
+    let start = word_start(line, col);
+    items.iter().filter_map(|item| to_item(item, lines, row, start)).collect()
+}
+
+fn to_item(
+    item: &Value,
+    lines: &[String],
+    row: usize,
+    word_start: usize,
+) -> Option<CompletionItem> {
+    let label = item["label"].as_str()?.to_string();
+    let mut completion = CompletionItem::plain(label, Source::Lsp, word_start);
+    completion.kind = item["kind"].as_u64().and_then(kind).map(str::to_string);
+    completion.detail = item["detail"].as_str().map(str::to_string);
+    completion.documentation = match &item["documentation"] {
+        Value::String(text) => Some(text.clone()),
+        docs => docs["value"].as_str().map(str::to_string),
+    };
+    if let Some(filter) = item["filterText"].as_str() {
+        completion.filter_text = filter.to_string();
+    }
+    match item.get("textEdit").and_then(|e| sync::text_edit(e, lines)) {
+        Some(edit) => {
+            // The edit ends where the cursor was when asking; what was
+            // typed since is replaced as well.
+            if edit.start.0 == row {
+                completion.start = edit.start.1;
+            }
+            completion.insert_text = edit.text;
+        }
+        None => {
+            if let Some(text) = item["insertText"].as_str() {
+                completion.insert_text = text.to_string();
+            }
+        }
+    }
+    completion.snippet = item["insertTextFormat"].as_u64() == Some(2);
+    completion.additional_edits = item["additionalTextEdits"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|edit| sync::text_edit(edit, lines))
+        .collect();
+    Some(completion)
+}
+
+/// A short name for an LSP `CompletionItemKind`.
+fn kind(kind: u64) -> Option<&'static str> {
+    let name = match kind {
+        2 => "method",
+        3 => "fn",
+        4 => "new",
+        5 => "field",
+        6 => "var",
+        7 => "class",
+        8 => "trait",
+        9 => "mod",
+        10 => "prop",
+        12 => "value",
+        13 => "enum",
+        14 => "kw",
+        15 => "snip",
+        17 => "file",
+        19 => "dir",
+        20 => "variant",
+        21 => "const",
+        22 => "struct",
+        25 => "type",
+        _ => return None,
+    };
+    Some(name)
+}
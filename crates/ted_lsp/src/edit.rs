@@ -0,0 +1,121 @@
+use crate::sync;
+use crate::uri::uri_to_path;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use ted_editor::text_edit::TextEdit;
+
+/// Changes to any number of files, e.g. from a rename.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspaceEdit {
+    pub files: BTreeMap<PathBuf, Vec<TextEdit>>,
+    /// The buffer version each open file's edits were read against. The
+    /// edits of a buffer edited since would land in the wrong place.
+    pub versions: BTreeMap<PathBuf, u64>,
+    /// The document version the server made a file's edits for, where it
+    /// said.
+    pub(crate) made_for: BTreeMap<PathBuf, i64>,
+    /// How many file creations, renames and deletions were left out; ted
+    /// only edits text.
+    pub skipped: usize,
+}
+
+impl WorkspaceEdit {
+    /// Reads an LSP `WorkspaceEdit`, in either its `changes` or its
+    /// `documentChanges` form. `lines_of` reads the text of a file, to
+    /// convert columns with.
+    pub fn parse(
+        edit: &Value,
+        mut lines_of: impl FnMut(&Path) -> Vec<String>,
+    ) -> Self {
+        let mut parsed = WorkspaceEdit::default();
+        let mut add = |document: &Value, edits: &Value| {
+            let Some(path) = document["uri"].as_str().and_then(uri_to_path)
+            else {
+                return;
+            };
+            if let Some(version) = document["version"].as_i64() {
+                parsed.made_for.insert(path.clone(), version);
+            }
+            let lines = lines_of(&path);
+            let edits = edits
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|edit| sync::text_edit(edit, &lines));
+            parsed.files.entry(path).or_default().extend(edits);
+        };
+        if let Some(changes) = edit["documentChanges"].as_array() {
+            let mut skipped = 0;
+            for change in changes {
+                match change.get("kind") {
+                    Some(_) => skipped += 1,
+                    None => add(&change["textDocument"], &change["edits"]),
+                }
+            }
+            parsed.skipped = skipped;
+        } else if let Some(changes) = edit["changes"].as_object() {
+            for (uri, edits) in changes {
+                add(&json!({ "uri": uri }), edits);
+            }
+        }
+        parsed
+    }
+
+    /// The edits of a formatting result, all to `path`.
+    pub fn of_file(path: PathBuf, edits: Vec<TextEdit>) -> Self {
+        Self { files: BTreeMap::from([(path, edits)]), ..Self::default() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.values().all(Vec::is_empty) && self.skipped == 0
+    }
+
+    /// How many edits there are in all.
+    pub fn len(&self) -> usize {
+        self.files.values().map(Vec::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(line: u32, start: u32, end: u32, text: &str) -> Value {
+        json!({
+            "range": {
+                "start": { "line": line, "character": start },
+                "end": { "line": line, "character": end },
+            },
+            "newText": text,
+        })
+    }
+
+    #[test]
+    fn parses_both_forms() {
+        let lines = |_: &Path| vec!["let 😀x = x;".to_string()];
+        let changes = json!({
+            "changes": { "file:///a.rs": [edit(0, 6, 7, "y"), edit(0, 10, 11, "y")] },
+        });
+        let parsed = WorkspaceEdit::parse(&changes, lines);
+        let edits = &parsed.files[Path::new("/a.rs")];
+        assert_eq!(edits[0].start, (0, 5));
+        assert_eq!(edits[1].end, (0, 10));
+        assert_eq!(parsed.len(), 2);
+
+        let document_changes = json!({
+            "documentChanges": [
+                {
+                    "textDocument": { "uri": "file:///b.rs", "version": 1 },
+                    "edits": [edit(0, 0, 3, "const")],
+                },
+                { "kind": "rename", "oldUri": "file:///b.rs", "newUri": "file:///c.rs" },
+            ],
+        });
+        let parsed = WorkspaceEdit::parse(&document_changes, lines);
+        assert_eq!(parsed.files[Path::new("/b.rs")][0].text, "const");
+        assert_eq!(parsed.skipped, 1);
+        assert_eq!(parsed.made_for[Path::new("/b.rs")], 1);
+        assert!(WorkspaceEdit::parse(&Value::Null, lines).is_empty());
+    }
+}
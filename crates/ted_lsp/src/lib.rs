@@ -0,0 +1,924 @@
+pub mod code_action;
+pub mod completion;
+pub mod edit;
+pub mod navigation;
+pub mod server;
+pub mod sync;
+pub mod transport;
+pub mod uri;
+
+use code_action::CodeAction;
+use edit::WorkspaceEdit;
+use navigation::{Location, Navigation};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use server::{Incoming, LanguageServer};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{self, Path, PathBuf};
+use std::sync::mpsc::Sender;
+use ted_common::event_loop::LoopEvent;
+use ted_common::notify;
+use ted_common::open_files::OpenFiles;
+use ted_editor::completion::CompletionItem;
+use ted_editor::diagnostic::{Diagnostic, Severity};
+use ted_editor::fold::Fold;
+use uri::{path_to_uri, uri_to_path};
+
+/// Indentation formatters are asked for, matching how ted draws tabs.
+const TAB_SIZE: usize = 4;
+
+/// The `[lsp]` section of `config.toml`: a server per language, keyed by
+/// the language id servers know it by.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct LspConfig {
+    pub servers: BTreeMap<String, ServerConfig>,
+}
+
+impl Default for LspConfig {
+    fn default() -> Self {
+        let rust = ServerConfig {
+            command: "rust-analyzer".to_string(),
+            args: vec![],
+            extensions: vec!["rs".to_string()],
+        };
+        Self { servers: BTreeMap::from([("rust".to_string(), rust)]) }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// An empty command turns the server off.
+    pub command: String,
+    pub args: Vec<String>,
+    /// File extensions the server is started for.
+    pub extensions: Vec<String>,
+}
+
+/// What `poll` found out, for the app to act on.
+#[derive(Debug)]
+pub enum LspEvent {
+    /// Diagnostics changed for some file.
+    Diagnostics,
+    /// The answer to `completion` for the cursor on `row` of `path`.
+    Completion { path: PathBuf, row: usize, items: Vec<CompletionItem> },
+    /// The answer to `navigate`, by file and position.
+    Locations { kind: Navigation, locations: Vec<Location> },
+    /// The answer to `hover` at `position` in `path`, as markdown. `None`
+    /// if the server had nothing to say.
+    Hover { path: PathBuf, position: (usize, usize), markdown: Option<String> },
+    /// Edits to apply, like a rename, formatting or what a command did.
+    /// `label` says where they came from. A server that sent them by
+    /// itself waits for `edit_applied` with `request`.
+    Edit { label: String, edit: WorkspaceEdit, request: Option<EditRequest> },
+    /// The answer to `code_actions`.
+    CodeActions(Vec<CodeAction>),
+    /// Where `path` folds, asked for whenever it changes.
+    FoldingRanges { path: PathBuf, folds: Vec<Fold> },
+}
+
+/// A server's `workspace/applyEdit`, waiting to hear how applying it went.
+#[derive(Debug)]
+pub struct EditRequest {
+    language: String,
+    id: Value,
+}
+
+/// A request waiting for its answer, with what it takes to make sense of
+/// it.
+enum Pending {
+    Completion {
+        path: PathBuf,
+        row: usize,
+        col: usize,
+    },
+    Locations(Navigation),
+    Hover {
+        path: PathBuf,
+        position: (usize, usize),
+    },
+    /// A rename from `path` as it was at `version`.
+    Rename {
+        path: PathBuf,
+        version: i64,
+    },
+    /// Formatting of `path` as it was at `version`.
+    Format {
+        path: PathBuf,
+        version: i64,
+    },
+    /// Code actions from the server of this language.
+    CodeActions(String),
+    /// A command run for a code action; what it does comes in through
+    /// `workspace/applyEdit`.
+    Command,
+    /// Folding ranges of `path` as it was at `version`.
+    FoldingRanges {
+        path: PathBuf,
+        version: i64,
+    },
+}
+
+/// A buffer as the server last saw it.
+struct OpenDocument {
+    language: String,
+    version: i64,
+    lines: Vec<String>,
+    /// The buffer's own version of `lines`, to skip buffers that weren't
+    /// edited since.
+    buffer_version: u64,
+}
+
+/// Language servers for the open buffers. Servers start the first time a
+/// buffer of their language is synced, and one that fails to start or dies
+/// stays off for the session.
+pub struct Lsp {
+    config: BTreeMap<String, ServerConfig>,
+    root: PathBuf,
+    waker: Sender<LoopEvent>,
+    servers: BTreeMap<String, LanguageServer>,
+    failed: BTreeSet<String>,
+    documents: BTreeMap<PathBuf, OpenDocument>,
+    diagnostics: BTreeMap<PathBuf, Vec<Diagnostic>>,
+    /// The diagnostics as the servers sent them, in the same order, to
+    /// hand back with code action requests.
+    reported: BTreeMap<PathBuf, Vec<Value>>,
+    /// By server language and request id.
+    pending: BTreeMap<(String, u64), Pending>,
+}
+
+impl Lsp {
+    /// `config` entries replace the built-in ones of the same language.
+    pub fn new(
+        config: &LspConfig,
+        root: &Path,
+        waker: Sender<LoopEvent>,
+    ) -> Self {
+        let mut servers = LspConfig::default().servers;
+        servers.extend(config.servers.clone());
+        servers.retain(|_, server| !server.command.is_empty());
+        Self {
+            config: servers,
+            root: path::absolute(root).unwrap_or_else(|_| root.to_path_buf()),
+            waker,
+            servers: BTreeMap::new(),
+            failed: BTreeSet::new(),
+            documents: BTreeMap::new(),
+            diagnostics: BTreeMap::new(),
+            reported: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// The workspace the servers were started in.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Tells the servers about buffers that were opened, edited or closed
+    /// since the last call.
+    pub fn sync(&mut self, buffers: &dyn OpenFiles) {
+        let mut open = BTreeSet::new();
+        for (path, version) in buffers.versions() {
+            let Ok(path) = path::absolute(path) else {
+                continue;
+            };
+            let Some(language) = self.language_of(&path) else {
+                continue;
+            };
+            open.insert(path.clone());
+            if self
+                .documents
+                .get(&path)
+                .is_some_and(|document| document.buffer_version == version)
+            {
+                continue;
+            }
+            if let Err(e) =
+                self.sync_document(&language, path, buffers, version)
+            {
+                self.fail(&language, &e.to_string());
+            }
+        }
+        let closed: Vec<PathBuf> = self
+            .documents
+            .keys()
+            .filter(|p| !open.contains(*p))
+            .cloned()
+            .collect();
+        for path in closed {
+            let Some(document) = self.documents.remove(&path) else {
+                continue;
+            };
+            self.diagnostics.remove(&path);
+            self.reported.remove(&path);
+            if let Some(server) = self.servers.get_mut(&document.language) {
+                let params =
+                    json!({ "textDocument": { "uri": path_to_uri(&path) } });
+                let _ = server.notify("textDocument/didClose", params);
+            }
+        }
+    }
+
+    fn sync_document(
+        &mut self,
+        language: &str,
+        path: PathBuf,
+        buffers: &dyn OpenFiles,
+        buffer_version: u64,
+    ) -> io::Result<()> {
+        if !self.start(language) {
+            return Ok(());
+        }
+        let Some(lines) = buffers.text(&path) else {
+            return Ok(());
+        };
+        let Some(server) = self.servers.get_mut(language) else {
+            return Ok(());
+        };
+        let uri = path_to_uri(&path);
+        let Some(document) = self.documents.get_mut(&path) else {
+            server.notify(
+                "textDocument/didOpen",
+                json!({ "textDocument": {
+                    "uri": uri,
+                    "languageId": language,
+                    "version": 0,
+                    "text": sync::document_text(&lines),
+                }}),
+            )?;
+            let document = OpenDocument {
+                language: language.to_string(),
+                version: 0,
+                lines,
+                buffer_version,
+            };
+            self.documents.insert(path.clone(), document);
+            self.folding_ranges(language, path);
+            return Ok(());
+        };
+        document.buffer_version = buffer_version;
+        let Some(edit) = sync::diff(&document.lines, &lines) else {
+            return Ok(());
+        };
+        let change = match server.incremental() {
+            true => json!({
+                "range": {
+                    "start": { "line": edit.start, "character": 0 },
+                    "end": { "line": edit.end, "character": 0 },
+                },
+                "text": edit.text,
+            }),
+            false => json!({ "text": sync::document_text(&lines) }),
+        };
+        document.version += 1;
+        document.lines = lines;
+        server.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": document.version },
+                "contentChanges": [change],
+            }),
+        )?;
+        self.folding_ranges(language, path);
+        Ok(())
+    }
+
+    /// Asks where `path` folds, if its server can tell. An answer to an
+    /// earlier request is dropped, being out of date.
+    fn folding_ranges(&mut self, language: &str, path: PathBuf) {
+        let Some(server) = self.servers.get(language) else {
+            return;
+        };
+        let Some(document) = self.documents.get(&path) else {
+            return;
+        };
+        if !server.supports("foldingRangeProvider") {
+            return;
+        }
+        let params = json!({ "textDocument": { "uri": path_to_uri(&path) } });
+        let version = document.version;
+        self.pending.retain(|_, p| {
+            !matches!(p, Pending::FoldingRanges { path: p, .. } if *p == path)
+        });
+        let pending = Pending::FoldingRanges { path, version };
+        self.request(language, "textDocument/foldingRange", params, pending);
+    }
+
+    /// Lets the server know `path` was written, if it has it open.
+    pub fn did_save(&mut self, path: &Path) {
+        let Ok(path) = path::absolute(path) else {
+            return;
+        };
+        let Some(document) = self.documents.get(&path) else {
+            return;
+        };
+        if let Some(server) = self.servers.get_mut(&document.language) {
+            let params =
+                json!({ "textDocument": { "uri": path_to_uri(&path) } });
+            let _ = server.notify("textDocument/didSave", params);
+        }
+    }
+
+    /// Asks for completions at `position` in `path`, after bringing the
+    /// server up to date with `buffers`. The answer comes back from `poll`;
+    /// an earlier request still waiting is dropped. Returns whether the
+    /// request went out.
+    pub fn completion(
+        &mut self,
+        buffers: &dyn OpenFiles,
+        path: &Path,
+        position: (usize, usize),
+    ) -> bool {
+        let Some((language, path, params)) =
+            self.position_params(buffers, path, position)
+        else {
+            return false;
+        };
+        self.pending.retain(|_, p| !matches!(p, Pending::Completion { .. }));
+        let (row, col) = position;
+        let pending = Pending::Completion { path, row, col };
+        self.request(&language, "textDocument/completion", params, pending)
+    }
+
+    /// Asks where the symbol at `position` in `path` is defined,
+    /// implemented or used. The answer comes back from `poll`. Returns
+    /// whether the request went out.
+    pub fn navigate(
+        &mut self,
+        buffers: &dyn OpenFiles,
+        path: &Path,
+        position: (usize, usize),
+        kind: Navigation,
+    ) -> bool {
+        let Some((language, _, mut params)) =
+            self.position_params(buffers, path, position)
+        else {
+            return false;
+        };
+        if kind == Navigation::References {
+            params["context"] = json!({ "includeDeclaration": true });
+        }
+        self.request(&language, kind.method(), params, Pending::Locations(kind))
+    }
+
+    /// Asks for the documentation of the symbol at `position` in `path`.
+    /// The answer comes back from `poll`. Returns whether the request went
+    /// out.
+    pub fn hover(
+        &mut self,
+        buffers: &dyn OpenFiles,
+        path: &Path,
+        position: (usize, usize),
+    ) -> bool {
+        let Some((language, path, params)) =
+            self.position_params(buffers, path, position)
+        else {
+            return false;
+        };
+        let pending = Pending::Hover { path, position };
+        self.request(&language, "textDocument/hover", params, pending)
+    }
+
+    /// Asks to rename the symbol at `position` in `path` to `new_name`
+    /// throughout the workspace. The edits come back from `poll`. Returns
+    /// whether the request went out.
+    pub fn rename(
+        &mut self,
+        buffers: &dyn OpenFiles,
+        path: &Path,
+        position: (usize, usize),
+        new_name: &str,
+    ) -> bool {
+        let Some((language, path, mut params)) =
+            self.position_params(buffers, path, position)
+        else {
+            return false;
+        };
+        params["newName"] = json!(new_name);
+        let version = self.documents[&path].version;
+        let pending = Pending::Rename { path, version };
+        self.request(&language, "textDocument/rename", params, pending)
+    }
+
+    /// Asks for `path` to be formatted, just `range` of it if given. The
+    /// edits come back from `poll`. Returns whether the request went out.
+    pub fn format(
+        &mut self,
+        buffers: &dyn OpenFiles,
+        path: &Path,
+        range: Option<((usize, usize), (usize, usize))>,
+    ) -> bool {
+        self.sync(buffers);
+        let Ok(path) = path::absolute(path) else {
+            return false;
+        };
+        let Some(document) = self.documents.get(&path) else {
+            return false;
+        };
+        let mut params = json!({
+            "textDocument": { "uri": path_to_uri(&path) },
+            "options": { "tabSize": TAB_SIZE, "insertSpaces": true },
+        });
+        let method = match range {
+            Some(range) => {
+                params["range"] = lsp_range(range, &document.lines);
+                "textDocument/rangeFormatting"
+            }
+            None => "textDocument/formatting",
+        };
+        let language = document.language.clone();
+        let pending = Pending::Format { path, version: document.version };
+        self.request(&language, method, params, pending)
+    }
+
+    /// Asks for the fixes and refactorings over `range` in `path`, handing
+    /// the server the diagnostics there. The actions come back from `poll`.
+    /// Returns whether the request went out.
+    pub fn code_actions(
+        &mut self,
+        buffers: &dyn OpenFiles,
+        path: &Path,
+        (start, end): ((usize, usize), (usize, usize)),
+    ) -> bool {
+        self.sync(buffers);
+        let Ok(path) = path::absolute(path) else {
+            return false;
+        };
+        let Some(document) = self.documents.get(&path) else {
+            return false;
+        };
+        let diagnostics: Vec<&Value> = self
+            .diagnostics
+            .get(&path)
+            .zip(self.reported.get(&path))
+            .into_iter()
+            .flat_map(|(diagnostics, reported)| {
+                diagnostics.iter().zip(reported)
+            })
+            .filter(|(d, _)| d.start <= end && start <= d.end)
+            .map(|(_, reported)| reported)
+            .collect();
+        let params = json!({
+            "textDocument": { "uri": path_to_uri(&path) },
+            "range": lsp_range((start, end), &document.lines),
+            "context": { "diagnostics": diagnostics },
+        });
+        let language = document.language.clone();
+        let pending = Pending::CodeActions(language.clone());
+        self.request(&language, "textDocument/codeAction", params, pending)
+    }
+
+    /// Runs `action`'s command, if it has one, and returns its edit for the
+    /// app to apply. A command may send edits of its own later, which come
+    /// from `poll`.
+    pub fn run_code_action(
+        &mut self,
+        action: &CodeAction,
+    ) -> Option<WorkspaceEdit> {
+        if let Some(command) = &action.command {
+            let params = json!({
+                "command": command["command"],
+                "arguments": command.get("arguments").cloned().unwrap_or(json!([])),
+            });
+            self.request(
+                &action.language,
+                "workspace/executeCommand",
+                params,
+                Pending::Command,
+            );
+        }
+        action.edit.clone()
+    }
+
+    /// Tells the server that sent `request` how applying its edit went,
+    /// once `buffers` have it: they are synced first, so the server hears
+    /// of the new text before it hears that the edit is in.
+    pub fn edit_applied(
+        &mut self,
+        buffers: &dyn OpenFiles,
+        request: EditRequest,
+        result: Result<(), String>,
+    ) {
+        self.sync(buffers);
+        self.answer_edit(request, result);
+    }
+
+    fn answer_edit(
+        &mut self,
+        request: EditRequest,
+        result: Result<(), String>,
+    ) {
+        let answer = match result {
+            Ok(()) => json!({ "applied": true }),
+            Err(reason) => json!({ "applied": false, "failureReason": reason }),
+        };
+        let EditRequest { language, id } = request;
+        if let Some(server) = self.servers.get_mut(&language)
+            && let Err(e) = server.respond(id, answer)
+        {
+            self.fail(&language, &e.to_string());
+        }
+    }
+
+    /// The language, absolute path and `TextDocumentPositionParams` for a
+    /// request about `position` in `path`.
+    fn position_params(
+        &mut self,
+        buffers: &dyn OpenFiles,
+        path: &Path,
+        position: (usize, usize),
+    ) -> Option<(String, PathBuf, Value)> {
+        self.sync(buffers);
+        let path = path::absolute(path).ok()?;
+        let document = self.documents.get(&path)?;
+        let params = json!({
+            "textDocument": { "uri": path_to_uri(&path) },
+            "position": sync::lsp_position(position, &document.lines),
+        });
+        Some((document.language.clone(), path, params))
+    }
+
+    fn request(
+        &mut self,
+        language: &str,
+        method: &'static str,
+        params: Value,
+        pending: Pending,
+    ) -> bool {
+        let Some(server) = self.servers.get_mut(language) else {
+            return false;
+        };
+        match server.request(method, params) {
+            Ok(id) => {
+                self.pending.insert((language.to_string(), id), pending);
+                true
+            }
+            Err(e) => {
+                self.fail(language, &e.to_string());
+                false
+            }
+        }
+    }
+
+    /// Handles whatever the servers sent.
+    pub fn poll(&mut self) -> Vec<LspEvent> {
+        let mut changed = false;
+        let mut events = vec![];
+        let mut incoming = vec![];
+        let mut dead = vec![];
+        for (language, server) in &mut self.servers {
+            // Checked first so nothing it sent before exiting is missed.
+            let exited = server.has_exited();
+            match server.poll() {
+                Ok(messages) => incoming.extend(
+                    messages.into_iter().map(|m| (language.clone(), m)),
+                ),
+                Err(e) => dead.push((language.clone(), e.to_string())),
+            }
+            if exited {
+                dead.push((language.clone(), "exited".to_string()));
+            }
+        }
+        for (language, message) in incoming {
+            match message {
+                Incoming::Request { id, method, params }
+                    if method == "workspace/applyEdit" =>
+                {
+                    let mut edit = WorkspaceEdit::parse(&params["edit"], |p| {
+                        self.lines_of(p)
+                    });
+                    let request = EditRequest { language, id };
+                    if let Err(reason) = self.stamp(&mut edit) {
+                        self.answer_edit(request, Err(reason));
+                        continue;
+                    }
+                    let label = params["label"].as_str().unwrap_or("edit");
+                    events.push(LspEvent::Edit {
+                        label: label.to_string(),
+                        edit,
+                        request: Some(request),
+                    });
+                }
+                Incoming::Request { id, .. } => {
+                    if let Some(server) = self.servers.get_mut(&language) {
+                        let _ = server.respond(id, Value::Null);
+                    }
+                }
+                Incoming::Notification { method, params } => {
+                    changed |= self.on_notification(&method, &params);
+                }
+                Incoming::Response { id, method, result } => {
+                    let pending = self.pending.remove(&(language.clone(), id));
+                    match (result, pending) {
+                        (Ok(result), Some(pending)) => {
+                            events.extend(self.on_response(pending, &result))
+                        }
+                        // Documents opened before the server was ready
+                        // could not ask for their folds yet.
+                        (Ok(_), None) if method == "initialize" => {
+                            let paths: Vec<PathBuf> = self
+                                .documents
+                                .iter()
+                                .filter(|(_, d)| d.language == language)
+                                .map(|(path, _)| path.clone())
+                                .collect();
+                            for path in paths {
+                                self.folding_ranges(&language, path);
+                            }
+                        }
+                        (Ok(_), None) => {}
+                        (Err(e), _) => notify::warn(format!(
+                            "language server: {method}: {e}"
+                        )),
+                    }
+                }
+            }
+        }
+        for (language, reason) in dead {
+            changed |= self.fail(&language, &reason);
+        }
+        if changed {
+            events.push(LspEvent::Diagnostics);
+        }
+        events
+    }
+
+    fn on_response(
+        &mut self,
+        pending: Pending,
+        result: &Value,
+    ) -> Option<LspEvent> {
+        match pending {
+            Pending::Completion { path, row, col } => {
+                let lines = &self.documents.get(&path)?.lines;
+                let items = completion::items(result, lines, (row, col));
+                Some(LspEvent::Completion { path, row, items })
+            }
+            Pending::Locations(kind) => {
+                let locations =
+                    navigation::locations(result, |path| self.lines_of(path));
+                Some(LspEvent::Locations { kind, locations })
+            }
+            Pending::Hover { path, position } => {
+                let markdown = navigation::hover(result);
+                Some(LspEvent::Hover { path, position, markdown })
+            }
+            Pending::Rename { path, version } => {
+                let mut edit =
+                    WorkspaceEdit::parse(result, |p| self.lines_of(p));
+                self.still_at("rename", &path, version)?;
+                if let Err(reason) = self.stamp(&mut edit) {
+                    notify::warn(format!("rename: {reason}"));
+                    return None;
+                }
+                Some(LspEvent::Edit {
+                    label: "rename".to_string(),
+                    edit,
+                    request: None,
+                })
+            }
+            Pending::Format { path, version } => {
+                self.still_at("format", &path, version)?;
+                let lines = self.lines_of(&path);
+                let edits = result
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|edit| sync::text_edit(edit, &lines))
+                    .collect();
+                let mut edit = WorkspaceEdit::of_file(path, edits);
+                self.stamp(&mut edit).ok()?;
+                Some(LspEvent::Edit {
+                    label: "format".to_string(),
+                    edit,
+                    request: None,
+                })
+            }
+            Pending::CodeActions(language) => {
+                let mut actions =
+                    code_action::actions(result, &language, |p| {
+                        self.lines_of(p)
+                    });
+                for action in &mut actions {
+                    if let Some(edit) = &mut action.edit
+                        && let Err(reason) = self.stamp(edit)
+                    {
+                        action.disabled.get_or_insert(reason);
+                    }
+                }
+                Some(LspEvent::CodeActions(actions))
+            }
+            Pending::Command => None,
+            Pending::FoldingRanges { path, version } => {
+                if self.documents.get(&path)?.version != version {
+                    return None;
+                }
+                let folds = result
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|range| {
+                        let start = range["startLine"].as_u64()? as usize;
+                        let end = range["endLine"].as_u64()? as usize;
+                        Some(Fold { start, end })
+                    })
+                    .collect();
+                Some(LspEvent::FoldingRanges { path, folds })
+            }
+        }
+    }
+
+    /// `Some` if `path` is still at the `version` a request for `what` was
+    /// sent at; its answer no longer fits the text otherwise.
+    fn still_at(&self, what: &str, path: &Path, version: i64) -> Option<()> {
+        if self.documents.get(path)?.version == version {
+            return Some(());
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        notify::warn(format!("{what}: {name} changed meanwhile, try again"));
+        None
+    }
+
+    /// Notes in `edit` the buffer version each open file's edits were read
+    /// against. Fails if the server made them for text it has since been
+    /// sent changes to.
+    fn stamp(&self, edit: &mut WorkspaceEdit) -> Result<(), String> {
+        for path in edit.files.keys() {
+            let Some(document) = self.documents.get(path) else {
+                continue;
+            };
+            if edit.made_for.get(path).is_some_and(|v| *v != document.version) {
+                return Err(format!("{} changed meanwhile", path.display()));
+            }
+            edit.versions.insert(path.clone(), document.buffer_version);
+        }
+        Ok(())
+    }
+
+    /// The text of `path` as the servers know it: the synced buffer if it
+    /// is open, the file on disk otherwise.
+    fn lines_of(&self, path: &Path) -> Vec<String> {
+        match self.documents.get(path) {
+            Some(document) => document.lines.clone(),
+            None => fs::read_to_string(path)
+                .map(|text| text.lines().map(str::to_string).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn on_notification(&mut self, method: &str, params: &Value) -> bool {
+        match method {
+            "textDocument/publishDiagnostics" => {
+                let Some(path) = params["uri"].as_str().and_then(uri_to_path)
+                else {
+                    return false;
+                };
+                let lines =
+                    self.documents.get(&path).map(|d| d.lines.as_slice());
+                let reported = params["diagnostics"]
+                    .as_array()
+                    .cloned()
+                    .unwrap_or_default();
+                let diagnostics: Vec<Diagnostic> = reported
+                    .iter()
+                    .map(|d| to_diagnostic(d, lines.unwrap_or_default()))
+                    .collect();
+                match diagnostics.is_empty() {
+                    true => {
+                        self.diagnostics.remove(&path);
+                        self.reported.remove(&path);
+                    }
+                    false => {
+                        self.diagnostics.insert(path.clone(), diagnostics);
+                        self.reported.insert(path, reported);
+                    }
+                }
+                true
+            }
+            "window/showMessage" => {
+                let message = params["message"].as_str().unwrap_or_default();
+                match params["type"].as_u64() {
+                    Some(1) => notify::error(message),
+                    Some(2) => notify::warn(message),
+                    _ => notify::info(message),
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    pub fn diagnostics(&self, path: &Path) -> &[Diagnostic] {
+        path::absolute(path)
+            .ok()
+            .and_then(|path| self.diagnostics.get(&path))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Every diagnostic the servers reported, by file.
+    pub fn all_diagnostics(
+        &self,
+    ) -> impl Iterator<Item = (&Path, &Diagnostic)> {
+        self.diagnostics
+            .iter()
+            .flat_map(|(path, list)| list.iter().map(|d| (path.as_path(), d)))
+    }
+
+    /// Stops every server, giving each a moment to exit cleanly.
+    pub fn shutdown(&mut self) {
+        for server in std::mem::take(&mut self.servers).into_values() {
+            server.shutdown();
+        }
+    }
+
+    fn language_of(&self, path: &Path) -> Option<String> {
+        let extension = path.extension()?.to_str()?;
+        self.config
+            .iter()
+            .find(|(_, server)| {
+                server.extensions.iter().any(|e| e == extension)
+            })
+            .map(|(language, _)| language.clone())
+    }
+
+    /// Starts the server for `language` unless it runs already. Returns
+    /// whether it runs now.
+    fn start(&mut self, language: &str) -> bool {
+        if self.servers.contains_key(language) {
+            return true;
+        }
+        let Some(config) = self.config.get(language) else {
+            return false;
+        };
+        if self.failed.contains(language) {
+            return false;
+        }
+        match LanguageServer::start(config, &self.root, self.waker.clone()) {
+            Ok(server) => {
+                self.servers.insert(language.to_string(), server);
+                true
+            }
+            Err(e) => {
+                self.fail(language, &e.to_string());
+                false
+            }
+        }
+    }
+
+    /// Gives up on `language`'s server. Returns whether that dropped any
+    /// diagnostics.
+    fn fail(&mut self, language: &str, reason: &str) -> bool {
+        let command =
+            self.config.get(language).map_or("", |c| c.command.as_str());
+        notify::warn(format!("language server {command}: {reason}"));
+        self.failed.insert(language.to_string());
+        self.pending.retain(|(l, _), _| l != language);
+        if let Some(server) = self.servers.remove(language) {
+            server.shutdown();
+        }
+        let paths: Vec<PathBuf> = self
+            .documents
+            .iter()
+            .filter(|(_, d)| d.language == language)
+            .map(|(path, _)| path.clone())
+            .collect();
+        let mut changed = false;
+        for path in paths {
+            self.documents.remove(&path);
+            self.reported.remove(&path);
+            changed |= self.diagnostics.remove(&path).is_some();
+        }
+        changed
+    }
+}
+
+fn lsp_range(
+    (start, end): ((usize, usize), (usize, usize)),
+    lines: &[String],
+) -> Value {
+    json!({
+        "start": sync::lsp_position(start, lines),
+        "end": sync::lsp_position(end, lines),
+    })
+}
+
+fn to_diagnostic(diagnostic: &Value, lines: &[String]) -> Diagnostic {
+    let position = |key: &str| sync::position(&diagnostic["range"][key], lines);
+    let severity = match diagnostic["severity"].as_u64() {
+        Some(2) => Severity::Warning,
+        Some(3) => Severity::Info,
+        Some(4) => Severity::Hint,
+        _ => Severity::Error,
+    };
+    Diagnostic {
+        start: position("start"),
+        end: position("end"),
+        severity,
+        message: diagnostic["message"].as_str().unwrap_or_default().to_string(),
+        source: diagnostic["source"].as_str().map(str::to_string),
+    }
+}
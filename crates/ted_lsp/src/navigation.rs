@@ -0,0 +1,148 @@
+use crate::sync;
+use crate::uri::uri_to_path;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// What `gd`, `gi` and `gr` look for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Navigation {
+    Definition,
+    Implementation,
+    References,
+}
+
+impl Navigation {
+    pub fn method(self) -> &'static str {
+        match self {
+            Navigation::Definition => "textDocument/definition",
+            Navigation::Implementation => "textDocument/implementation",
+            Navigation::References => "textDocument/references",
+        }
+    }
+
+    /// What the results are called, e.g. in the picker's title.
+    pub fn label(self) -> &'static str {
+        match self {
+            Navigation::Definition => "definitions",
+            Navigation::Implementation => "implementations",
+            Navigation::References => "references",
+        }
+    }
+}
+
+/// A place a server pointed at.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub path: PathBuf,
+    /// `(row, column)` in chars.
+    pub start: (usize, usize),
+    /// The text of the row, to preview the location with.
+    pub line: String,
+}
+
+/// The locations of a definition, implementation or references result:
+/// one `Location`, a list of them or of `LocationLink`s, or null.
+/// `lines_of` reads the text of a file, to convert columns with.
+pub fn locations(
+    result: &Value,
+    mut lines_of: impl FnMut(&Path) -> Vec<String>,
+) -> Vec<Location> {
+    let list = match result {
+        Value::Array(list) => list.as_slice(),
+        Value::Null => &[],
+        location => std::slice::from_ref(location),
+    };
+    let mut files: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+    let mut locations: Vec<Location> = list
+        .iter()
+        .filter_map(|location| {
+            let uri = location.get("uri").or(location.get("targetUri"))?;
+            let range = location
+                .get("range")
+                .or(location.get("targetSelectionRange"))?;
+            let path = uri_to_path(uri.as_str()?)?;
+            let lines =
+                files.entry(path.clone()).or_insert_with(|| lines_of(&path));
+            let start = sync::position(&range["start"], lines);
+            let line = lines.get(start.0).cloned().unwrap_or_default();
+            Some(Location { path, start, line })
+        })
+        .collect();
+    locations.sort();
+    locations.dedup();
+    locations
+}
+
+/// The contents of a hover result as markdown: `MarkupContent`, a
+/// `MarkedString` or a list of them. `None` if there is nothing to show.
+pub fn hover(result: &Value) -> Option<String> {
+    let parts: Vec<String> = match result.get("contents")? {
+        Value::Array(list) => list.iter().filter_map(marked_string).collect(),
+        contents => marked_string(contents).into_iter().collect(),
+    };
+    let markdown = parts.join("\n\n");
+    (!markdown.trim().is_empty()).then_some(markdown)
+}
+
+/// A `MarkedString` or `MarkupContent` as markdown. Code in a language
+/// becomes a fenced block.
+fn marked_string(value: &Value) -> Option<String> {
+    if let Value::String(text) = value {
+        return Some(text.clone());
+    }
+    let text = value["value"].as_str()?;
+    match value["language"].as_str() {
+        Some(language) => Some(format!("```{language}\n{text}\n```")),
+        None => Some(text.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_locations_and_links() {
+        let range = json!({
+            "start": { "line": 1, "character": 3 },
+            "end": { "line": 1, "character": 6 },
+        });
+        let result = json!([
+            { "uri": "file:///src/b.rs", "range": range },
+            {
+                "targetUri": "file:///src/a.rs",
+                "targetRange": range,
+                "targetSelectionRange": range,
+            },
+            { "uri": "file:///src/b.rs", "range": range },
+        ]);
+        let mut reads = 0;
+        let locations = locations(&result, |_| {
+            reads += 1;
+            vec!["use x;".to_string(), "fn 😀ab() {}".to_string()]
+        });
+        assert_eq!(reads, 2);
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[0].path, Path::new("/src/a.rs"));
+        assert_eq!(locations[0].start, (1, 3));
+        assert_eq!(locations[0].line, "fn 😀ab() {}");
+        assert!(super::locations(&Value::Null, |_| vec![]).is_empty());
+    }
+
+    #[test]
+    fn reads_hover_contents() {
+        let markup =
+            json!({ "contents": { "kind": "markdown", "value": "**x**" } });
+        assert_eq!(hover(&markup).as_deref(), Some("**x**"));
+        let marked = json!({
+            "contents": [{ "language": "rust", "value": "fn x()" }, "Docs."],
+        });
+        assert_eq!(
+            hover(&marked).as_deref(),
+            Some("```rust\nfn x()\n```\n\nDocs.")
+        );
+        assert_eq!(hover(&json!({ "contents": "" })), None);
+    }
+}
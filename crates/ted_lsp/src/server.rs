@@ -0,0 +1,312 @@
+use crate::ServerConfig;
+use crate::transport::{read_message, write_message};
+use crate::uri::path_to_uri;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use ted_common::event_loop::LoopEvent;
+
+/// How long a server gets to answer `shutdown` and exit before it is killed.
+const SHUTDOWN_WAIT: Duration = Duration::from_millis(300);
+
+/// Something a server sent that the client has to act on.
+#[derive(Debug)]
+pub enum Incoming {
+    /// The answer to `request`, with the method that was asked.
+    Response {
+        id: u64,
+        method: &'static str,
+        result: Result<Value, String>,
+    },
+    Notification {
+        method: String,
+        params: Value,
+    },
+    /// A request the client answers later, through `respond`.
+    Request {
+        id: Value,
+        method: String,
+        params: Value,
+    },
+}
+
+/// A language server process spoken to over stdio. Its output is read on a
+/// thread of its own, which wakes the main loop with `LoopEvent::Wake`
+/// whenever there is something to `poll`.
+pub struct LanguageServer {
+    child: Child,
+    stdin: ChildStdin,
+    incoming: Receiver<Value>,
+    next_id: u64,
+    pending: HashMap<u64, &'static str>,
+    /// Messages held back until the server answered `initialize`; `None`
+    /// once it has.
+    queued: Option<Vec<Value>>,
+    incremental: bool,
+    /// What the server said it can do when it was initialized.
+    capabilities: Value,
+}
+
+impl LanguageServer {
+    pub fn start(
+        config: &ServerConfig,
+        root: &Path,
+        waker: Sender<LoopEvent>,
+    ) -> io::Result<Self> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) =
+            (child.stdin.take(), child.stdout.take())
+        else {
+            unreachable!("stdio is piped");
+        };
+        let (sender, incoming) = mpsc::channel();
+        thread::Builder::new()
+            .name(format!("ted-lsp-{}", config.command))
+            .spawn(move || {
+                let mut stdout = BufReader::new(stdout);
+                while let Ok(Some(message)) = read_message(&mut stdout) {
+                    if sender.send(message).is_err() {
+                        return;
+                    }
+                    let _ = waker.send(LoopEvent::Wake);
+                }
+            })?;
+        let mut server = Self {
+            child,
+            stdin,
+            incoming,
+            next_id: 0,
+            pending: HashMap::new(),
+            queued: None,
+            incremental: false,
+            capabilities: Value::Null,
+        };
+        let uri = path_to_uri(root);
+        server.request(
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootUri": uri,
+                "workspaceFolders": [{ "uri": uri, "name": "ted" }],
+                "capabilities": {
+                    "general": { "positionEncodings": ["utf-16"] },
+                    "textDocument": {
+                        "synchronization": { "didSave": true },
+                        "publishDiagnostics": {},
+                        "completion": {
+                            "completionItem": {
+                                "snippetSupport": true,
+                                "documentationFormat": ["markdown", "plaintext"],
+                            },
+                        },
+                        "hover": {
+                            "contentFormat": ["markdown", "plaintext"],
+                        },
+                        "definition": { "linkSupport": true },
+                        "implementation": { "linkSupport": true },
+                        "references": {},
+                        "rename": {},
+                        "formatting": {},
+                        "rangeFormatting": {},
+                        "foldingRange": { "lineFoldingOnly": true },
+                        "codeAction": {
+                            "codeActionLiteralSupport": {
+                                "codeActionKind": {
+                                    "valueSet": [
+                                        "quickfix",
+                                        "refactor",
+                                        "refactor.extract",
+                                        "refactor.inline",
+                                        "refactor.rewrite",
+                                        "source",
+                                        "source.organizeImports",
+                                    ],
+                                },
+                            },
+                            "isPreferredSupport": true,
+                            "disabledSupport": true,
+                        },
+                    },
+                    "workspace": {
+                        "configuration": true,
+                        "applyEdit": true,
+                        "workspaceEdit": { "documentChanges": true },
+                    },
+                },
+            }),
+        )?;
+        server.queued = Some(vec![]);
+        Ok(server)
+    }
+
+    /// Whether the server takes `didChange` as edits rather than the whole
+    /// text every time.
+    pub fn incremental(&self) -> bool {
+        self.incremental
+    }
+
+    /// Whether the server offers `provider`, like `foldingRangeProvider`.
+    /// Nothing is known to be offered before it answered `initialize`.
+    pub fn supports(&self, provider: &str) -> bool {
+        !matches!(self.capabilities[provider], Value::Null | Value::Bool(false))
+    }
+
+    pub fn request(
+        &mut self,
+        method: &'static str,
+        params: Value,
+    ) -> io::Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, method);
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        self.send(message)?;
+        Ok(id)
+    }
+
+    pub fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        self.send(
+            json!({ "jsonrpc": "2.0", "method": method, "params": params }),
+        )
+    }
+
+    /// Answers the server's request `id`, which `poll` handed on.
+    pub fn respond(&mut self, id: Value, result: Value) -> io::Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+
+    fn send(&mut self, message: Value) -> io::Result<()> {
+        match &mut self.queued {
+            Some(queued) => {
+                queued.push(message);
+                Ok(())
+            }
+            None => write_message(&mut self.stdin, &message),
+        }
+    }
+
+    /// Whether the process is gone, e.g. because it crashed.
+    pub fn has_exited(&mut self) -> bool {
+        !matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Everything that arrived since the last call. Requests from the server
+    /// are answered here, except `workspace/applyEdit`: that one is passed
+    /// on to be answered with `respond` once the edit was applied.
+    pub fn poll(&mut self) -> io::Result<Vec<Incoming>> {
+        let mut incoming = vec![];
+        while let Ok(message) = self.incoming.try_recv() {
+            let method = message.get("method").and_then(Value::as_str);
+            let id = message.get("id");
+            match (method, id) {
+                (Some("workspace/applyEdit"), Some(id)) => {
+                    incoming.push(Incoming::Request {
+                        id: id.clone(),
+                        method: "workspace/applyEdit".to_string(),
+                        params: message["params"].clone(),
+                    });
+                }
+                (Some(method), Some(id)) => {
+                    let result = match method {
+                        "workspace/configuration" => {
+                            let items = message["params"]["items"]
+                                .as_array()
+                                .map_or(0, Vec::len);
+                            Value::Array(vec![Value::Null; items])
+                        }
+                        _ => Value::Null,
+                    };
+                    let reply =
+                        json!({ "jsonrpc": "2.0", "id": id, "result": result });
+                    write_message(&mut self.stdin, &reply)?;
+                }
+                (Some(method), None) => incoming.push(Incoming::Notification {
+                    method: method.to_string(),
+                    params: message.get("params").cloned().unwrap_or_default(),
+                }),
+                (None, Some(id)) => {
+                    let Some(id) = id.as_u64() else {
+                        continue;
+                    };
+                    let Some(method) = self.pending.remove(&id) else {
+                        continue;
+                    };
+                    let result = match message.get("error") {
+                        Some(error) => Err(error["message"]
+                            .as_str()
+                            .unwrap_or("request failed")
+                            .to_string()),
+                        None => Ok(message
+                            .get("result")
+                            .cloned()
+                            .unwrap_or_default()),
+                    };
+                    if method == "initialize"
+                        && let Ok(result) = &result
+                    {
+                        self.initialized(result)?;
+                    }
+                    incoming.push(Incoming::Response { id, method, result });
+                }
+                (None, None) => {}
+            }
+        }
+        Ok(incoming)
+    }
+
+    fn initialized(&mut self, result: &Value) -> io::Result<()> {
+        let sync = &result["capabilities"]["textDocumentSync"];
+        let kind = sync.get("change").unwrap_or(sync).as_u64();
+        self.incremental = kind == Some(2);
+        self.capabilities = result["capabilities"].clone();
+        let queued = self.queued.take().unwrap_or_default();
+        self.notify("initialized", json!({}))?;
+        for message in queued {
+            write_message(&mut self.stdin, &message)?;
+        }
+        Ok(())
+    }
+
+    /// Asks the server to exit, and kills it if it takes too long.
+    pub fn shutdown(mut self) {
+        let deadline = Instant::now() + SHUTDOWN_WAIT;
+        if self.queued.is_none()
+            && let Ok(id) = self.request("shutdown", Value::Null)
+        {
+            let id = json!(id);
+            while let Ok(message) = self.incoming.recv_timeout(
+                deadline.saturating_duration_since(Instant::now()),
+            ) {
+                if message.get("method").is_none() && message["id"] == id {
+                    let _ = self.notify("exit", Value::Null);
+                    break;
+                }
+            }
+        }
+        drop(self.stdin);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
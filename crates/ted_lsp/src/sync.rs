@@ -0,0 +1,108 @@
+use serde_json::{Value, json};
+use ted_editor::text_edit::TextEdit;
+
+/// What a buffer looks like to a language server: its lines, each ending in
+/// a newline, the way ted writes the file.
+pub fn document_text(lines: &[String]) -> String {
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+/// An edit replacing whole lines, `start..end` of the old text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// The single edit that turns `old` into `new`: everything between the
+/// lines they share at the start and at the end. `None` if nothing changed.
+pub fn diff(old: &[String], new: &[String]) -> Option<LineEdit> {
+    if old == new {
+        return None;
+    }
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    Some(LineEdit {
+        start: prefix,
+        end: old.len() - suffix,
+        text: document_text(&new[prefix..new.len() - suffix]),
+    })
+}
+
+/// Servers count columns in UTF-16 code units, ted in chars.
+pub fn utf16_to_char(line: &str, col: usize) -> usize {
+    let mut units = 0;
+    for (idx, ch) in line.chars().enumerate() {
+        if units >= col {
+            return idx;
+        }
+        units += ch.len_utf16();
+    }
+    line.chars().count()
+}
+
+pub fn char_to_utf16(line: &str, col: usize) -> usize {
+    line.chars().take(col).map(char::len_utf16).sum()
+}
+
+/// An LSP position as `(row, column)` in chars, given the text it refers
+/// to. Rows past the end keep their column as is.
+pub fn position(position: &Value, lines: &[String]) -> (usize, usize) {
+    let row = position["line"].as_u64().unwrap_or(0) as usize;
+    let col = position["character"].as_u64().unwrap_or(0) as usize;
+    match lines.get(row) {
+        Some(line) => (row, utf16_to_char(line, col)),
+        None => (row, col),
+    }
+}
+
+pub fn lsp_position((row, col): (usize, usize), lines: &[String]) -> Value {
+    let line = lines.get(row).map_or("", String::as_str);
+    json!({ "line": row, "character": char_to_utf16(line, col) })
+}
+
+/// An LSP `TextEdit`, or the `replace` range of an `InsertReplaceEdit`.
+pub fn text_edit(edit: &Value, lines: &[String]) -> Option<TextEdit> {
+    let range = edit.get("range").or_else(|| edit.get("replace"))?;
+    Some(TextEdit {
+        start: position(&range["start"], lines),
+        end: position(&range["end"], lines),
+        text: edit["newText"].as_str()?.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn diffs_the_changed_lines() {
+        let old = lines("a\nb\nc");
+        assert_eq!(diff(&old, &old), None);
+        let edit = diff(&old, &lines("a\nx\ny\nc")).unwrap();
+        assert_eq!(edit, LineEdit { start: 1, end: 2, text: "x\ny\n".into() });
+        let edit = diff(&old, &lines("a\nb\nc\nd")).unwrap();
+        assert_eq!(edit, LineEdit { start: 3, end: 3, text: "d\n".into() });
+        // A repeated line is only counted once.
+        let edit = diff(&lines("a\na"), &lines("a")).unwrap();
+        assert_eq!(edit, LineEdit { start: 1, end: 2, text: String::new() });
+    }
+
+    #[test]
+    fn converts_utf16_columns() {
+        let line = "a😀b";
+        assert_eq!(char_to_utf16(line, 2), 3);
+        assert_eq!(utf16_to_char(line, 3), 2);
+        assert_eq!(utf16_to_char(line, 99), 3);
+    }
+}
@@ -0,0 +1,62 @@
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+
+/// Reads one `Content-Length` framed JSON-RPC message. `None` means the other
+/// side closed the stream.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let Some(length) = length else {
+        return Err(invalid("message without a Content-Length"));
+    };
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(io::Error::from)
+}
+
+pub fn write_message(
+    writer: &mut impl Write,
+    message: &Value,
+) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn frames_messages() {
+        let mut stream = vec![];
+        write_message(&mut stream, &json!({ "id": 1, "text": "héllo" }))
+            .unwrap();
+        write_message(&mut stream, &json!({ "id": 2 })).unwrap();
+        let mut reader = stream.as_slice();
+        let first = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(first["text"], "héllo");
+        let second = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(second["id"], 2);
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+use ted_common::event_loop::LoopEvent;
+use ted_editor::buffer_list::BufferList;
+use ted_editor::diagnostic::{Diagnostic, Severity};
+use ted_editor::fold::Fold;
+use ted_lsp::navigation::Navigation;
+use ted_lsp::{Lsp, LspConfig, LspEvent, ServerConfig};
+
+/// Polls until the diagnostics for `path` satisfy `done`.
+fn wait_for(
+    lsp: &mut Lsp,
+    wakes: &Receiver<LoopEvent>,
+    path: &Path,
+    done: impl Fn(&[Diagnostic]) -> bool,
+) {
+    while !done(lsp.diagnostics(path)) {
+        wakes
+            .recv_timeout(Duration::from_secs(5))
+            .expect("the mock server went quiet");
+        lsp.poll();
+    }
+}
+
+/// Polls until `find` picks something out of what the server sent.
+fn answer<T>(
+    lsp: &mut Lsp,
+    wakes: &Receiver<LoopEvent>,
+    find: impl Fn(LspEvent) -> Option<T>,
+) -> T {
+    loop {
+        wakes
+            .recv_timeout(Duration::from_secs(5))
+            .expect("the mock server went quiet");
+        if let Some(found) = lsp.poll().into_iter().find_map(&find) {
+            return found;
+        }
+    }
+}
+
+#[test]
+fn talks_to_a_mock_server() {
+    let root =
+        std::env::temp_dir().join(format!("ted-lsp-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    let path = root.join("main.rs");
+    fs::write(&path, "fn main() {}\n// TODO: more\n").unwrap();
+
+    let mock = ServerConfig {
+        command: env!("CARGO_BIN_EXE_mock_lsp").to_string(),
+        args: vec![],
+        extensions: vec!["rs".to_string()],
+    };
+    let config =
+        LspConfig { servers: BTreeMap::from([("rust".to_string(), mock)]) };
+    let (waker, wakes) = mpsc::channel();
+    let mut lsp = Lsp::new(&config, &root, waker);
+    let mut buffers = BufferList::new();
+    buffers.open(&path).unwrap();
+
+    lsp.sync(&buffers);
+    wait_for(&mut lsp, &wakes, &path, |d| !d.is_empty());
+    let todo = &lsp.diagnostics(&path)[0];
+    assert_eq!(todo.severity, Severity::Warning);
+    assert_eq!((todo.start, todo.end), ((1, 3), (1, 7)));
+
+    // Goes over as an incremental edit, which the mock applies to its copy.
+    let lines = ["// FIXME".to_string(), "fn main() {}".to_string()];
+    buffers.get_mut(0).unwrap().set_lines(&lines);
+    lsp.sync(&buffers);
+    wait_for(&mut lsp, &wakes, &path, |d| d.len() == 1 && d[0].start.0 == 0);
+    assert_eq!(lsp.diagnostics(&path)[0].severity, Severity::Error);
+
+    assert!(lsp.completion(&buffers, &path, (1, 5)));
+    let items = answer(&mut lsp, &wakes, |event| match event {
+        LspEvent::Completion { items, .. } => Some(items),
+        _ => None,
+    });
+    assert_eq!(items[0].label, "mock_fn");
+    assert_eq!(items[0].start, 3);
+    assert!(items[0].snippet);
+    assert_eq!(items[0].additional_edits[0].start, (0, 0));
+
+    let main = (1, 4);
+    assert!(lsp.navigate(&buffers, &path, main, Navigation::Definition));
+    let locations = answer(&mut lsp, &wakes, |event| match event {
+        LspEvent::Locations { kind: Navigation::Definition, locations } => {
+            Some(locations)
+        }
+        _ => None,
+    });
+    assert_eq!(locations.len(), 1);
+    assert_eq!(
+        (locations[0].path.as_path(), locations[0].start),
+        (&*path, (1, 3))
+    );
+    assert_eq!(locations[0].line, "fn main() {}");
+
+    assert!(lsp.hover(&buffers, &path, main));
+    let (position, markdown) = answer(&mut lsp, &wakes, |event| match event {
+        LspEvent::Hover { position, markdown, .. } => {
+            Some((position, markdown))
+        }
+        _ => None,
+    });
+    assert_eq!(position, main);
+    assert!(markdown.unwrap().contains("**main**"));
+
+    let fixme = ((0, 4), (0, 4));
+    assert!(lsp.code_actions(&buffers, &path, fixme));
+    let actions = answer(&mut lsp, &wakes, |event| match event {
+        LspEvent::CodeActions(actions) => Some(actions),
+        _ => None,
+    });
+    assert_eq!(actions.len(), 2);
+    assert_eq!(actions[0].title, "Remove FIXME");
+    let remove = lsp.run_code_action(&actions[0]).unwrap();
+    assert_eq!(remove.files[&path][0].start, (0, 3));
+    // The command sends its edit back by itself.
+    assert_eq!(lsp.run_code_action(&actions[1]), None);
+    let edit = |label: &'static str| {
+        move |event| match event {
+            LspEvent::Edit { label: l, edit, request } if l == label => {
+                Some((edit, request))
+            }
+            _ => None,
+        }
+    };
+    let (done, request) = answer(&mut lsp, &wakes, edit("mark done"));
+    assert_eq!(done.files[&path][0].text, "DONE");
+    // Answered once the buffer has the edit, which the server hears of
+    // first.
+    buffers.get_mut(0).unwrap().apply_edits(&done.files[&path]);
+    lsp.edit_applied(&buffers, request.unwrap(), Ok(()));
+    let noted = |note: &'static str| {
+        move |d: &[Diagnostic]| d.iter().any(|d| d.message == note)
+    };
+    wait_for(&mut lsp, &wakes, &path, noted("edit applied"));
+    assert_eq!(lsp.run_code_action(&actions[1]), None);
+    let (_, request) = answer(&mut lsp, &wakes, edit("mark done"));
+    lsp.edit_applied(&buffers, request.unwrap(), Err("read-only".into()));
+    wait_for(&mut lsp, &wakes, &path, noted("edit failed: read-only"));
+
+    assert!(lsp.rename(&buffers, &path, main, "start"));
+    let (renamed, request) = answer(&mut lsp, &wakes, edit("rename"));
+    assert!(request.is_none());
+    assert_eq!(renamed.len(), 1);
+    assert_eq!(renamed.files[&path][0].start, (1, 3));
+    let version = buffers.get(0).unwrap().version();
+    assert_eq!(renamed.versions[&path], version);
+
+    let lines = ["// FIXME  ".to_string(), "fn main() {}".to_string()];
+    buffers.get_mut(0).unwrap().set_lines(&lines);
+    assert!(lsp.format(&buffers, &path, None));
+    let (formatted, _) = answer(&mut lsp, &wakes, edit("format"));
+    assert_eq!(formatted.files[&path][0].start, (0, 8));
+
+    // A rename answered after more typing would land in the wrong place,
+    // so it is dropped.
+    assert!(lsp.rename(&buffers, &path, main, "start"));
+    let lines = ["// FIXME", "", "fn main() {}"].map(str::to_string);
+    buffers.get_mut(0).unwrap().set_lines(&lines);
+    lsp.sync(&buffers);
+    assert!(lsp.format(&buffers, &path, None));
+    let label = answer(&mut lsp, &wakes, |event| match event {
+        LspEvent::Edit { label, .. } => Some(label),
+        _ => None,
+    });
+    assert_eq!(label, "format");
+
+    let lines = ["fn main() {", "    run();", "}"].map(str::to_string);
+    buffers.get_mut(0).unwrap().set_lines(&lines);
+    lsp.sync(&buffers);
+    let folds = answer(&mut lsp, &wakes, |event| match event {
+        LspEvent::FoldingRanges { folds, .. } if !folds.is_empty() => {
+            Some(folds)
+        }
+        _ => None,
+    });
+    assert_eq!(folds, [Fold { start: 0, end: 2 }]);
+
+    buffers.close(0);
+    lsp.sync(&buffers);
+    assert_eq!(lsp.all_diagnostics().count(), 0);
+    lsp.shutdown();
+    fs::remove_dir_all(&root).unwrap();
+}
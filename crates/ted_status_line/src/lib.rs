@@ -0,0 +1,244 @@
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Paragraph};
+use serde::Deserialize;
+use ted_common::tasks::TaskStatus;
+use ted_editor::editor_status::EditorStatus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusSegment {
+    Mode,
+    FilePath,
+    Dirty,
+    Filetype,
+    Encoding,
+    LineEnding,
+    Position,
+    Percentage,
+    PendingKeys,
+    Focus,
+    /// The oldest background task and how many more there are.
+    Tasks,
+}
+
+/// The `[status_line]` section of `config.toml`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StatusLineConfig {
+    pub left: Vec<StatusSegment>,
+    pub right: Vec<StatusSegment>,
+    pub separator: String,
+}
+
+impl Default for StatusLineConfig {
+    fn default() -> Self {
+        use StatusSegment::*;
+        Self {
+            left: vec![Mode, FilePath, Dirty, PendingKeys],
+            right: vec![
+                Tasks, Focus, Filetype, Encoding, LineEnding, Position,
+                Percentage,
+            ],
+            separator: " | ".to_string(),
+        }
+    }
+}
+
+/// What the status line reflects on a given frame.
+pub struct StatusContext<'a> {
+    pub editor: &'a EditorStatus,
+    pub focused_pane: &'a str,
+    pub tasks: &'a [TaskStatus],
+}
+
+#[derive(Default)]
+pub struct StatusLine {
+    config: StatusLineConfig,
+}
+
+impl StatusLine {
+    pub fn new(config: StatusLineConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn draw(
+        &self,
+        frame: &mut Frame,
+        left: Rect,
+        right: Rect,
+        ctx: &StatusContext,
+    ) {
+        let left_line = self.build_line(&self.config.left, ctx);
+        let right_line = self.build_line(&self.config.right, ctx);
+        frame.render_widget(
+            Paragraph::new(left_line).block(Block::bordered().title("status")),
+            left,
+        );
+        frame.render_widget(
+            Paragraph::new(right_line)
+                .alignment(Alignment::Right)
+                .block(Block::bordered()),
+            right,
+        );
+    }
+
+    fn build_line(
+        &self,
+        segments: &[StatusSegment],
+        ctx: &StatusContext,
+    ) -> Line<'static> {
+        let mut spans = vec![];
+        for span in segments.iter().filter_map(|s| segment_span(*s, ctx)) {
+            if !spans.is_empty() {
+                spans.push(Span::raw(self.config.separator.clone()));
+            }
+            spans.push(span);
+        }
+        Line::from(spans)
+    }
+}
+
+fn segment_span(
+    segment: StatusSegment,
+    ctx: &StatusContext,
+) -> Option<Span<'static>> {
+    let editor = ctx.editor;
+    let span = match segment {
+        StatusSegment::Mode => {
+            Span::styled(format!(" {} ", editor.mode), mode_style(&editor.mode))
+        }
+        StatusSegment::FilePath => Span::raw(match &editor.path {
+            Some(path) => path.display().to_string(),
+            None => "[No Name]".to_string(),
+        }),
+        StatusSegment::Dirty if editor.dirty => {
+            Span::styled("[+]", Style::new().fg(Color::LightRed))
+        }
+        StatusSegment::Filetype => Span::raw(editor.filetype.clone()?),
+        StatusSegment::Encoding => Span::raw(editor.encoding),
+        StatusSegment::LineEnding => Span::raw(editor.line_ending.to_string()),
+        StatusSegment::Position => Span::raw(format!(
+            "{}:{}",
+            editor.cursor.0 + 1,
+            editor.cursor.1 + 1
+        )),
+        StatusSegment::Percentage => Span::raw(percentage(editor)),
+        StatusSegment::PendingKeys if !editor.pending_keys.is_empty() => {
+            Span::styled(
+                editor.pending_keys.clone(),
+                Style::new().fg(Color::LightYellow),
+            )
+        }
+        StatusSegment::Focus => Span::raw(ctx.focused_pane.to_string()),
+        StatusSegment::Tasks => {
+            Span::styled(tasks(ctx.tasks)?, Style::new().fg(Color::LightCyan))
+        }
+        StatusSegment::Dirty | StatusSegment::PendingKeys => return None,
+    };
+    Some(span)
+}
+
+fn mode_style(mode: &str) -> Style {
+    let color = match mode {
+        "INSERT" => Color::LightBlue,
+        "VISUAL" => Color::LightYellow,
+        "SEARCH" => Color::LightMagenta,
+        m if m.starts_with("OPERATOR") => Color::LightGreen,
+        _ => Color::Gray,
+    };
+    Style::new().fg(Color::Black).bg(color).add_modifier(Modifier::BOLD)
+}
+
+fn tasks(tasks: &[TaskStatus]) -> Option<String> {
+    let first = tasks.first()?;
+    let mut text = first.label.clone();
+    if let Some(percent) = first.percent() {
+        text.push_str(&format!(" {percent}%"));
+    }
+    if tasks.len() > 1 {
+        text.push_str(&format!(" (+{})", tasks.len() - 1));
+    }
+    Some(text)
+}
+
+fn percentage(editor: &EditorStatus) -> String {
+    let last = editor.line_count.saturating_sub(1);
+    match editor.cursor.0 {
+        0 => "Top".to_string(),
+        row if row >= last => "Bot".to_string(),
+        row => format!("{}%", row * 100 / last),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+    use ted_common::tasks::{TaskEvent, WorkerPool};
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|span| span.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn joins_the_segments_that_have_something_to_show() {
+        let editor = EditorStatus {
+            mode: "INSERT".to_string(),
+            path: Some(PathBuf::from("src/main.rs")),
+            encoding: "utf-8",
+            cursor: (4, 2),
+            line_count: 9,
+            ..Default::default()
+        };
+        let ctx = StatusContext {
+            editor: &editor,
+            focused_pane: "editor",
+            tasks: &[],
+        };
+        let status = StatusLine::default();
+        let left = status.build_line(&status.config.left, &ctx);
+        // Neither dirty nor waiting for keys, so those segments drop out.
+        assert_eq!(text(&left), " INSERT  | src/main.rs");
+        assert_eq!(left.spans[0].style.bg, Some(Color::LightBlue));
+        let right = status.build_line(&status.config.right, &ctx);
+        assert_eq!(text(&right), "editor | utf-8 | LF | 5:3 | 50%");
+    }
+
+    #[test]
+    fn places_the_cursor_in_the_file() {
+        let at = |row, line_count| {
+            let editor = EditorStatus {
+                cursor: (row, 0),
+                line_count,
+                ..Default::default()
+            };
+            percentage(&editor)
+        };
+        assert_eq!(at(0, 1), "Top");
+        assert_eq!(at(0, 10), "Top");
+        assert_eq!(at(3, 10), "33%");
+        assert_eq!(at(9, 10), "Bot");
+    }
+
+    #[test]
+    fn sums_up_tasks_by_the_oldest() {
+        assert_eq!(tasks(&[]), None);
+        let (events, _rx) = mpsc::channel();
+        let mut pool = WorkerPool::new(1, events);
+        let (release, blocked) = mpsc::channel::<()>();
+        let first = pool.spawn("indexing", move |_| {
+            blocked.recv().map_err(|e| e.to_string())
+        });
+        assert_eq!(tasks(pool.tasks()).as_deref(), Some("indexing"));
+        pool.spawn("formatting", |_| Ok(()));
+        pool.spawn("linting", |_| Ok(()));
+        pool.update(&TaskEvent::Progress {
+            id: first,
+            done: 1,
+            total: Some(4),
+        });
+        assert_eq!(tasks(pool.tasks()).as_deref(), Some("indexing 25% (+2)"));
+        release.send(()).unwrap();
+    }
+}
@@ -0,0 +1,181 @@
+use ratatui::crossterm::event::{Event, MouseButton, MouseEventKind};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Paragraph};
+use unicode_width::UnicodeWidthStr;
+
+const MAX_TITLE_WIDTH: usize = 24;
+
+pub struct TabInfo {
+    pub title: String,
+    pub icon: Option<char>,
+    pub dirty: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabBarAction {
+    Select(usize),
+    Close(usize),
+    Move { from: usize, to: usize },
+}
+
+/// Buffer line rendered in the header. It remembers where each tab was drawn
+/// on the last frame so mouse events can be mapped back to tabs.
+#[derive(Default)]
+pub struct TabBar {
+    hit_areas: Vec<(usize, Rect)>,
+    dragging: Option<usize>,
+}
+
+impl TabBar {
+    pub fn draw(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        summary_area: Rect,
+        tabs: &[TabInfo],
+        active: usize,
+    ) {
+        let block = Block::bordered().title("buffers");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        self.hit_areas.clear();
+
+        let labels: Vec<String> = tabs.iter().map(tab_label).collect();
+        let (first, last) = visible_range(&labels, active, inner.width);
+        let mut spans = vec![];
+        let mut x = inner.x;
+        if first > 0 {
+            spans.push(Span::raw("<"));
+            x += 1;
+        }
+        for idx in first..last {
+            let width = labels[idx].width() as u16;
+            self.hit_areas.push((idx, Rect::new(x, inner.y, width, 1)));
+            let style = if idx == active {
+                Style::new().fg(Color::Black).bg(Color::LightGreen)
+            } else if tabs[idx].dirty {
+                Style::new().fg(Color::LightRed)
+            } else {
+                Style::new().fg(Color::Gray)
+            };
+            spans.push(Span::styled(labels[idx].clone(), style));
+            x += width;
+        }
+        if last < labels.len() {
+            spans.push(Span::raw(">"));
+        }
+        frame.render_widget(Paragraph::new(Line::from(spans)), inner);
+
+        let modified = tabs.iter().filter(|t| t.dirty).count();
+        let summary = format!(
+            "{}/{} buffers, {} modified",
+            active + 1,
+            tabs.len(),
+            modified
+        );
+        frame.render_widget(
+            Paragraph::new(summary)
+                .alignment(Alignment::Right)
+                .block(Block::bordered()),
+            summary_area,
+        );
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> Option<TabBarAction> {
+        let Event::Mouse(mouse) = event else {
+            return None;
+        };
+        let hit = self.tab_at(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.dragging = hit;
+                hit.map(TabBarAction::Select)
+            }
+            MouseEventKind::Down(MouseButton::Middle) => {
+                hit.map(TabBarAction::Close)
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let from = self.dragging?;
+                let to = hit?;
+                if from == to {
+                    return None;
+                }
+                self.dragging = Some(to);
+                Some(TabBarAction::Move { from, to })
+            }
+            MouseEventKind::Up(_) => {
+                self.dragging = None;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, column: u16, row: u16) -> bool {
+        self.dragging.is_some() || self.tab_at(column, row).is_some()
+    }
+
+    fn tab_at(&self, column: u16, row: u16) -> Option<usize> {
+        let pos = Position::new(column, row);
+        self.hit_areas.iter().find(|(_, r)| r.contains(pos)).map(|(i, _)| *i)
+    }
+}
+
+fn tab_label(tab: &TabInfo) -> String {
+    let mut title = tab.title.clone();
+    if title.width() > MAX_TITLE_WIDTH {
+        let keep: String = title.chars().take(MAX_TITLE_WIDTH - 1).collect();
+        title = format!("{}…", keep);
+    }
+    let icon = tab.icon.map(|c| format!("{} ", c)).unwrap_or_default();
+    let dirty = if tab.dirty { " ●" } else { "" };
+    format!(" {}{}{} ", icon, title, dirty)
+}
+
+/// Picks the window of tabs to show so the active one is always visible,
+/// leaving room for the `<` / `>` overflow markers.
+fn visible_range(
+    labels: &[String],
+    active: usize,
+    width: u16,
+) -> (usize, usize) {
+    let width = width as usize;
+    let widths: Vec<usize> = labels.iter().map(|l| l.width()).collect();
+    let total: usize = widths.iter().sum();
+    if total <= width {
+        return (0, labels.len());
+    }
+    let budget = width.saturating_sub(2);
+    let (mut first, mut last) = (active, active + 1);
+    let mut used = widths.get(active).copied().unwrap_or(0);
+    loop {
+        let grew_right = last < labels.len() && used + widths[last] <= budget;
+        if grew_right {
+            used += widths[last];
+            last += 1;
+        }
+        let grew_left = first > 0 && used + widths[first - 1] <= budget;
+        if grew_left {
+            first -= 1;
+            used += widths[first];
+        }
+        if !grew_left && !grew_right {
+            return (first, last);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visible_range_keeps_active_tab() {
+        let labels: Vec<String> =
+            (0..10).map(|_| "abcde".to_string()).collect();
+        let (first, last) = visible_range(&labels, 9, 22);
+        assert!(first <= 9 && 9 < last);
+        assert_eq!(last, 10);
+        assert_eq!(visible_range(&labels, 0, 80), (0, 10));
+    }
+}
@@ -0,0 +1,104 @@
+use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Paragraph};
+use ted_lsp::code_action::CodeAction;
+
+/// The menu grows to fit the titles up to this wide, borders included.
+const MAX_WIDTH: u16 = 72;
+const MAX_ROWS: usize = 12;
+
+/// What `CodeActionsMenu::handle_event` asks the app to do.
+pub enum CodeActionsEvent {
+    None,
+    Close,
+    Run(Box<CodeAction>),
+}
+
+/// The fixes and refactorings offered at the cursor. `Enter` or an
+/// action's number runs it.
+pub struct CodeActionsMenu {
+    actions: Vec<CodeAction>,
+    selected: usize,
+}
+
+impl CodeActionsMenu {
+    pub fn new(actions: Vec<CodeAction>) -> Self {
+        Self { actions, selected: 0 }
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        let widest = self
+            .actions
+            .iter()
+            .enumerate()
+            .map(|(idx, action)| label(idx, action).chars().count())
+            .max()
+            .unwrap_or(0);
+        let width = (widest as u16).saturating_add(2).min(MAX_WIDTH);
+        (width, self.actions.len().min(MAX_ROWS) as u16 + 2)
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> CodeActionsEvent {
+        let Event::Key(key) = event else {
+            return CodeActionsEvent::None;
+        };
+        if key.kind != KeyEventKind::Press {
+            return CodeActionsEvent::None;
+        }
+        let last = self.actions.len().saturating_sub(1);
+        let run = match key.code {
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                None
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.selected = (self.selected + 1).min(last);
+                None
+            }
+            KeyCode::Enter => Some(self.selected),
+            KeyCode::Char(c @ '1'..='9') => Some(c as usize - '1' as usize),
+            KeyCode::Char('q') => return CodeActionsEvent::Close,
+            _ => None,
+        };
+        match run.and_then(|idx| self.actions.get(idx)) {
+            Some(action) if action.disabled.is_none() => {
+                CodeActionsEvent::Run(Box::new(action.clone()))
+            }
+            _ => CodeActionsEvent::None,
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title("code actions");
+        let rows = (block.inner(area).height as usize).max(1);
+        let start = (self.selected + 1).saturating_sub(rows);
+        let lines: Vec<Line> = self
+            .actions
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(rows)
+            .map(|(idx, action)| {
+                let style = match (idx == self.selected, &action.disabled) {
+                    (_, Some(_)) => Style::new().fg(Color::DarkGray),
+                    (true, None) => Style::new().bg(Color::DarkGray),
+                    (false, None) => Style::new(),
+                };
+                Line::styled(label(idx, action), style)
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+/// `1. Import x`, with why it can't run if it can't.
+fn label(idx: usize, action: &CodeAction) -> String {
+    let number = match idx < 9 {
+        true => format!("{}.", idx + 1),
+        false => "  ".to_string(),
+    };
+    match &action.disabled {
+        Some(reason) => format!("{number} {} ({reason})", action.title),
+        None => format!("{number} {}", action.title),
+    }
+}
@@ -0,0 +1,73 @@
+use crate::markdown;
+use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use std::path::PathBuf;
+
+/// The popup grows to fit the documentation up to this size, borders
+/// included.
+const MAX_WIDTH: u16 = 80;
+const MAX_HEIGHT: u16 = 20;
+
+/// Documentation for the symbol under the cursor, shown by `K`.
+pub struct Hover {
+    lines: Vec<Line<'static>>,
+    /// Where it was asked for; it goes away once the cursor leaves.
+    pub path: PathBuf,
+    pub cursor: (usize, usize),
+    scroll: u16,
+}
+
+impl Hover {
+    pub fn new(markdown: &str, path: PathBuf, cursor: (usize, usize)) -> Self {
+        let rule = MAX_WIDTH as usize - 2;
+        Self {
+            lines: markdown::render(markdown, rule),
+            path,
+            cursor,
+            scroll: 0,
+        }
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        let widest = self.lines.iter().map(Line::width).max().unwrap_or(0);
+        let width = (widest as u16).saturating_add(2).min(MAX_WIDTH);
+        let inner = (width - 2).max(1) as usize;
+        let rows: usize = self
+            .lines
+            .iter()
+            .map(|line| line.width().div_ceil(inner).max(1))
+            .sum();
+        (width, (rows as u16).saturating_add(2).min(MAX_HEIGHT))
+    }
+
+    /// Scrolls with `j`/`k` once the popup has focus. Returns whether it
+    /// should close.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        let Event::Key(key) = event else {
+            return false;
+        };
+        if key.kind != KeyEventKind::Press {
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.scroll = self.scroll.saturating_add(1)
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.scroll = self.scroll.saturating_sub(1)
+            }
+            KeyCode::Char('q') => return true,
+            _ => {}
+        }
+        false
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        let paragraph = Paragraph::new(self.lines.clone())
+            .block(Block::bordered())
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(paragraph, area);
+    }
+}
@@ -0,0 +1,102 @@
+use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Paragraph};
+use std::path::PathBuf;
+use ted_editor::{diff, diff_view};
+
+/// The popup grows to fit the lines up to this size, borders included.
+const MAX_WIDTH: u16 = 100;
+const MAX_HEIGHT: u16 = 20;
+const TITLE: &str = "change";
+const REMOVED: Style = Style::new().fg(Color::Red);
+const ADDED: Style = Style::new().fg(Color::Green);
+
+/// The change under the cursor as a diff against git, shown by
+/// `<leader>hp`.
+pub struct HunkPreview {
+    lines: Vec<Line<'static>>,
+    /// Where it was asked for; it goes away once the cursor leaves.
+    pub path: Option<PathBuf>,
+    pub cursor: (usize, usize),
+    scroll: u16,
+}
+
+impl HunkPreview {
+    /// Lines of a modified hunk are paired up in order, and the chars that
+    /// changed between a pair stand out.
+    pub fn new(
+        old: &[String],
+        new: &[String],
+        path: Option<PathBuf>,
+        cursor: (usize, usize),
+    ) -> Self {
+        let mut lines: Vec<Line> = old
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| {
+                let changed = match new.get(idx) {
+                    Some(other) => diff::changed_chars(line, other).0,
+                    None => vec![],
+                };
+                diff_line('-', line, &changed, REMOVED)
+            })
+            .collect();
+        lines.extend(new.iter().enumerate().map(|(idx, line)| {
+            let changed = match old.get(idx) {
+                Some(other) => diff::changed_chars(other, line).1,
+                None => vec![],
+            };
+            diff_line('+', line, &changed, ADDED)
+        }));
+        Self { lines, path, cursor, scroll: 0 }
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        let widest = self.lines.iter().map(Line::width).max().unwrap_or(0);
+        let widest = widest.max(TITLE.len());
+        let width = (widest as u16).saturating_add(2).min(MAX_WIDTH);
+        let height = (self.lines.len() as u16).saturating_add(2);
+        (width, height.min(MAX_HEIGHT))
+    }
+
+    /// Scrolls with `j`/`k` once the popup has focus. Returns whether it
+    /// should close.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        let Event::Key(key) = event else {
+            return false;
+        };
+        if key.kind != KeyEventKind::Press {
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.scroll = self.scroll.saturating_add(1)
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.scroll = self.scroll.saturating_sub(1)
+            }
+            KeyCode::Char('q') => return true,
+            _ => {}
+        }
+        false
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        let paragraph = Paragraph::new(self.lines.clone())
+            .block(Block::bordered().title(TITLE))
+            .scroll((self.scroll, 0));
+        frame.render_widget(paragraph, area);
+    }
+}
+
+/// `line` after `sign`, with the `changed` char ranges reversed.
+fn diff_line(
+    sign: char,
+    line: &str,
+    changed: &[std::ops::Range<usize>],
+    style: Style,
+) -> Line<'static> {
+    let mut spans = vec![Span::styled(sign.to_string(), style)];
+    spans.extend(diff_view::highlight_changes(line, changed, style));
+    Line::from(spans)
+}
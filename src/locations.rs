@@ -0,0 +1,87 @@
+use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Paragraph};
+use std::path::Path;
+use ted_lsp::navigation::{Location, Navigation};
+
+/// What `LocationsView::handle_event` asks the app to do.
+pub enum LocationsEvent {
+    None,
+    Close,
+    Jump(Location),
+}
+
+/// The picker `gr` opens, or `gd` and `gi` when there is more than one
+/// place to go.
+pub struct LocationsView {
+    kind: Navigation,
+    locations: Vec<Location>,
+    selected: usize,
+}
+
+impl LocationsView {
+    pub fn new(kind: Navigation, locations: Vec<Location>) -> Self {
+        Self { kind, locations, selected: 0 }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> LocationsEvent {
+        let Event::Key(key) = event else {
+            return LocationsEvent::None;
+        };
+        if key.kind != KeyEventKind::Press {
+            return LocationsEvent::None;
+        }
+        let last = self.locations.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.selected = (self.selected + 1).min(last)
+            }
+            KeyCode::Enter => {
+                return match self.locations.get(self.selected) {
+                    Some(location) => LocationsEvent::Jump(location.clone()),
+                    None => LocationsEvent::Close,
+                };
+            }
+            KeyCode::Char('q') => return LocationsEvent::Close,
+            _ => {}
+        }
+        LocationsEvent::None
+    }
+
+    /// Draws the list, with paths relative to `workspace`.
+    pub fn draw(&self, frame: &mut Frame, area: Rect, workspace: &Path) {
+        let title = format!("{} ({})", self.kind.label(), self.locations.len());
+        let block = Block::bordered().title(title);
+        let rows = (block.inner(area).height as usize).max(1);
+        let start = (self.selected + 1).saturating_sub(rows);
+        let lines: Vec<Line> = self
+            .locations
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(rows)
+            .map(|(idx, location)| {
+                let path = location
+                    .path
+                    .strip_prefix(workspace)
+                    .unwrap_or(&location.path);
+                let (row, col) = location.start;
+                let line = Line::from(vec![
+                    Span::styled(
+                        format!("{}:{}:{} ", path.display(), row + 1, col + 1),
+                        Style::new().fg(Color::Gray),
+                    ),
+                    Span::raw(location.line.trim().to_string()),
+                ]);
+                match idx == self.selected {
+                    true => line.style(Style::new().bg(Color::DarkGray)),
+                    false => line,
+                }
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
@@ -1,71 +1,1993 @@
+mod code_actions;
+mod hover;
+mod hunk_preview;
+mod locations;
+mod markdown;
+mod problems;
+mod recovery;
+mod session;
+mod unsaved_guard;
+mod views;
+
+use code_actions::{CodeActionsEvent, CodeActionsMenu};
+use hover::Hover;
+use hunk_preview::HunkPreview;
+use locations::{LocationsEvent, LocationsView};
+use problems::{ProblemsEvent, ProblemsView};
 use ratatui::crossterm::event::{
-    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
+    DisableFocusChange, DisableMouseCapture, EnableFocusChange,
+    EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
 };
+use ratatui::crossterm::execute;
 use ratatui::prelude::*;
 use ratatui::widgets::Block;
+use recovery::SwapNotice;
+use session::{Session, SessionConfig, map_views};
+use std::env;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+use ted_command_line::{Command, CommandLine, CommandLineEvent, PromptEvent};
+use ted_common::bus::{Bus, Message, Topic};
+use ted_common::component::{Component, EventResult};
+use ted_common::config::Config;
+use ted_common::event_loop::{EventLoop, LoopEvent};
+use ted_common::keymap::{
+    KeyContext, KeyPress, Keymap, KeymapConfig, KeymapResolver, KeymapResult,
+};
+use ted_common::notify::{self, Notifications};
+use ted_common::state;
+use ted_common::tasks::{TaskEvent, TaskId, TaskOutcome, WorkerPool};
+use ted_editor::buffer_list::BufferList;
+use ted_editor::completion::{
+    self, Completion, CompletionConfig, CompletionItem, Snippets,
+};
+use ted_editor::diff_view::{DiffConfig, DiffLayout, DiffSide, DiffView};
+use ted_editor::fold::FoldConfig;
+use ted_editor::git::{self, GitBase, GitConfig};
+use ted_editor::gutter::GutterConfig;
+use ted_editor::jump_list::{Jump, JumpList};
+use ted_editor::swap::{self, SwapFiles, SwapStatus};
 use ted_editor::vim_editor::vim_editor::VimEditor;
-use ted_editor::vim_lite_editor::vim_editor::VimLiteEditor;
 use ted_fs_explorer::fs_explorer::FsExplorer;
-use ted_layout::TedLayoutManager;
+use ted_fs_explorer::fs_icon_manager::FsIconManager;
+use ted_layout::focus_manager::{FocusDirection, FocusManager, PaneId};
+use ted_layout::layer_stack::{Anchor, Layer, LayerEvent, LayerStack};
+use ted_layout::resize_controller::ResizeController;
+use ted_layout::split_tree::SplitDirection;
+use ted_layout::{AsideSizes, TedLayoutManager};
+use ted_lsp::code_action::CodeAction;
+use ted_lsp::edit::WorkspaceEdit;
+use ted_lsp::navigation::{Location, Navigation};
+use ted_lsp::{Lsp, LspConfig, LspEvent};
+use ted_status_line::{StatusContext, StatusLine};
+use ted_tab_bar::{TabBar, TabBarAction, TabInfo};
+use unsaved_guard::{GuardTarget, UnsavedGuard};
+use views::Views;
 
-fn main() -> io::Result<()> {
-    let mut term = ratatui::init();
+const GLOBAL_ACTIONS: &[&str] = &[
+    "quit",
+    "command_line",
+    "focus_next",
+    "focus_previous",
+    "focus_left",
+    "focus_down",
+    "focus_up",
+    "focus_right",
+    "buffer_next",
+    "buffer_prev",
+    "split_horizontal",
+    "split_vertical",
+    "close_view",
+    "only_view",
+    "equalize_views",
+    "rotate_views",
+    "increase_height",
+    "decrease_height",
+    "increase_width",
+    "decrease_width",
+    "complete",
+    "goto_definition",
+    "goto_implementation",
+    "goto_references",
+    "hover",
+    "jump_back",
+    "jump_forward",
+    "rename",
+    "code_actions",
+    "format",
+    "preview_hunk",
+    "close_diff",
+];
 
-    let mut layout_manager = TedLayoutManager::default();
-    layout_manager.set_left_aside_width(20);
+/// Written under the state directory when ted runs with `--trace-bus`.
+const BUS_TRACE: &str = "bus.log";
+const WORKER_THREADS: usize = 4;
+/// Wakes the loop when the oldest toast is due to go away.
+const TOAST_TIMER: &str = "toasts";
+/// At most about 60 frames a second, however fast events come in.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+/// Wakes the loop to draw a frame that was held back by the cap.
+const FRAME_TIMER: &str = "frame";
+/// Most events handled between two frames, so a flood of input can't keep
+/// the screen from updating.
+const MAX_BATCH: usize = 256;
 
-    let mut fs_explorer = FsExplorer::new(PathBuf::from(
-        "/Users/w4ngzhen/projects/rust-projects/ted/crates/ted_fs_explorer/test",
-    ))?;
+/// Prompts float in the middle of the screen at this size.
+const PROMPT_WIDTH: u16 = 72;
+const PROMPT_HEIGHT: u16 = 5;
+const MESSAGES_SIZE: (u16, u16) = (100, 20);
+const PROBLEMS_SIZE: (u16, u16) = (100, 20);
+const LOCATIONS_SIZE: (u16, u16) = (100, 20);
+/// Typing a word this long opens completion by itself.
+const COMPLETION_PREFIX: usize = 2;
+/// Typing one of these opens completion for what comes after it.
+const COMPLETION_TRIGGERS: &[char] = &['.', ':', '/'];
 
-    // let mut vim_editor = VimLiteEditor::new();
-    let mut vim_editor = VimEditor::new();
+/// What a floating layer shows; the state behind it lives on `App`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Popup {
+    UnsavedGuard,
+    SwapNotice,
+    Messages,
+    Problems,
+    Completion,
+    Locations,
+    Hover,
+    CodeActions,
+    HunkPreview,
+}
+
+impl Popup {
+    /// Prompts come and go with the state that asks the question.
+    fn is_prompt(self) -> bool {
+        matches!(self, Popup::UnsavedGuard | Popup::SwapNotice)
+    }
+}
 
-    let mut focus_editor = true;
+/// A file and what git has for it, as reloaded on a worker.
+type GitLines = (PathBuf, Option<Vec<String>>);
 
-    loop {
-        term.draw(|f| {
-            // render whole UI with black
-            f.render_widget(
-                Block::default().style(Style::default().bg(Color::Black)),
-                f.area(),
+/// Who the bus delivers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Subscriber {
+    App,
+    Explorer,
+}
+
+/// What the main loop should do after handling an action or command.
+enum Flow {
+    Continue,
+    Quit,
+}
+
+struct App {
+    layout_manager: TedLayoutManager,
+    resize: ResizeController,
+    /// The screen area of the last frame, used to lay out mouse hits and
+    /// keyboard resizes between draws.
+    area: Rect,
+    fs_explorer: FsExplorer,
+    buffers: BufferList,
+    views: Views,
+    focus: FocusManager,
+    command_line: CommandLine,
+    swaps: SwapFiles,
+    /// Swap files found on open, answered one at a time.
+    swap_notices: Vec<SwapNotice>,
+    unsaved_guard: Option<UnsavedGuard>,
+    layers: LayerStack<Popup>,
+    /// Quitting keeps unsaved edits in the session instead of asking.
+    hot_exit: bool,
+    keymap: Keymap,
+    key_resolver: KeymapResolver,
+    tab_bar: TabBar,
+    notifications: Notifications,
+    /// How many lines `:messages` is scrolled back from the newest.
+    messages_scroll: usize,
+    bus: Bus<Subscriber>,
+    tasks: WorkerPool,
+    /// The task reading every buffer's git version again, and where it
+    /// sends each file's lines.
+    git_reload: Option<(TaskId, Receiver<GitLines>)>,
+    lsp: Lsp,
+    problems: ProblemsView,
+    locations: Option<LocationsView>,
+    hover: Option<Hover>,
+    hunk_preview: Option<HunkPreview>,
+    /// Shown in place of the views while open.
+    diff: Option<DiffView>,
+    diff_layout: DiffLayout,
+    code_actions: Option<CodeActionsMenu>,
+    jumps: JumpList,
+    completion: Option<Completion>,
+    /// A language server is still working on the open completion.
+    completion_pending: bool,
+    snippets: Snippets,
+    /// The char just typed into the active buffer, which may open
+    /// completion.
+    typed: Option<char>,
+    /// Whether anything on screen changed since the last frame.
+    dirty: bool,
+}
+
+impl App {
+    fn run_global_action(&mut self, action: &str) -> io::Result<Flow> {
+        match action {
+            "quit" => return self.guard(GuardTarget::Quit),
+            "command_line" => self.command_line.open(),
+            "focus_next" => self.focus.focus_next(),
+            "focus_previous" => self.focus.focus_previous(),
+            "focus_left" => self.focus.focus_direction(FocusDirection::Left),
+            "focus_down" => self.focus.focus_direction(FocusDirection::Down),
+            "focus_up" => self.focus.focus_direction(FocusDirection::Up),
+            "focus_right" => self.focus.focus_direction(FocusDirection::Right),
+            "buffer_next" => self.buffers.select_next(),
+            "buffer_prev" => self.buffers.select_prev(),
+            "split_horizontal" => self.split(SplitDirection::Horizontal),
+            "split_vertical" => self.split(SplitDirection::Vertical),
+            "close_view" => self.close_view(),
+            "only_view" => self.views.only(),
+            "equalize_views" => self.views.equalize(),
+            "rotate_views" => self.views.rotate(),
+            "increase_height" => self.resize(SplitDirection::Horizontal, 1),
+            "decrease_height" => self.resize(SplitDirection::Horizontal, -1),
+            "increase_width" => self.resize(SplitDirection::Vertical, 1),
+            "decrease_width" => self.resize(SplitDirection::Vertical, -1),
+            "complete" => self.open_completion(),
+            "goto_definition" => self.navigate(Navigation::Definition),
+            "goto_implementation" => self.navigate(Navigation::Implementation),
+            "goto_references" => self.navigate(Navigation::References),
+            "hover" => self.hover(),
+            "jump_back" => self.jump_back()?,
+            "jump_forward" => self.jump_forward()?,
+            "rename" => {
+                let buffer = self.buffers.active();
+                let (row, col) = buffer.cursor();
+                let line = buffer.line(row).unwrap_or_default();
+                let word = completion::word_at(&line, col);
+                self.command_line.open_with(&format!("rename {word}"));
+            }
+            "code_actions" => self.request_code_actions(),
+            "format" => self.format(),
+            "preview_hunk" => self.preview_hunk(),
+            "close_diff" => self.diff = None,
+            action => {
+                self.focused_component().run_action(action);
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn run_command(&mut self, command: Command) -> io::Result<Flow> {
+        match command.name.as_str() {
+            "sp" | "split" => self.split(SplitDirection::Horizontal),
+            "vs" | "vsplit" => self.split(SplitDirection::Vertical),
+            "clo" | "close" => self.close_view(),
+            "on" | "only" => self.views.only(),
+            "q" | "quit" if self.views.count() > 1 => self.close_view(),
+            "q" | "quit" | "qa" | "qall" if command.bang => {
+                return Ok(Flow::Quit);
+            }
+            "q" | "quit" | "qa" | "qall" => {
+                return self.guard(GuardTarget::Quit);
+            }
+            "w" | "write" => {
+                let buffer = self.buffers.active_mut();
+                buffer.save()?;
+                notify::info(format!("\"{}\" written", buffer.title()));
+            }
+            "wa" | "wall" => {
+                for idx in 0..self.buffers.len() {
+                    if let Some(buffer) = self.buffers.get_mut(idx)
+                        && buffer.path().is_some()
+                        && buffer.is_dirty()
+                    {
+                        buffer.save()?;
+                    }
+                }
+            }
+            "e" | "edit" => {
+                if let Some(path) = command.args.first() {
+                    self.open_buffer(path)?;
+                    self.views.follow_active_buffer(&self.buffers);
+                }
+            }
+            "mes" | "messages" => self.show_messages(),
+            "cancel" => self.tasks.cancel_all(),
+            "problems" => self.show_problems(),
+            "rename" => match command.args.as_slice() {
+                [new_name] => self.rename(new_name),
+                _ => notify::error("usage: :rename <new name>"),
+            },
+            "format" => self.format(),
+            "diff" => match command.args.as_slice() {
+                [] => self.diff_buffer(false),
+                [base] if base == "head" => self.diff_buffer(true),
+                _ => notify::error("usage: :diff [head]"),
+            },
+            "diffoff" => self.diff = None,
+            "bn" | "bnext" => self.buffers.select_next(),
+            "bp" | "bprevious" => self.buffers.select_prev(),
+            name => notify::error(format!("not an editor command: {name}")),
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn open_buffer(&mut self, path: impl Into<PathBuf>) -> io::Result<usize> {
+        let idx = self.buffers.open(path)?;
+        let path = self.buffers.active().path().map(Path::to_path_buf);
+        if let Some(path) = path
+            && !self.swaps.owns(&path)
+            && !self.swap_notices.iter().any(|n| n.path() == path)
+        {
+            match SwapFiles::check(&path) {
+                SwapStatus::Clean => self.swaps.claim(&path)?,
+                SwapStatus::InUse { pid } => {
+                    self.swap_notices.push(SwapNotice::InUse { path, pid })
+                }
+                SwapStatus::Recoverable { lines } => {
+                    self.swap_notices.push(SwapNotice::Recover { path, lines })
+                }
+            }
+        }
+        Ok(idx)
+    }
+
+    fn close_buffer(&mut self, idx: usize) -> io::Result<()> {
+        if let Some(path) = self.buffers.get(idx).and_then(|b| b.path()) {
+            self.swaps.release(path)?;
+        }
+        self.buffers.close(idx);
+        self.views.buffer_closed(idx, &self.buffers);
+        Ok(())
+    }
+
+    /// Carries out `target` right away unless it would lose unsaved edits,
+    /// in which case the unsaved-changes prompt is shown first.
+    fn guard(&mut self, target: GuardTarget) -> io::Result<Flow> {
+        if self.hot_exit && target == GuardTarget::Quit {
+            return self.proceed(target);
+        }
+        match UnsavedGuard::new(target, &self.buffers) {
+            Some(guard) => {
+                self.unsaved_guard = Some(guard);
+                Ok(Flow::Continue)
+            }
+            None => self.proceed(target),
+        }
+    }
+
+    fn proceed(&mut self, target: GuardTarget) -> io::Result<Flow> {
+        match target {
+            GuardTarget::Quit => return Ok(Flow::Quit),
+            GuardTarget::CloseBuffer(idx) => self.close_buffer(idx)?,
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn answer_unsaved_guard(&mut self, event: &Event) -> io::Result<Flow> {
+        let Some(guard) = &mut self.unsaved_guard else {
+            return Ok(Flow::Continue);
+        };
+        let choice = match guard.prompt(&self.buffers).handle_event(event) {
+            PromptEvent::Choose(choice) => choice,
+            PromptEvent::Cancel => {
+                self.unsaved_guard = None;
+                return Ok(Flow::Continue);
+            }
+            PromptEvent::None => return Ok(Flow::Continue),
+        };
+        let done = match choice {
+            's' => guard.save_all(&mut self.buffers),
+            'd' => guard.discard_all(),
+            'r' => {
+                guard.review();
+                false
+            }
+            'y' => guard.answer(true, &mut self.buffers),
+            _ => guard.answer(false, &mut self.buffers),
+        };
+        let target = guard.target;
+        let current = guard.current();
+        if done {
+            self.unsaved_guard = None;
+            return self.proceed(target);
+        }
+        if let Some(current) = current {
+            self.show_buffer(current);
+        }
+        Ok(Flow::Continue)
+    }
+
+    /// Brings `buffer` up in the active view, e.g. to review it.
+    fn show_buffer(&mut self, buffer: usize) {
+        self.buffers.select(buffer);
+        self.views.follow_active_buffer(&self.buffers);
+    }
+
+    /// Shows the prompt for whatever needs an answer first, if anything.
+    fn sync_popups(&mut self) {
+        let wanted = if self.unsaved_guard.is_some() {
+            Some(Popup::UnsavedGuard)
+        } else if !self.swap_notices.is_empty() {
+            Some(Popup::SwapNotice)
+        } else {
+            None
+        };
+        self.layers
+            .retain(|popup| !popup.is_prompt() || Some(*popup) == wanted);
+        if let Some(popup) = wanted
+            && !self.layers.contains(|p| *p == popup)
+        {
+            self.layers.push(
+                Layer::new(popup, Anchor::Screen, PROMPT_WIDTH, PROMPT_HEIGHT)
+                    .modal(),
             );
-            // render widgets.
-            let ted_layout = layout_manager.build(f.area());
-            vim_editor.draw(f, ted_layout.content);
-            fs_explorer.draw(f, ted_layout.aside_left);
-        })?;
+        }
+    }
+
+    fn draw_popup(&self, frame: &mut Frame, popup: &Popup, area: Rect) {
+        match popup {
+            Popup::UnsavedGuard => {
+                if let Some(guard) = &self.unsaved_guard {
+                    guard.prompt(&self.buffers).draw(frame, area);
+                }
+            }
+            Popup::SwapNotice => {
+                if let Some(notice) = self.swap_notices.first() {
+                    notice.prompt().draw(frame, area);
+                }
+            }
+            Popup::Messages => self.notifications.draw_history(
+                frame,
+                area,
+                self.messages_scroll,
+            ),
+            Popup::Problems => {
+                self.problems.draw(frame, area, &self.lsp, self.lsp.root())
+            }
+            Popup::Completion => {
+                if let Some(menu) = &self.completion {
+                    menu.draw(frame, area);
+                }
+            }
+            Popup::Locations => {
+                if let Some(locations) = &self.locations {
+                    locations.draw(frame, area, self.lsp.root());
+                }
+            }
+            Popup::Hover => {
+                if let Some(hover) = &self.hover {
+                    hover.draw(frame, area);
+                }
+            }
+            Popup::CodeActions => {
+                if let Some(menu) = &self.code_actions {
+                    menu.draw(frame, area);
+                }
+            }
+            Popup::HunkPreview => {
+                if let Some(preview) = &self.hunk_preview {
+                    preview.draw(frame, area);
+                }
+            }
+        }
+    }
+
+    fn show_messages(&mut self) {
+        self.messages_scroll = 0;
+        if !self.layers.contains(|p| *p == Popup::Messages) {
+            let (width, height) = MESSAGES_SIZE;
+            self.layers.push(
+                Layer::new(Popup::Messages, Anchor::Screen, width, height)
+                    .modal(),
+            );
+        }
+    }
+
+    fn show_problems(&mut self) {
+        if !self.layers.contains(|p| *p == Popup::Problems) {
+            self.problems = ProblemsView::default();
+            let (width, height) = PROBLEMS_SIZE;
+            self.layers.push(
+                Layer::new(Popup::Problems, Anchor::Screen, width, height)
+                    .modal(),
+            );
+        }
+    }
+
+    fn answer_problems(&mut self, event: &Event) -> io::Result<()> {
+        match self.problems.handle_event(event, &self.lsp) {
+            ProblemsEvent::None => {}
+            ProblemsEvent::Close => {
+                self.layers.retain(|p| *p != Popup::Problems)
+            }
+            ProblemsEvent::Jump(path, position) => {
+                self.layers.retain(|p| *p != Popup::Problems);
+                self.jump_to(&path, position)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn answer_locations(&mut self, event: &Event) -> io::Result<()> {
+        let Some(locations) = &mut self.locations else {
+            return Ok(());
+        };
+        match locations.handle_event(event) {
+            LocationsEvent::None => {}
+            LocationsEvent::Close => self.close_popup(Popup::Locations),
+            LocationsEvent::Jump(location) => {
+                self.close_popup(Popup::Locations);
+                self.jump_to(&location.path, location.start)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn close_popup(&mut self, popup: Popup) {
+        self.layers.retain(|p| *p != popup);
+        self.dismiss_popup(popup);
+    }
+
+    /// Asks the language server where to go from the cursor. The answer
+    /// comes in through `show_locations`.
+    fn navigate(&mut self, kind: Navigation) {
+        let buffer = self.buffers.active();
+        let Some(path) = buffer.path() else {
+            notify::warn("the buffer has no file to look things up in");
+            return;
+        };
+        if !self.lsp.navigate(&self.buffers, path, buffer.cursor(), kind) {
+            notify::warn(format!("{}: no language server", path.display()));
+        }
+    }
+
+    /// Goes straight to the only definition or implementation found, and
+    /// lets the user pick otherwise. References are always listed.
+    fn show_locations(
+        &mut self,
+        kind: Navigation,
+        mut locations: Vec<Location>,
+    ) -> io::Result<()> {
+        match locations.len() {
+            0 => notify::info(format!("no {} found", kind.label())),
+            1 if kind != Navigation::References => {
+                let location = locations.remove(0);
+                self.jump_to(&location.path, location.start)?;
+            }
+            _ => {
+                self.close_popup(Popup::Locations);
+                self.locations = Some(LocationsView::new(kind, locations));
+                let (width, height) = LOCATIONS_SIZE;
+                self.layers.push(
+                    Layer::new(Popup::Locations, Anchor::Screen, width, height)
+                        .modal(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Asks for the documentation under the cursor, or moves into the
+    /// popup to scroll it if it is already shown.
+    fn hover(&mut self) {
+        if self.hover.is_some() {
+            let top = self.layers.top().cloned();
+            if let Some(layer) = top.filter(|l| l.content == Popup::Hover) {
+                self.layers.retain(|p| *p != Popup::Hover);
+                self.layers.push(layer.modal());
+            }
+            return;
+        }
+        let buffer = self.buffers.active();
+        let Some(path) = buffer.path() else {
+            return;
+        };
+        if !self.lsp.hover(&self.buffers, path, buffer.cursor()) {
+            notify::warn(format!("{}: no language server", path.display()));
+        }
+    }
+
+    /// Shows documentation that came back for `cursor` in `path`, unless
+    /// the cursor has moved on since.
+    fn show_hover(
+        &mut self,
+        path: PathBuf,
+        cursor: (usize, usize),
+        markdown: Option<String>,
+    ) {
+        let buffer = self.buffers.active();
+        let here = buffer.path().and_then(|p| std::path::absolute(p).ok());
+        if here.as_ref() != Some(&path) || buffer.cursor() != cursor {
+            return;
+        }
+        let Some(markdown) = markdown else {
+            notify::info("no documentation here");
+            return;
+        };
+        let Some(position) = buffer.screen_position(cursor) else {
+            return;
+        };
+        let hover = Hover::new(&markdown, path, cursor);
+        let (width, height) = hover.size();
+        self.close_popup(Popup::Hover);
+        self.hover = Some(hover);
+        self.layers.push(Layer::new(
+            Popup::Hover,
+            Anchor::Cursor(position),
+            width,
+            height,
+        ));
+    }
+
+    /// Closes the documentation once the cursor leaves where it was asked
+    /// for.
+    fn sync_hover(&mut self) {
+        let Some(hover) = &self.hover else {
+            return;
+        };
+        let buffer = self.buffers.active();
+        let here = buffer.path().and_then(|p| std::path::absolute(p).ok());
+        if here.as_ref() != Some(&hover.path) || buffer.cursor() != hover.cursor
+        {
+            self.close_popup(Popup::Hover);
+        }
+    }
+
+    /// Shows the change under the cursor against git, or moves into the
+    /// popup to scroll it if it is already shown.
+    fn preview_hunk(&mut self) {
+        if self.hunk_preview.is_some() {
+            let top = self.layers.top().cloned();
+            if let Some(layer) = top.filter(|l| l.content == Popup::HunkPreview)
+            {
+                self.layers.retain(|p| *p != Popup::HunkPreview);
+                self.layers.push(layer.modal());
+            }
+            return;
+        }
+        let buffer = self.buffers.active_mut();
+        let cursor = buffer.cursor();
+        let Some((hunk, old)) = buffer.hunk_at(cursor.0) else {
+            notify::info("no change here");
+            return;
+        };
+        let new = buffer.lines().get(hunk.new).unwrap_or_default().to_vec();
+        let Some(position) = buffer.screen_position(cursor) else {
+            return;
+        };
+        let path = buffer.path().map(Path::to_path_buf);
+        let preview = HunkPreview::new(&old, &new, path, cursor);
+        let (width, height) = preview.size();
+        self.hunk_preview = Some(preview);
+        self.layers.push(Layer::new(
+            Popup::HunkPreview,
+            Anchor::Cursor(position),
+            width,
+            height,
+        ));
+    }
+
+    /// Closes the change preview once the cursor leaves where it was
+    /// asked for.
+    fn sync_hunk_preview(&mut self) {
+        let Some(preview) = &self.hunk_preview else {
+            return;
+        };
+        let buffer = self.buffers.active();
+        if buffer.path() != preview.path.as_deref()
+            || buffer.cursor() != preview.cursor
+        {
+            self.close_popup(Popup::HunkPreview);
+        }
+    }
+
+    /// Compares the active buffer with its file, or with the last commit
+    /// if `head`.
+    fn diff_buffer(&mut self, head: bool) {
+        let buffer = self.buffers.active();
+        let Some(path) = buffer.path().map(Path::to_path_buf) else {
+            notify::warn("the buffer has no file");
+            return;
+        };
+        let title = buffer.title();
+        let base = if head {
+            match git::base_lines(&path, GitBase::Head) {
+                Some(lines) => lines,
+                None => {
+                    notify::warn(format!("{title} is not committed to git"));
+                    return;
+                }
+            }
+        } else {
+            match std::fs::read_to_string(&path) {
+                Ok(text) => text.lines().map(str::to_string).collect(),
+                Err(e) => {
+                    notify::error(format!("{}: {e}", path.display()));
+                    return;
+                }
+            }
+        };
+        let base_title = match head {
+            true => format!("{title} at HEAD"),
+            false => format!("{title} on disk"),
+        };
+        let left = DiffSide::fixed(base_title, base);
+        let right =
+            DiffSide::buffer(title, buffer.lines(), path, buffer.version());
+        self.open_diff(left, right);
+    }
+
+    /// Opens `old` and `new` in buffers and compares them.
+    fn diff_files(&mut self, old: &Path, new: &Path) -> io::Result<()> {
+        let old = self.open_buffer(old)?;
+        let new = self.open_buffer(new)?;
+        self.views.follow_active_buffer(&self.buffers);
+        let side = |idx| {
+            let buffer = self.buffers.get(idx)?;
+            let path = buffer.path()?.to_path_buf();
+            let (title, version) = (buffer.title(), buffer.version());
+            Some(DiffSide::buffer(title, buffer.lines(), path, version))
+        };
+        if let (Some(left), Some(right)) = (side(old), side(new)) {
+            self.open_diff(left, right);
+        }
+        Ok(())
+    }
+
+    fn open_diff(&mut self, left: DiffSide, right: DiffSide) {
+        self.diff = Some(DiffView::new(left, right, self.diff_layout));
+        self.focus.focus(PaneId::Content(self.views.active()));
+    }
+
+    /// Hands buffers what `do` and `dp` changed in them, and the diff what
+    /// was typed into them since.
+    fn sync_diff(&mut self) {
+        let Some(diff) = &mut self.diff else {
+            return;
+        };
+        for (path, lines) in diff.take_edits() {
+            let idx = self.buffers.position(&path);
+            if let Some(buffer) = idx.and_then(|idx| self.buffers.get_mut(idx))
+            {
+                buffer.edit_lines(&lines);
+            }
+        }
+        let shown: Vec<(PathBuf, u64)> = diff
+            .buffers()
+            .map(|(path, version)| (path.to_path_buf(), version))
+            .collect();
+        for (path, version) in shown {
+            let idx = self.buffers.position(&path);
+            if let Some(buffer) = idx.and_then(|idx| self.buffers.get(idx))
+                && buffer.version() != version
+            {
+                diff.refresh(&path, buffer.version(), &buffer.lines());
+            }
+        }
+    }
 
-        let event = ratatui::crossterm::event::read()?;
+    /// Renames the symbol under the cursor throughout the workspace.
+    fn rename(&mut self, new_name: &str) {
+        let buffer = self.buffers.active();
+        let Some(path) = buffer.path() else {
+            notify::warn("the buffer has no file to rename in");
+            return;
+        };
+        if !self.lsp.rename(&self.buffers, path, buffer.cursor(), new_name) {
+            notify::warn(format!("{}: no language server", path.display()));
+        }
+    }
+
+    /// Formats the visual selection, or the whole buffer without one.
+    fn format(&mut self) {
+        let buffer = self.buffers.active();
+        let Some(path) = buffer.path() else {
+            return;
+        };
+        let selection = buffer.selection();
+        if !self.lsp.format(&self.buffers, path, selection) {
+            notify::warn(format!("{}: no language server", path.display()));
+        }
+        if selection.is_some() {
+            self.buffers.active_mut().run_action("normal_mode");
+        }
+    }
+
+    /// Asks what can be done over the visual selection, or at the cursor.
+    fn request_code_actions(&mut self) {
+        let buffer = self.buffers.active();
+        let Some(path) = buffer.path() else {
+            return;
+        };
+        let cursor = buffer.cursor();
+        let range = buffer.selection().unwrap_or((cursor, cursor));
+        if !self.lsp.code_actions(&self.buffers, path, range) {
+            notify::warn(format!("{}: no language server", path.display()));
+        }
+    }
+
+    fn show_code_actions(&mut self, actions: Vec<CodeAction>) {
+        if actions.is_empty() {
+            notify::info("no code actions here");
+            return;
+        }
+        let buffer = self.buffers.active();
+        let anchor = match buffer.screen_position(buffer.cursor()) {
+            Some(position) => Anchor::Cursor(position),
+            None => Anchor::Screen,
+        };
+        let menu = CodeActionsMenu::new(actions);
+        let (width, height) = menu.size();
+        self.close_popup(Popup::CodeActions);
+        self.code_actions = Some(menu);
+        self.layers.push(
+            Layer::new(Popup::CodeActions, anchor, width, height).modal(),
+        );
+    }
+
+    fn answer_code_actions(&mut self, event: &Event) -> io::Result<()> {
+        let Some(menu) = &mut self.code_actions else {
+            return Ok(());
+        };
+        match menu.handle_event(event) {
+            CodeActionsEvent::None => {}
+            CodeActionsEvent::Close => self.close_popup(Popup::CodeActions),
+            CodeActionsEvent::Run(action) => {
+                self.close_popup(Popup::CodeActions);
+                if let Some(edit) = self.lsp.run_code_action(&action)
+                    && let Err(e) = self.apply_edit(&action.title, &edit)
+                {
+                    notify::error(format!("{}: {e}", action.title));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies edits from a language server, each buffer's as one undo
+    /// step. Files that aren't open are opened, and left unsaved for a
+    /// look before `:wa`. Nothing is applied if a buffer was edited since
+    /// the edits were made.
+    fn apply_edit(
+        &mut self,
+        label: &str,
+        edit: &WorkspaceEdit,
+    ) -> io::Result<()> {
+        for (path, version) in &edit.versions {
+            let idx = self.buffers.position(path);
+            if let Some(buffer) = idx.and_then(|idx| self.buffers.get(idx))
+                && buffer.version() != *version
+            {
+                return Err(io::Error::other(format!(
+                    "{} changed since the edit was made",
+                    path.display()
+                )));
+            }
+        }
+        let active = self.buffers.active_index();
+        let mut opened = 0;
+        for (path, edits) in &edit.files {
+            if edits.is_empty() {
+                continue;
+            }
+            let idx = match self.buffers.position(path) {
+                Some(idx) => idx,
+                None => {
+                    opened += 1;
+                    self.open_buffer(path)?
+                }
+            };
+            if let Some(buffer) = self.buffers.get_mut(idx) {
+                buffer.apply_edits(edits);
+            }
+        }
+        self.buffers.select(active);
+        self.views.follow_active_buffer(&self.buffers);
+        if edit.skipped > 0 {
+            notify::warn(format!(
+                "{label}: left out {} file operations",
+                edit.skipped
+            ));
+        }
+        let files = edit.files.values().filter(|e| !e.is_empty()).count();
+        if files > 1 || opened > 0 {
+            let mut message =
+                format!("{label}: {} changes in {files} files", edit.len());
+            if opened > 0 {
+                message += &format!(", {opened} opened unsaved");
+            }
+            notify::info(message);
+        }
+        Ok(())
+    }
+
+    /// Where the cursor is, to come back to later.
+    fn here(&self) -> Option<Jump> {
+        let buffer = self.buffers.active();
+        let path = buffer.path()?.to_path_buf();
+        Some(Jump { path, cursor: buffer.cursor() })
+    }
+
+    /// Opens `path` at `position`, remembering where the cursor was on
+    /// the jump list.
+    fn jump_to(
+        &mut self,
+        path: &Path,
+        position: (usize, usize),
+    ) -> io::Result<()> {
+        let from = self.here();
+        self.go_to(path, position)?;
+        if let Some(from) = from {
+            self.jumps.push(from);
+        }
+        Ok(())
+    }
+
+    fn go_to(
+        &mut self,
+        path: &Path,
+        position: (usize, usize),
+    ) -> io::Result<()> {
+        self.open_buffer(path)?;
+        self.buffers.active_mut().set_cursor(position);
+        self.views.follow_active_buffer(&self.buffers);
+        self.focus.focus(PaneId::Content(self.views.active()));
+        Ok(())
+    }
+
+    fn jump_back(&mut self) -> io::Result<()> {
+        let Some(here) = self.here() else {
+            return Ok(());
+        };
+        match self.jumps.back(here) {
+            Some(jump) => self.go_to(&jump.path, jump.cursor),
+            None => Ok(()),
+        }
+    }
+
+    fn jump_forward(&mut self) -> io::Result<()> {
+        let Some(here) = self.here() else {
+            return Ok(());
+        };
+        match self.jumps.forward(here) {
+            Some(jump) => self.go_to(&jump.path, jump.cursor),
+            None => Ok(()),
+        }
+    }
+
+    fn scroll_messages(&mut self, event: &Event) {
+        let Event::Key(key) = event else {
+            return;
+        };
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        let max = self.notifications.history().len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.messages_scroll = (self.messages_scroll + 1).min(max)
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.messages_scroll = self.messages_scroll.saturating_sub(1)
+            }
+            KeyCode::Char('q') | KeyCode::Enter => {
+                self.layers.retain(|p| *p != Popup::Messages)
+            }
+            _ => {}
+        }
+    }
 
-        match event {
+    fn answer_popup(&mut self, event: &Event) -> io::Result<Flow> {
+        match self.layers.top().map(|layer| layer.content) {
+            Some(Popup::UnsavedGuard) => self.answer_unsaved_guard(event),
+            Some(Popup::SwapNotice) => {
+                self.answer_swap_notice(event)?;
+                Ok(Flow::Continue)
+            }
+            Some(Popup::Messages) => {
+                self.scroll_messages(event);
+                Ok(Flow::Continue)
+            }
+            Some(Popup::Problems) => {
+                self.answer_problems(event)?;
+                Ok(Flow::Continue)
+            }
+            Some(Popup::Locations) => {
+                self.answer_locations(event)?;
+                Ok(Flow::Continue)
+            }
+            Some(Popup::CodeActions) => {
+                self.answer_code_actions(event)?;
+                Ok(Flow::Continue)
+            }
+            Some(Popup::Hover) => {
+                if self.hover.as_mut().is_some_and(|h| h.handle_event(event)) {
+                    self.close_popup(Popup::Hover);
+                }
+                Ok(Flow::Continue)
+            }
+            Some(Popup::HunkPreview) => {
+                if self
+                    .hunk_preview
+                    .as_mut()
+                    .is_some_and(|p| p.handle_event(event))
+                {
+                    self.close_popup(Popup::HunkPreview);
+                }
+                Ok(Flow::Continue)
+            }
+            // Never modal, so it doesn't capture events.
+            Some(Popup::Completion) | None => Ok(Flow::Continue),
+        }
+    }
+
+    fn dismiss_popup(&mut self, popup: Popup) {
+        match popup {
+            Popup::UnsavedGuard => self.unsaved_guard = None,
+            // Left alone, the swap file is offered again next time.
+            Popup::SwapNotice if !self.swap_notices.is_empty() => {
+                self.swap_notices.remove(0);
+            }
+            Popup::Completion => self.completion = None,
+            Popup::Locations => self.locations = None,
+            Popup::Hover => self.hover = None,
+            Popup::HunkPreview => self.hunk_preview = None,
+            Popup::CodeActions => self.code_actions = None,
+            Popup::SwapNotice | Popup::Messages | Popup::Problems => {}
+        }
+    }
+
+    fn answer_swap_notice(&mut self, event: &Event) -> io::Result<()> {
+        let Some(notice) = self.swap_notices.first() else {
+            return Ok(());
+        };
+        let choice = match notice.prompt().handle_event(event) {
+            PromptEvent::Choose(choice) => choice,
+            PromptEvent::Cancel => {
+                self.swap_notices.remove(0);
+                return Ok(());
+            }
+            PromptEvent::None => return Ok(()),
+        };
+        let path = notice.path().to_path_buf();
+        let buffer = self.buffers.position(&path);
+        // A diff of the swapped text is out of date once it is answered.
+        if let Some(diff) = &self.diff
+            && diff.buffers().any(|(p, _)| self.buffers.position(p) == buffer)
+        {
+            self.diff = None;
+        }
+        match (self.swap_notices.remove(0), choice) {
+            (SwapNotice::Recover { lines, .. }, 'r') => {
+                if let Some(editor) =
+                    buffer.and_then(|b| self.buffers.get_mut(b))
+                {
+                    editor.set_lines(&lines);
+                }
+                self.swaps.claim(&path)?;
+            }
+            (SwapNotice::Recover { lines, .. }, 'd') => {
+                // Compare the file with the swapped text, then ask again.
+                if let Some(idx) = buffer
+                    && let Some(editor) = self.buffers.get(idx)
+                    && let Some(file) = editor.path().map(Path::to_path_buf)
+                {
+                    let title = editor.title();
+                    let left = DiffSide::buffer(
+                        title.clone(),
+                        editor.lines(),
+                        file,
+                        editor.version(),
+                    );
+                    let swapped = format!("{title} in the swap file");
+                    let right = DiffSide::fixed(swapped, lines.clone());
+                    self.show_buffer(idx);
+                    self.open_diff(left, right);
+                }
+                self.swap_notices
+                    .insert(0, SwapNotice::Recover { path, lines });
+            }
+            (SwapNotice::Recover { .. }, _) => {
+                swap::discard(&path)?;
+                self.swaps.claim(&path)?;
+            }
+            (SwapNotice::InUse { .. }, _) => {}
+        }
+        Ok(())
+    }
+
+    fn split(&mut self, direction: SplitDirection) {
+        if let Some(view) = self.views.split(direction) {
+            self.focus.focus(PaneId::Content(view));
+            self.views.activate(view, &mut self.buffers);
+        }
+    }
+
+    /// Resizes whatever has focus: the explorer's aside or the active view.
+    fn resize(&mut self, direction: SplitDirection, delta: i32) {
+        match (self.focus.focused(), direction) {
+            (PaneId::AsideLeft, SplitDirection::Vertical) => {
+                self.layout_manager.resize_left_aside(delta)
+            }
+            (PaneId::AsideRight, SplitDirection::Vertical) => {
+                self.layout_manager.resize_right_aside(delta)
+            }
+            (PaneId::Content(_), _) => {
+                let content = self.layout_manager.build(self.area).content;
+                self.views.resize(content, direction, delta);
+            }
+            _ => {}
+        }
+    }
+
+    fn close_view(&mut self) {
+        if let Some(view) = self.views.close(&mut self.buffers) {
+            self.focus.focus(PaneId::Content(view));
+        }
+    }
+
+    fn session(&self) -> Session {
+        // Without hot exit only named buffers are kept, and views showing
+        // the others fall back to the first saved buffer.
+        let mut index = vec![None; self.buffers.len()];
+        let mut buffers = vec![];
+        for (idx, buffer) in self.buffers.iter().enumerate() {
+            let mut snapshot = buffer.snapshot();
+            if !self.hot_exit {
+                snapshot.lines = None;
+            }
+            if snapshot.path.is_some() || snapshot.lines.is_some() {
+                index[idx] = Some(buffers.len());
+                buffers.push(snapshot);
+            }
+        }
+        let map = |b: usize| index.get(b).copied().flatten().unwrap_or(0);
+        let (views, active_view) = self.views.to_layout();
+        Session {
+            active_buffer: map(self.buffers.active_index()),
+            active_view,
+            buffers,
+            views: Some(map_views(views, &map)),
+            asides: Some(self.layout_manager.sizes()),
+            explorer: self.fs_explorer.session(),
+        }
+    }
+
+    fn restore_session(&mut self, session: Session) {
+        let mut index = vec![];
+        for saved in session.buffers {
+            let opened = match &saved.path {
+                Some(path) if path.exists() || saved.lines.is_some() => {
+                    match self.open_buffer(path) {
+                        Ok(buffer) => Some(buffer),
+                        // The unsaved text is all that is left of it, so
+                        // keep it rather than lose it with the file.
+                        Err(e) if saved.lines.is_some() => {
+                            notify::warn(format!(
+                                "{}: {e}; its unsaved text is in a new buffer",
+                                path.display()
+                            ));
+                            Some(self.buffers.push(VimEditor::new()))
+                        }
+                        Err(e) => {
+                            notify::warn(format!("{}: {e}", path.display()));
+                            None
+                        }
+                    }
+                }
+                Some(_) => None,
+                None => Some(self.buffers.push(VimEditor::new())),
+            };
+            if let Some(buffer) = opened.and_then(|b| self.buffers.get_mut(b)) {
+                buffer.restore(&saved);
+            }
+            index.push(opened);
+        }
+        let map = |b: usize| index.get(b).copied().flatten().unwrap_or(0);
+        self.buffers.select(map(session.active_buffer));
+        if let Some(layout) = session.views {
+            self.views = Views::restore(
+                map_views(layout, &map),
+                session.active_view,
+                &mut self.buffers,
+            );
+            self.focus.focus(PaneId::Content(self.views.active()));
+        }
+        if let Some(sizes) = session.asides {
+            self.layout_manager.restore_sizes(sizes);
+        }
+        self.fs_explorer.restore_session(session.explorer);
+    }
+
+    fn handle_event(&mut self, event: Event) -> io::Result<Flow> {
+        // Mouse events only count once something reacts to them: most are
+        // bare motion, which would otherwise redraw on every cell crossed.
+        self.dirty |= match &event {
+            Event::Key(key) => key.kind != KeyEventKind::Release,
+            Event::Paste(_) | Event::Resize(_, _) => true,
+            Event::Mouse(_) | Event::FocusGained | Event::FocusLost => false,
+        };
+        // Whatever happened meanwhile may have been a commit.
+        if event == Event::FocusGained {
+            self.reload_git();
+        }
+        Ok(match self.layers.handle_event(&event) {
+            LayerEvent::Dismissed(popup) => {
+                self.dirty = true;
+                self.dismiss_popup(popup);
+                Flow::Continue
+            }
+            LayerEvent::Captured => {
+                self.dirty = true;
+                self.answer_popup(&event)?
+            }
+            LayerEvent::DismissedAndForwarded(popup) => {
+                self.dirty = true;
+                self.dismiss_popup(popup);
+                self.handle_pane_event(event)?
+            }
+            LayerEvent::Ignored => self.handle_pane_event(event)?,
+        })
+    }
+
+    /// Events no layer took: the command line, completion menu, keymap and
+    /// panes get them in that order.
+    fn handle_pane_event(&mut self, event: Event) -> io::Result<Flow> {
+        Ok(match event {
             Event::Key(key) if !matches!(key.kind, KeyEventKind::Press) => {
-                ();
+                Flow::Continue
             }
-            Event::Key(KeyEvent { code, modifiers, .. }) => {
-                match (code, modifiers) {
-                    (KeyCode::F(1), _) => {
-                        focus_editor = !focus_editor;
+            Event::Key(_) if self.command_line.is_active() => {
+                match self.command_line.handle_event(&event) {
+                    CommandLineEvent::Submit(command) => {
+                        self.run_command(command)?
+                    }
+                    CommandLineEvent::Cancel | CommandLineEvent::None => {
+                        Flow::Continue
                     }
-                    (KeyCode::Char('q'), KeyModifiers::CONTROL) => {
-                        break;
+                }
+            }
+            Event::Key(key)
+                if self.completion.as_ref().is_some_and(|m| !m.is_empty())
+                    && menu_key(&key).is_some() =>
+            {
+                if let Some(menu_key) = menu_key(&key) {
+                    self.completion_key(menu_key);
+                }
+                Flow::Continue
+            }
+            Event::Key(key) => {
+                let contexts = [
+                    self.focused_component().key_context(),
+                    KeyContext::Global,
+                ];
+                // Partway through a sequence of its own, e.g. `ci[`, the
+                // pane gets the key even where the keymap has a prefix.
+                let resolved = if self.key_resolver.pending().is_empty()
+                    && self.focused_component().has_pending_keys()
+                {
+                    KeymapResult::Unmatched(vec![KeyPress::from(key)])
+                } else {
+                    self.key_resolver.feed(
+                        &self.keymap,
+                        &contexts,
+                        KeyPress::from(key),
+                    )
+                };
+                match resolved {
+                    KeymapResult::Action(action) => {
+                        self.run_global_action(&action)?
                     }
-                    _ => {
-                        if focus_editor {
-                            vim_editor.handle_events(event);
-                        } else {
-                            fs_explorer.handle_event(event);
+                    KeymapResult::Pending => Flow::Continue,
+                    KeymapResult::Unmatched(keys) => {
+                        // Keys the keymap doesn't know fall through to the
+                        // focused pane, e.g. the editor's own Vim handling.
+                        let mut flow = Flow::Continue;
+                        for key in keys {
+                            self.typed = match key.code {
+                                KeyCode::Char(c)
+                                    if !key.modifiers.intersects(
+                                        KeyModifiers::CONTROL
+                                            | KeyModifiers::ALT,
+                                    ) =>
+                                {
+                                    Some(c)
+                                }
+                                _ => None,
+                            };
+                            let event = Event::Key(key.into());
+                            if let EventResult::Action(action) =
+                                self.focused_component().handle_event(&event)
+                            {
+                                flow = self.run_global_action(&action)?;
+                            }
                         }
+                        flow
                     }
                 }
             }
-            _ => {}
+            Event::Mouse(_)
+                if self.resize.handle_event(
+                    &event,
+                    &self.layout_manager.build(self.area),
+                    &mut self.layout_manager,
+                    self.views.splits_mut(),
+                ) =>
+            {
+                self.dirty = true;
+                Flow::Continue
+            }
+            Event::Mouse(mouse)
+                if self.tab_bar.contains(mouse.column, mouse.row) =>
+            {
+                let action = self.tab_bar.handle_event(&event);
+                self.dirty |= action.is_some();
+                match action {
+                    Some(TabBarAction::Select(idx)) => self.buffers.select(idx),
+                    Some(TabBarAction::Close(idx)) => {
+                        self.guard(GuardTarget::CloseBuffer(idx))?;
+                    }
+                    Some(TabBarAction::Move { from, to }) => {
+                        self.buffers.move_buffer(from, to);
+                        self.views.buffer_moved(from, to);
+                    }
+                    None => {}
+                }
+                Flow::Continue
+            }
+            Event::Mouse(mouse) => {
+                let focused = self.focus.focused();
+                self.focus.handle_event(&event);
+                self.sync_views();
+                let result = self
+                    .focus
+                    .pane_at(mouse.column, mouse.row)
+                    .and_then(|pane| self.pane_component(pane))
+                    .map(|component| component.handle_event(&event));
+                self.dirty |= self.focus.focused() != focused
+                    || !matches!(result, None | Some(EventResult::Ignored));
+                match result {
+                    Some(EventResult::Action(action)) => {
+                        self.run_global_action(&action)?
+                    }
+                    _ => Flow::Continue,
+                }
+            }
+            _ => Flow::Continue,
+        })
+    }
+
+    /// The component drawn in `pane`, if any.
+    fn pane_component(&mut self, pane: PaneId) -> Option<&mut dyn Component> {
+        match pane {
+            PaneId::AsideLeft => Some(&mut self.fs_explorer),
+            PaneId::Content(_) if self.diff.is_some() => {
+                self.diff.as_mut().map(|diff| diff as &mut dyn Component)
+            }
+            PaneId::Content(view) => {
+                let buffer = self.views.buffer_of(view)?;
+                let editor = self.buffers.get_mut(buffer)?;
+                Some(editor)
+            }
+            PaneId::AsideRight => None,
+        }
+    }
+
+    /// Where keys go: the explorer when it has focus, the diff or the
+    /// active buffer otherwise.
+    fn focused_component(&mut self) -> &mut dyn Component {
+        match (self.focus.focused(), &mut self.diff) {
+            (PaneId::AsideLeft, _) => &mut self.fs_explorer,
+            (_, Some(diff)) => diff,
+            _ => self.buffers.active_mut(),
         }
     }
 
+    /// Delivers what components published since the last call. Runs once
+    /// per event, after it is handled and before the next frame is drawn.
+    fn dispatch_messages(&mut self) {
+        let mut bus = std::mem::take(&mut self.bus);
+        self.dirty |= bus.dispatch(|subscriber, message| match subscriber {
+            Subscriber::App => self.on_message(message),
+            Subscriber::Explorer => self.fs_explorer.on_message(message),
+        });
+        self.bus = bus;
+    }
+
+    /// Handles whatever woke the loop up.
+    fn handle_loop_event(&mut self, event: LoopEvent) -> Flow {
+        let flow = match event {
+            LoopEvent::Terminal(event) => match self.handle_event(event) {
+                Ok(flow) => flow,
+                Err(e) => {
+                    notify::error(e.to_string());
+                    Flow::Continue
+                }
+            },
+            LoopEvent::Task(event) => {
+                self.task_event(&event);
+                self.dirty = true;
+                Flow::Continue
+            }
+            LoopEvent::Wake => {
+                for event in self.lsp.poll() {
+                    match event {
+                        LspEvent::Diagnostics => self.apply_diagnostics(),
+                        LspEvent::Completion { path, row, items } => {
+                            self.completion_arrived(&path, row, items)
+                        }
+                        LspEvent::Locations { kind, locations } => {
+                            if let Err(e) = self.show_locations(kind, locations)
+                            {
+                                notify::error(e.to_string());
+                            }
+                        }
+                        LspEvent::Hover { path, position, markdown } => {
+                            self.show_hover(path, position, markdown)
+                        }
+                        LspEvent::Edit { label, edit, request } => {
+                            let result = self.apply_edit(&label, &edit);
+                            if let Err(e) = &result {
+                                notify::error(format!("{label}: {e}"));
+                            }
+                            if let Some(request) = request {
+                                let result = result.map_err(|e| e.to_string());
+                                self.lsp.edit_applied(
+                                    &self.buffers,
+                                    request,
+                                    result,
+                                );
+                            }
+                        }
+                        LspEvent::CodeActions(actions) => {
+                            self.show_code_actions(actions)
+                        }
+                        LspEvent::FoldingRanges { path, folds } => {
+                            let idx = self.buffers.position(&path);
+                            if let Some(buffer) =
+                                idx.and_then(|idx| self.buffers.get_mut(idx))
+                            {
+                                buffer.set_lsp_folds(folds);
+                            }
+                        }
+                    }
+                    self.dirty = true;
+                }
+                Flow::Continue
+            }
+            // Toasts expire and swap files are written at the top of the
+            // loop; a held back frame is drawn there too.
+            LoopEvent::Tick | LoopEvent::Timer(_) => Flow::Continue,
+        };
+        self.dispatch_messages();
+        self.sync_views();
+        self.update_completion();
+        self.sync_hover();
+        self.sync_hunk_preview();
+        self.sync_diff();
+        flow
+    }
+
+    /// Opens completion for the word at the cursor, if the active buffer
+    /// is being typed into. Language servers answer later, through
+    /// `completion_arrived`.
+    fn open_completion(&mut self) {
+        let buffer = self.buffers.active();
+        if !matches!(self.focus.focused(), PaneId::Content(_))
+            || !buffer.is_inserting()
+        {
+            return;
+        }
+        let (row, col) = buffer.cursor();
+        let line = buffer.line(row).unwrap_or_default();
+        let start = completion::word_start(&line, col);
+        let texts: Vec<Vec<String>> =
+            self.buffers.iter().map(|buffer| buffer.lines()).collect();
+        let base = match buffer.path().and_then(Path::parent) {
+            Some(dir) => dir.to_path_buf(),
+            None => self.lsp.root().to_path_buf(),
+        };
+        let mut menu = Completion::new(row);
+        menu.add(completion::words(texts.iter().map(Vec::as_slice), start));
+        menu.add(completion::paths(&line, col, &base));
+        menu.add(self.snippets.items(buffer.path(), start));
+        menu.update(&line, col);
+        self.completion_pending = buffer.path().is_some_and(|path| {
+            self.lsp.completion(&self.buffers, path, (row, col))
+        });
+        self.completion = Some(menu);
+        self.sync_completion();
+    }
+
+    /// Adds what a language server offers, if the menu it was asked for
+    /// is still open.
+    fn completion_arrived(
+        &mut self,
+        path: &Path,
+        row: usize,
+        items: Vec<CompletionItem>,
+    ) {
+        let active = self.buffers.active().path().map(std::path::absolute);
+        let Some(menu) = &mut self.completion else {
+            return;
+        };
+        if menu.row != row || !matches!(active, Some(Ok(p)) if p == path) {
+            return;
+        }
+        menu.add(items);
+        self.completion_pending = false;
+        self.sync_completion();
+    }
+
+    /// Keeps completion in step with the active buffer after an event:
+    /// filters the menu as the word grows, closes it once the cursor
+    /// leaves the row or insert mode, and opens it as words are typed.
+    fn update_completion(&mut self) {
+        let typed = self.typed.take();
+        let buffer = self.buffers.active();
+        let editing = matches!(self.focus.focused(), PaneId::Content(_))
+            && buffer.is_inserting();
+        let (row, col) = buffer.cursor();
+        let line = buffer.line(row).unwrap_or_default();
+        if let Some(menu) = &mut self.completion {
+            match editing && menu.row == row {
+                true => menu.update(&line, col),
+                false => self.completion_pending = false,
+            }
+            if !editing || menu.row != row {
+                self.completion = None;
+            }
+        }
+        if self.completion.is_none()
+            && editing
+            && let Some(c) = typed
+        {
+            let prefix = col - completion::word_start(&line, col);
+            if prefix >= COMPLETION_PREFIX || COMPLETION_TRIGGERS.contains(&c) {
+                self.open_completion();
+            }
+        }
+        self.sync_completion();
+    }
+
+    /// Shows the completion layer under the word being completed, or
+    /// drops the menu once nothing in it matches.
+    fn sync_completion(&mut self) {
+        self.layers.retain(|p| *p != Popup::Completion);
+        let Some(menu) = &self.completion else {
+            return;
+        };
+        if menu.is_empty() {
+            if !self.completion_pending {
+                self.completion = None;
+            }
+            return;
+        }
+        let buffer = self.buffers.active();
+        let start = menu.start().unwrap_or(buffer.cursor().1);
+        let Some(position) = buffer.screen_position((menu.row, start)) else {
+            return;
+        };
+        // Lines the labels up with the text, inside the border.
+        let position = Position::new(position.x.saturating_sub(1), position.y);
+        let (width, height) = menu.size();
+        self.layers.push(Layer::new(
+            Popup::Completion,
+            Anchor::Cursor(position),
+            width,
+            height,
+        ));
+    }
+
+    fn completion_key(&mut self, key: MenuKey) {
+        let Some(menu) = &mut self.completion else {
+            return;
+        };
+        match key {
+            MenuKey::Next => menu.select_next(),
+            MenuKey::Prev => menu.select_prev(),
+            MenuKey::Accept => {
+                if let Some(accepted) = menu.accept() {
+                    let buffer = self.buffers.active_mut();
+                    buffer.apply_edits(&accepted.edits);
+                    buffer.set_cursor(accepted.cursor);
+                }
+                self.completion = None;
+                self.completion_pending = false;
+            }
+        }
+    }
+
+    /// Keeps the task list current and reports tasks that failed.
+    fn task_event(&mut self, event: &TaskEvent) {
+        let Some(task) = self.tasks.update(event) else {
+            return;
+        };
+        let TaskEvent::Finished { id, outcome } = event else {
+            return;
+        };
+        if let TaskOutcome::Failed(e) = outcome {
+            notify::error(format!("{}: {e}", task.label));
+        }
+        if let Some((_, reloaded)) =
+            self.git_reload.take_if(|(task, _)| task == id)
+            && *outcome == TaskOutcome::Done
+        {
+            for (path, lines) in reloaded.try_iter() {
+                self.buffers.set_git_lines(&path, lines);
+            }
+        }
+    }
+
+    /// Has every buffer read its git version again, after commits or
+    /// staging that may have happened outside ted. That is a git run per
+    /// file, so it happens on a worker and the buffers catch up once it is
+    /// done; a newer reload cancels one still running.
+    fn reload_git(&mut self) {
+        if let Some((task, _)) = self.git_reload.take() {
+            self.tasks.cancel(task);
+        }
+        let paths: Vec<PathBuf> = self
+            .buffers
+            .iter()
+            .filter_map(|buffer| buffer.path().map(Path::to_path_buf))
+            .collect();
+        if paths.is_empty() {
+            return;
+        }
+        let base = self.buffers.git_base();
+        let (sender, reloaded) = mpsc::channel();
+        let task = self.tasks.spawn("git", move |ctx| {
+            let total = paths.len() as u64;
+            for (done, path) in paths.into_iter().enumerate() {
+                if ctx.is_cancelled() {
+                    break;
+                }
+                ctx.progress(done as u64, Some(total));
+                let lines = git::base_lines(&path, base);
+                sender.send((path, lines)).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        });
+        self.git_reload = Some((task, reloaded));
+    }
+
+    /// Hands each buffer what the language servers reported for it.
+    fn apply_diagnostics(&mut self) {
+        for idx in 0..self.buffers.len() {
+            if let Some(buffer) = self.buffers.get_mut(idx) {
+                let diagnostics = buffer
+                    .path()
+                    .map(|path| self.lsp.diagnostics(path).to_vec())
+                    .unwrap_or_default();
+                buffer.set_diagnostics(diagnostics);
+            }
+        }
+    }
+
+    fn on_message(&mut self, message: &Message) {
+        match message {
+            Message::OpenFile(path) => match self.open_buffer(path) {
+                Ok(_) => {
+                    self.diff = None;
+                    self.views.follow_active_buffer(&self.buffers);
+                    self.focus.focus(PaneId::Content(self.views.active()));
+                }
+                Err(e) => notify::error(format!("{}: {e}", path.display())),
+            },
+            Message::FileSaved(path) => self.lsp.did_save(path),
+            Message::DiffFiles(old, new) => {
+                if let Err(e) = self.diff_files(old, new) {
+                    notify::error(e.to_string());
+                }
+            }
+        }
+    }
+
+    /// Keeps the active view in step with focus and the active buffer.
+    fn sync_views(&mut self) {
+        match self.focus.focused() {
+            PaneId::Content(view) if view != self.views.active() => {
+                self.views.activate(view, &mut self.buffers)
+            }
+            _ => self.views.follow_active_buffer(&self.buffers),
+        }
+    }
+}
+
+/// Keys the completion menu takes before the keymap sees them.
+#[derive(Debug, Clone, Copy)]
+enum MenuKey {
+    Next,
+    Prev,
+    Accept,
+}
+
+fn menu_key(key: &KeyEvent) -> Option<MenuKey> {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Tab | KeyCode::Down => Some(MenuKey::Next),
+        KeyCode::Char('n') if ctrl => Some(MenuKey::Next),
+        KeyCode::BackTab | KeyCode::Up => Some(MenuKey::Prev),
+        KeyCode::Char('p') if ctrl => Some(MenuKey::Prev),
+        KeyCode::Enter => Some(MenuKey::Accept),
+        KeyCode::Char('y') if ctrl => Some(MenuKey::Accept),
+        _ => None,
+    }
+}
+
+/// Startup problems shouldn't keep ted from opening; they are reported and
+/// the defaults are used instead.
+fn or_notify<T: Default>(result: io::Result<T>, what: &str) -> T {
+    result.unwrap_or_else(|e| {
+        notify::error(format!("{what}: {e}"));
+        T::default()
+    })
+}
+
+/// Draws the whole UI.
+fn draw(
+    f: &mut Frame,
+    app: &mut App,
+    status_line: &StatusLine,
+    icons: &FsIconManager,
+) {
+    // render whole UI with black
+    f.render_widget(
+        Block::default().style(Style::default().bg(Color::Black)),
+        f.area(),
+    );
+    // render widgets.
+    app.area = f.area();
+    let ted_layout = app.layout_manager.build(f.area());
+    let view_rects = match &app.diff {
+        // The diff takes the place of every view while it is open.
+        Some(_) => vec![(app.views.active(), ted_layout.content)],
+        None => app.views.layout(ted_layout.content),
+    };
+    app.focus.set_panes(
+        std::iter::once((PaneId::AsideLeft, ted_layout.aside_left)).chain(
+            view_rects.iter().map(|(id, rect)| (PaneId::Content(*id), *rect)),
+        ),
+    );
+    app.fs_explorer.set_focused(app.focus.is_focused(PaneId::AsideLeft));
+    let tabs: Vec<TabInfo> = app
+        .buffers
+        .iter()
+        .map(|buffer| TabInfo {
+            title: buffer.title(),
+            icon: buffer.path().and_then(|p| icons.get_icon(p)),
+            dirty: buffer.is_dirty(),
+        })
+        .collect();
+    app.tab_bar.draw(
+        f,
+        ted_layout.header_left,
+        ted_layout.header_right,
+        &tabs,
+        app.buffers.active_index(),
+    );
+    if let Some(diff) = &mut app.diff {
+        diff.set_focused(!app.focus.is_focused(PaneId::AsideLeft));
+        diff.render(f, ted_layout.content);
+    }
+    for (view, rect) in view_rects.iter().filter(|_| app.diff.is_none()) {
+        let focused = app.focus.is_focused(PaneId::Content(*view));
+        let buffer = app.views.buffer_of(*view);
+        if let Some(editor) = buffer.and_then(|b| app.buffers.get_mut(b)) {
+            editor.set_focused(focused);
+            Component::render(editor, f, *rect);
+        }
+    }
+    app.fs_explorer.render(f, ted_layout.aside_left);
+    let editor_status = app.buffers.active().status();
+    let focused_pane = app.focused_component().id().to_string();
+    let status_ctx = StatusContext {
+        editor: &editor_status,
+        focused_pane: &focused_pane,
+        tasks: app.tasks.tasks(),
+    };
+    status_line.draw(
+        f,
+        ted_layout.footer_left,
+        ted_layout.footer_right,
+        &status_ctx,
+    );
+    if app.command_line.is_active() {
+        app.command_line.draw(f, ted_layout.footer_left);
+    }
+    app.layers.render(
+        f,
+        |pane| app.focus.rect_of(pane),
+        |f, popup, area| app.draw_popup(f, popup, area),
+    );
+    app.notifications.draw_toasts(f, ted_layout.content);
+}
+
+fn main() -> io::Result<()> {
+    let config = or_notify(Config::load(), "config.toml");
+    let status_line = StatusLine::new(or_notify(
+        config.section("status_line"),
+        "status_line",
+    ));
+    let keymap_config: KeymapConfig =
+        or_notify(config.section("keymap"), "keymap");
+    let session_config: SessionConfig =
+        or_notify(config.section("session"), "session");
+    let lsp_config: LspConfig = or_notify(config.section("lsp"), "lsp");
+    let completion_config: CompletionConfig =
+        or_notify(config.section("completion"), "completion");
+    let fold_config: FoldConfig =
+        or_notify(config.section("folding"), "folding");
+    let gutter_config: GutterConfig =
+        or_notify(config.section("gutter"), "gutter");
+    let git_config: GitConfig = or_notify(config.section("git"), "git");
+    let diff_config: DiffConfig = or_notify(config.section("diff"), "diff");
+    let editor_actions = [VimEditor::ACTIONS, GLOBAL_ACTIONS].concat();
+    let diff_actions = [DiffView::ACTIONS, GLOBAL_ACTIONS].concat();
+    let actions = [
+        (KeyContext::Global, GLOBAL_ACTIONS),
+        (KeyContext::Explorer, FsExplorer::ACTIONS),
+        (KeyContext::Normal, &editor_actions),
+        (KeyContext::Insert, &editor_actions),
+        (KeyContext::Visual, &editor_actions),
+        (KeyContext::Diff, &diff_actions),
+    ];
+    let keymap =
+        Keymap::load(&keymap_config, &actions).unwrap_or_else(|errors| {
+            for error in errors {
+                notify::error(format!("keymap: {error}"));
+            }
+            Keymap::load(&KeymapConfig::default(), &actions)
+                .expect("the default keymap is valid")
+        });
+
+    let mut term = ratatui::init();
+    execute!(io::stdout(), EnableMouseCapture, EnableFocusChange)?;
+    let mut events = EventLoop::new(SwapFiles::INTERVAL);
+    events.spawn_terminal_reader();
+
+    let mut layout_manager = TedLayoutManager::default();
+    layout_manager.restore_sizes(AsideSizes::default());
+
+    let mut bus = Bus::new();
+    bus.subscribe(
+        Subscriber::App,
+        &[Topic::OpenFile, Topic::FileSaved, Topic::DiffFiles],
+    );
+    bus.subscribe(Subscriber::Explorer, &[Topic::FileSaved]);
+
+    let workspace = env::current_dir()?;
+    let fs_explorer = or_notify(
+        FsExplorer::new(workspace.clone(), bus.publisher()),
+        "explorer",
+    );
+
+    let (flags, paths): (Vec<String>, Vec<String>) =
+        env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    let use_session = !flags.iter().any(|flag| flag == "--no-session");
+
+    // let mut vim_editor = VimLiteEditor::new();
+    let mut buffers = BufferList::new();
+    buffers.set_fold_method(fold_config.method);
+    buffers.set_gutter(gutter_config);
+    buffers.set_git_base(git_config.base);
+    buffers.set_publisher(bus.publisher());
+    let views = Views::new(&buffers);
+
+    let icons = FsIconManager::new();
+
+    let mut app = App {
+        layout_manager,
+        resize: ResizeController::default(),
+        area: Rect::default(),
+        fs_explorer,
+        buffers,
+        views,
+        focus: FocusManager::new(PaneId::Content(0)),
+        command_line: CommandLine::default(),
+        swaps: SwapFiles::default(),
+        swap_notices: vec![],
+        unsaved_guard: None,
+        layers: LayerStack::default(),
+        // Hot exit relies on the session being saved.
+        hot_exit: session_config.hot_exit && use_session,
+        keymap,
+        key_resolver: KeymapResolver::default(),
+        tab_bar: TabBar::default(),
+        notifications: Notifications::default(),
+        messages_scroll: 0,
+        bus,
+        tasks: WorkerPool::new(WORKER_THREADS, events.sender()),
+        git_reload: None,
+        lsp: Lsp::new(&lsp_config, &workspace, events.sender()),
+        problems: ProblemsView::default(),
+        locations: None,
+        hover: None,
+        hunk_preview: None,
+        diff: None,
+        diff_layout: diff_config.layout,
+        code_actions: None,
+        jumps: JumpList::default(),
+        completion: None,
+        completion_pending: false,
+        snippets: Snippets::new(&completion_config),
+        typed: None,
+        dirty: true,
+    };
+    if flags.iter().any(|flag| flag == "--trace-bus")
+        && let Some(dir) = state::state_dir()
+        && let Err(e) = app.bus.trace_to(&dir.join(BUS_TRACE))
+    {
+        notify::error(format!("{BUS_TRACE}: {e}"));
+    }
+    for path in &paths {
+        if let Err(e) = app.open_buffer(path) {
+            notify::error(format!("{path}: {e}"));
+        }
+    }
+    app.buffers.select(0);
+    app.views.follow_active_buffer(&app.buffers);
+    // Files named on the command line take the place of the last session.
+    if use_session && paths.is_empty() {
+        app.restore_session(Session::load(&workspace));
+    }
+
+    let mut last_frame: Option<Instant> = None;
+    loop {
+        app.dirty |= app.notifications.collect();
+        app.dirty |= app.notifications.expire(Instant::now());
+        match app.notifications.next_expiry(Instant::now()) {
+            Some(due) => events.set_timer(TOAST_TIMER, due),
+            None => events.cancel_timer(TOAST_TIMER),
+        }
+        app.sync_popups();
+        app.lsp.sync(&app.buffers);
+        let hold = last_frame.map_or(Duration::ZERO, |at| {
+            FRAME_INTERVAL.saturating_sub(at.elapsed())
+        });
+        if app.dirty && !hold.is_zero() {
+            events.set_timer(FRAME_TIMER, hold);
+        } else if app.dirty {
+            app.dirty = false;
+            last_frame = Some(Instant::now());
+            term.draw(|f| draw(f, &mut app, &status_line, &icons))?;
+        }
+
+        if let Err(e) = app.swaps.tick(&app.buffers) {
+            notify::warn(format!("could not write swap file: {e}"));
+        }
+        // Everything already queued is handled before the next frame, so a
+        // paste or key repeat costs one redraw instead of one per key.
+        let mut flow = app.handle_loop_event(events.wait());
+        for _ in 0..MAX_BATCH {
+            if let Flow::Quit = flow {
+                break;
+            }
+            match events.poll() {
+                Some(event) => flow = app.handle_loop_event(event),
+                None => break,
+            }
+        }
+        if let Flow::Quit = flow {
+            break;
+        }
+    }
+
+    app.tasks.cancel_all();
+    execute!(io::stdout(), DisableMouseCapture, DisableFocusChange)?;
     ratatui::restore();
+    app.lsp.shutdown();
+    // Swap files go only once the session holding their unsaved text is
+    // written.
+    if use_session {
+        app.session().save(&workspace)?;
+    }
+    app.swaps.release_all()?;
 
     Ok(())
 }
@@ -0,0 +1,171 @@
+use ratatui::prelude::*;
+
+const CODE: Style = Style::new().fg(Color::Yellow);
+const HEADING: Style =
+    Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+const RULE: Style = Style::new().fg(Color::DarkGray);
+
+/// Renders the markdown language servers write: headings, fenced code,
+/// lists, rules and inline code, emphasis and links. Runs of blank lines
+/// become one; rules are as wide as the text, up to `width`.
+pub fn render(markdown: &str, width: usize) -> Vec<Line<'static>> {
+    let mut lines: Vec<Line<'static>> = vec![];
+    let mut rules = vec![];
+    let mut in_code = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            lines.push(Line::styled(line.to_string(), CODE));
+            continue;
+        }
+        if trimmed.is_empty() {
+            if lines.last().is_some_and(|l| l.width() > 0) {
+                lines.push(Line::default());
+            }
+            continue;
+        }
+        let heading = trimmed.trim_start_matches('#');
+        let line = if heading.len() < trimmed.len() && heading.starts_with(' ')
+        {
+            Line::from(inline(heading.trim(), HEADING))
+        } else if trimmed.chars().all(|c| "-*_".contains(c))
+            && trimmed.len() >= 3
+        {
+            rules.push(lines.len());
+            Line::default()
+        } else if let Some(item) =
+            trimmed.strip_prefix("- ").or(trimmed.strip_prefix("* "))
+        {
+            let indent = " ".repeat(line.len() - trimmed.len());
+            let mut spans = vec![Span::raw(format!("{indent}• "))];
+            spans.extend(inline(item, Style::new()));
+            Line::from(spans)
+        } else {
+            Line::from(inline(line, Style::new()))
+        };
+        lines.push(line);
+    }
+    let widest = lines.iter().map(Line::width).max().unwrap_or(0);
+    for idx in rules {
+        lines[idx] = Line::styled("─".repeat(widest.min(width)), RULE);
+    }
+    while lines.last().is_some_and(|l| l.width() == 0) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Inline code, `**bold**`, `*italic*`, `[links](url)` and backslash
+/// escapes within a line.
+fn inline(text: &str, base: Style) -> Vec<Span<'static>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = vec![];
+    let mut current = String::new();
+    let mut style = base;
+    let flush =
+        |spans: &mut Vec<Span<'static>>, current: &mut String, style| {
+            if !current.is_empty() {
+                spans.push(Span::styled(std::mem::take(current), style));
+            }
+        };
+    let mut idx = 0;
+    while idx < chars.len() {
+        let next = chars.get(idx + 1).copied();
+        let closing = |from: usize, c: char| {
+            chars[from..].iter().position(|&x| x == c).map(|at| from + at)
+        };
+        match chars[idx] {
+            '\\' if next.is_some_and(|c| c.is_ascii_punctuation()) => {
+                current.extend(next);
+                idx += 2;
+            }
+            '`' if let Some(end) = closing(idx + 1, '`') => {
+                flush(&mut spans, &mut current, style);
+                let code: String = chars[idx + 1..end].iter().collect();
+                spans.push(Span::styled(code, CODE));
+                idx = end + 1;
+            }
+            c @ ('*' | '_') if next == Some(c) => {
+                flush(&mut spans, &mut current, style);
+                style = toggle(style, Modifier::BOLD);
+                idx += 2;
+            }
+            // An underscore inside a word, like in `snake_case`, is just
+            // an underscore.
+            '_' if idx > 0
+                && chars[idx - 1].is_alphanumeric()
+                && next.is_some_and(char::is_alphanumeric) =>
+            {
+                current.push('_');
+                idx += 1;
+            }
+            '*' | '_' => {
+                flush(&mut spans, &mut current, style);
+                style = toggle(style, Modifier::ITALIC);
+                idx += 1;
+            }
+            '[' if let Some(end) = closing(idx + 1, ']')
+                && chars.get(end + 1) == Some(&'(')
+                && let Some(close) = closing(end + 2, ')') =>
+            {
+                flush(&mut spans, &mut current, style);
+                let label: String = chars[idx + 1..end].iter().collect();
+                let link = style.add_modifier(Modifier::UNDERLINED);
+                spans.extend(inline(&label, link));
+                idx = close + 1;
+            }
+            c => {
+                current.push(c);
+                idx += 1;
+            }
+        }
+    }
+    flush(&mut spans, &mut current, style);
+    spans
+}
+
+fn toggle(style: Style, modifier: Modifier) -> Style {
+    match style.add_modifier.contains(modifier) {
+        true => style.remove_modifier(modifier),
+        false => style.add_modifier(modifier),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn renders_markdown() {
+        let markdown = "```rust\nfn x()\n```\n\n\n# Title\n- a `b`\n---\n\
+                        **bold** snake_case \\*not\\* [link](http://x)";
+        let lines = render(markdown, 3);
+        assert_eq!(render("a\n---\nbc", 80)[1].width(), 2);
+        let texts: Vec<String> = lines.iter().map(text).collect();
+        assert_eq!(
+            texts,
+            [
+                "fn x()",
+                "",
+                "Title",
+                "• a b",
+                "───",
+                "bold snake_case *not* link"
+            ]
+        );
+        assert_eq!(lines[0].style, CODE);
+        assert_eq!(lines[3].spans[2].style, CODE);
+        let last = &lines[5].spans;
+        assert!(last[0].style.add_modifier.contains(Modifier::BOLD));
+        assert!(!last[1].style.add_modifier.contains(Modifier::BOLD));
+        assert!(last[2].style.add_modifier.contains(Modifier::UNDERLINED));
+    }
+}
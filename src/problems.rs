@@ -0,0 +1,115 @@
+use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Paragraph};
+use std::path::{Path, PathBuf};
+use ted_editor::diagnostic::Diagnostic;
+use ted_lsp::Lsp;
+
+/// What `ProblemsView::handle_event` asks the app to do.
+pub enum ProblemsEvent {
+    None,
+    Close,
+    /// Open the file and put the cursor on the problem.
+    Jump(PathBuf, (usize, usize)),
+}
+
+/// The `:problems` list of everything the language servers reported, worst
+/// first.
+#[derive(Debug, Default)]
+pub struct ProblemsView {
+    selected: usize,
+}
+
+impl ProblemsView {
+    pub fn problems(lsp: &Lsp) -> Vec<(&Path, &Diagnostic)> {
+        let mut problems: Vec<_> = lsp.all_diagnostics().collect();
+        problems.sort_by(|(a_path, a), (b_path, b)| {
+            b.severity
+                .cmp(&a.severity)
+                .then(a_path.cmp(b_path))
+                .then(a.start.cmp(&b.start))
+        });
+        problems
+    }
+
+    pub fn handle_event(&mut self, event: &Event, lsp: &Lsp) -> ProblemsEvent {
+        let Event::Key(key) = event else {
+            return ProblemsEvent::None;
+        };
+        if key.kind != KeyEventKind::Press {
+            return ProblemsEvent::None;
+        }
+        let problems = Self::problems(lsp);
+        self.selected = self.selected.min(problems.len().saturating_sub(1));
+        match key.code {
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.selected =
+                    (self.selected + 1).min(problems.len().saturating_sub(1))
+            }
+            KeyCode::Enter => {
+                return match problems.get(self.selected) {
+                    Some((path, diagnostic)) => ProblemsEvent::Jump(
+                        path.to_path_buf(),
+                        diagnostic.start,
+                    ),
+                    None => ProblemsEvent::Close,
+                };
+            }
+            KeyCode::Char('q') => return ProblemsEvent::Close,
+            _ => {}
+        }
+        ProblemsEvent::None
+    }
+
+    /// Draws the list, with paths relative to `workspace`.
+    pub fn draw(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        lsp: &Lsp,
+        workspace: &Path,
+    ) {
+        let problems = Self::problems(lsp);
+        let block =
+            Block::bordered().title(format!("problems ({})", problems.len()));
+        let rows = (block.inner(area).height as usize).max(1);
+        let selected = self.selected.min(problems.len().saturating_sub(1));
+        let start = (selected + 1).saturating_sub(rows);
+        let lines: Vec<Line> = problems
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(rows)
+            .map(|(idx, (path, diagnostic))| {
+                let path = path.strip_prefix(workspace).unwrap_or(path);
+                let (row, col) = diagnostic.start;
+                let message =
+                    diagnostic.message.lines().next().unwrap_or_default();
+                let source = diagnostic
+                    .source
+                    .as_deref()
+                    .map(|s| format!(" [{s}]"))
+                    .unwrap_or_default();
+                let line = Line::from(vec![
+                    Span::styled(
+                        format!("{} ", diagnostic.severity.sign()),
+                        Style::new().fg(diagnostic.severity.color()),
+                    ),
+                    Span::styled(
+                        format!("{}:{}:{} ", path.display(), row + 1, col + 1),
+                        Style::new().fg(Color::Gray),
+                    ),
+                    Span::raw(format!("{message}{source}")),
+                ]);
+                match idx == selected {
+                    true => line.style(Style::new().bg(Color::DarkGray)),
+                    false => line,
+                }
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
@@ -0,0 +1,36 @@
+use std::path::{Path, PathBuf};
+use ted_command_line::Prompt;
+
+/// A file whose swap file needs an answer before this ted takes it over.
+pub enum SwapNotice {
+    InUse { path: PathBuf, pid: u32 },
+    Recover { path: PathBuf, lines: Vec<String> },
+}
+
+impl SwapNotice {
+    pub fn path(&self) -> &Path {
+        match self {
+            SwapNotice::InUse { path, .. }
+            | SwapNotice::Recover { path, .. } => path,
+        }
+    }
+
+    pub fn prompt(&self) -> Prompt {
+        let name = self.path().display();
+        match self {
+            SwapNotice::InUse { pid, .. } => Prompt::new(format!(
+                "{name} is already open in another ted (pid {pid}); \
+                 edits here won't be protected by a swap file"
+            ))
+            .choice('o', "ok")
+            .cancel("later"),
+            SwapNotice::Recover { .. } => {
+                Prompt::new(format!("found unsaved edits to {name}"))
+                    .choice('r', "recover")
+                    .choice('d', "diff")
+                    .choice('x', "discard")
+                    .cancel("later")
+            }
+        }
+    }
+}
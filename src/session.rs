@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+use ted_common::state;
+use ted_editor::snapshot::BufferSnapshot;
+use ted_fs_explorer::fs_explorer::FsExplorerSession;
+use ted_layout::AsideSizes;
+use ted_layout::split_tree::SplitLayout;
+
+/// The `[session]` section of `config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// Quit without asking about unsaved changes, keeping them in the
+    /// session instead, unnamed buffers included.
+    pub hot_exit: bool,
+}
+
+/// Everything restored when ted is started again in the same workspace.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub active_buffer: usize,
+    pub active_view: usize,
+    pub buffers: Vec<BufferSnapshot>,
+    /// Buffer index shown by each view.
+    pub views: Option<SplitLayout<usize>>,
+    pub asides: Option<AsideSizes>,
+    pub explorer: FsExplorerSession,
+}
+
+impl Session {
+    pub fn load(workspace: &Path) -> Self {
+        state::load(&file_name(workspace))
+    }
+
+    pub fn save(&self, workspace: &Path) -> io::Result<()> {
+        state::save(&file_name(workspace), self)
+    }
+}
+
+/// One file per workspace under `sessions/`, named after its path.
+fn file_name(workspace: &Path) -> String {
+    format!("sessions/{}.toml", state::escape_path(workspace))
+}
+
+/// Rewrites the buffer index of every view, e.g. after buffers were
+/// skipped while saving or restoring.
+pub fn map_views(
+    layout: SplitLayout<usize>,
+    map: &impl Fn(usize) -> usize,
+) -> SplitLayout<usize> {
+    match layout {
+        SplitLayout::View(buffer) => SplitLayout::View(map(buffer)),
+        SplitLayout::Split { direction, weights, children } => {
+            SplitLayout::Split {
+                direction,
+                weights,
+                children: children
+                    .into_iter()
+                    .map(|c| map_views(c, map))
+                    .collect(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use ted_layout::split_tree::SplitDirection;
+
+    #[test]
+    fn round_trips_through_toml() {
+        let session = Session {
+            active_buffer: 1,
+            active_view: 1,
+            buffers: vec![
+                BufferSnapshot {
+                    path: Some(PathBuf::from("src/main.rs")),
+                    cursor: (3, 4),
+                    lines: None,
+                },
+                BufferSnapshot {
+                    path: None,
+                    cursor: (0, 0),
+                    lines: Some(vec!["scratch".to_string()]),
+                },
+            ],
+            views: Some(SplitLayout::Split {
+                direction: SplitDirection::Vertical,
+                weights: vec![2, 1],
+                children: vec![SplitLayout::View(0), SplitLayout::View(1)],
+            }),
+            asides: Some(AsideSizes { left: 32, right: 0 }),
+            explorer: FsExplorerSession {
+                opened: vec![vec!["/w".to_string()]],
+                selected: vec!["/w".to_string(), "/w/src".to_string()],
+            },
+        };
+        let text = toml::to_string(&session).unwrap();
+        let restored: Session = toml::from_str(&text).unwrap();
+        assert_eq!(restored.views, session.views);
+        assert_eq!(restored.buffers, session.buffers);
+        assert_eq!(restored.explorer, session.explorer);
+        assert_eq!(file_name(Path::new("/w/ted")), "sessions/%w%ted.toml");
+    }
+}
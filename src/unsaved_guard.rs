@@ -0,0 +1,148 @@
+use ted_command_line::Prompt;
+use ted_editor::buffer_list::BufferList;
+
+/// What the guard lets through once every modified buffer is dealt with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuardTarget {
+    Quit,
+    CloseBuffer(usize),
+}
+
+/// Asks what to do with modified buffers before quitting or closing them:
+/// save all, discard all, or go through them one by one.
+pub struct UnsavedGuard {
+    pub target: GuardTarget,
+    /// Buffer indices still to be dealt with.
+    dirty: Vec<usize>,
+    reviewing: bool,
+    error: Option<String>,
+}
+
+impl UnsavedGuard {
+    /// Returns `None` when nothing in `buffers` would be lost.
+    pub fn new(target: GuardTarget, buffers: &BufferList) -> Option<Self> {
+        let dirty: Vec<usize> = match target {
+            GuardTarget::Quit => (0..buffers.len()).collect(),
+            GuardTarget::CloseBuffer(idx) => vec![idx],
+        };
+        let dirty: Vec<usize> = dirty
+            .into_iter()
+            .filter(|b| buffers.get(*b).is_some_and(|b| b.is_dirty()))
+            .collect();
+        (!dirty.is_empty()).then_some(Self {
+            target,
+            dirty,
+            reviewing: false,
+            error: None,
+        })
+    }
+
+    /// The buffer being asked about while reviewing one by one.
+    pub fn current(&self) -> Option<usize> {
+        self.reviewing.then(|| self.dirty.first().copied()).flatten()
+    }
+
+    pub fn prompt(&self, buffers: &BufferList) -> Prompt {
+        let title = |b: usize| buffers.get(b).map(|b| b.title());
+        if let Some(current) = self.current() {
+            let title = title(current).unwrap_or_default();
+            let named =
+                buffers.get(current).is_some_and(|b| b.path().is_some());
+            let message = match &self.error {
+                Some(error) => error.clone(),
+                None => format!("save changes to {title}?"),
+            };
+            let prompt = Prompt::new(message);
+            let prompt = match named {
+                true => prompt.choice('y', "save"),
+                false => prompt,
+            };
+            return prompt.choice('n', "discard");
+        }
+        let titles: Vec<String> =
+            self.dirty.iter().filter_map(|b| title(*b)).collect();
+        let message = match &self.error {
+            Some(error) => error.clone(),
+            None => format!("unsaved changes in {}", titles.join(", ")),
+        };
+        Prompt::new(message)
+            .choice('s', "save all")
+            .choice('d', "discard all")
+            .choice('r', "review each")
+    }
+
+    /// Saves every named buffer. Unnamed ones can't be saved, so whatever
+    /// is left over is reviewed one by one. Returns `true` when done.
+    pub fn save_all(&mut self, buffers: &mut BufferList) -> bool {
+        let mut left = vec![];
+        self.error = None;
+        for idx in std::mem::take(&mut self.dirty) {
+            match buffers.get_mut(idx) {
+                Some(buffer) if buffer.path().is_some() => {
+                    if let Err(e) = buffer.save() {
+                        self.error = Some(save_error(&buffer.title(), e));
+                        left.push(idx);
+                    }
+                }
+                Some(_) => left.push(idx),
+                None => {}
+            }
+        }
+        self.dirty = left;
+        self.reviewing = self.error.is_none();
+        self.dirty.is_empty()
+    }
+
+    pub fn discard_all(&mut self) -> bool {
+        self.dirty.clear();
+        true
+    }
+
+    pub fn review(&mut self) {
+        self.reviewing = true;
+        self.error = None;
+    }
+
+    /// Saves or discards the reviewed buffer and moves on. Returns `true`
+    /// once there is nothing left to review.
+    pub fn answer(&mut self, save: bool, buffers: &mut BufferList) -> bool {
+        let Some(current) = self.current() else {
+            return self.dirty.is_empty();
+        };
+        if save
+            && let Some(buffer) = buffers.get_mut(current)
+            && let Err(e) = buffer.save()
+        {
+            self.error = Some(save_error(&buffer.title(), e));
+            return false;
+        }
+        self.error = None;
+        self.dirty.remove(0);
+        self.dirty.is_empty()
+    }
+}
+
+fn save_error(title: &str, error: std::io::Error) -> String {
+    format!("could not save {title}: {error}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ted_editor::vim_editor::vim_editor::VimEditor;
+
+    #[test]
+    fn reviews_what_cannot_be_saved() {
+        let mut buffers = BufferList::new();
+        assert!(UnsavedGuard::new(GuardTarget::Quit, &buffers).is_none());
+        buffers.push(VimEditor::scratch(&["one".to_string()]));
+        buffers.push(VimEditor::scratch(&["two".to_string()]));
+        let mut guard = UnsavedGuard::new(GuardTarget::Quit, &buffers).unwrap();
+        assert_eq!(guard.current(), None);
+        assert!(!guard.save_all(&mut buffers));
+        assert_eq!(guard.current(), Some(0));
+        assert!(!guard.answer(false, &mut buffers));
+        assert_eq!(guard.current(), Some(1));
+        assert!(guard.answer(false, &mut buffers));
+    }
+}
@@ -0,0 +1,136 @@
+use ratatui::prelude::*;
+use ted_editor::buffer_list::BufferList;
+use ted_layout::split_tree::{SplitDirection, SplitLayout, SplitTree};
+
+/// Editor views in the content area and the buffer each one shows. The
+/// active view is the one whose buffer is `BufferList::active`.
+pub struct Views {
+    tree: SplitTree<usize>,
+    active: usize,
+}
+
+impl Views {
+    pub fn new(buffers: &BufferList) -> Self {
+        Self { tree: SplitTree::new(buffers.active_index()), active: 0 }
+    }
+
+    /// Rebuilds views from a saved layout; views are numbered in leaf order.
+    /// Falls back to a single view of the active buffer if `layout` is empty.
+    pub fn restore(
+        layout: SplitLayout<usize>,
+        active: usize,
+        buffers: &mut BufferList,
+    ) -> Self {
+        let mut views = match SplitTree::from_layout(layout) {
+            Some(tree) => Self { tree, active: 0 },
+            None => Self::new(buffers),
+        };
+        views.activate(active.min(views.count() - 1), buffers);
+        views
+    }
+
+    /// The layout plus the position of the active view in leaf order, as
+    /// `restore` expects it.
+    pub fn to_layout(&self) -> (SplitLayout<usize>, usize) {
+        let views = self.tree.views();
+        let active = views.iter().position(|(id, _)| *id == self.active);
+        (self.tree.to_layout(), active.unwrap_or(0))
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn buffer_of(&self, view: usize) -> Option<usize> {
+        self.tree.get(view).copied()
+    }
+
+    pub fn layout(&self, area: Rect) -> Vec<(usize, Rect)> {
+        self.tree.layout(area)
+    }
+
+    pub fn count(&self) -> usize {
+        self.tree.views().len()
+    }
+
+    /// Makes `view` active and switches the buffer list over to its buffer.
+    pub fn activate(&mut self, view: usize, buffers: &mut BufferList) {
+        if let Some(buffer) = self.buffer_of(view) {
+            self.active = view;
+            buffers.select(buffer);
+        }
+    }
+
+    /// Points the active view at whatever buffer is active now, e.g. after
+    /// switching tabs.
+    pub fn follow_active_buffer(&mut self, buffers: &BufferList) {
+        if let Some(buffer) = self.tree.get_mut(self.active) {
+            *buffer = buffers.active_index();
+        }
+    }
+
+    pub fn split(&mut self, direction: SplitDirection) -> Option<usize> {
+        self.tree.split(self.active, direction)
+    }
+
+    /// Closes the active view, returning the view that replaces it, or
+    /// `None` if it was the last one.
+    pub fn close(&mut self, buffers: &mut BufferList) -> Option<usize> {
+        let next = self.tree.close(self.active)?;
+        self.activate(next, buffers);
+        Some(next)
+    }
+
+    pub fn only(&mut self) {
+        self.tree.only(self.active);
+    }
+
+    pub fn equalize(&mut self) {
+        self.tree.equalize();
+    }
+
+    /// Grows the active view by `delta` cells along `direction`.
+    pub fn resize(
+        &mut self,
+        area: Rect,
+        direction: SplitDirection,
+        delta: i32,
+    ) {
+        self.tree.resize_view(area, self.active, direction, delta);
+    }
+
+    pub fn splits_mut(&mut self) -> &mut SplitTree<usize> {
+        &mut self.tree
+    }
+
+    pub fn rotate(&mut self) {
+        self.tree.rotate(self.active);
+    }
+
+    /// Keeps buffer indices valid after `BufferList::close(closed)`.
+    pub fn buffer_closed(&mut self, closed: usize, buffers: &BufferList) {
+        let replacement = buffers.active_index();
+        for buffer in self.tree.values_mut() {
+            if *buffer == closed {
+                *buffer = replacement;
+            } else if *buffer > closed {
+                *buffer -= 1;
+            }
+        }
+    }
+
+    /// Keeps buffer indices valid after `BufferList::move_buffer(from, to)`.
+    pub fn buffer_moved(&mut self, from: usize, to: usize) {
+        for buffer in self.tree.values_mut() {
+            *buffer = if *buffer == from {
+                to
+            } else if from < *buffer && *buffer <= to {
+                *buffer - 1
+            } else if to <= *buffer && *buffer < from {
+                *buffer + 1
+            } else {
+                *buffer
+            };
+        }
+    }
+}